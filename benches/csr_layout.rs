@@ -0,0 +1,144 @@
+use std::{hint::black_box, time::Duration};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use optimizer::{
+    csr_graph::{CSRGraph, InputEdge, WeightedGraph},
+    cycle_finder::find_profitable_cycle_with_graph,
+};
+
+#[path = "common/mod.rs"]
+#[allow(dead_code)]
+mod common;
+
+use common::load_edges;
+
+const LAYOUT_EDGE_COUNT: usize = 50_000;
+const HOP_CAP: usize = 4;
+
+fn build_graph() -> CSRGraph {
+    let edges: Vec<InputEdge> = load_edges(LAYOUT_EDGE_COUNT)
+        .expect("Datasets required to run benchmarks")
+        .into_iter()
+        .map(|edge| (edge.from, edge.to, edge.rate))
+        .collect();
+    let node_count = edges
+        .iter()
+        .map(|&(from, to, _)| from.max(to))
+        .max()
+        .map_or(0, |highest| highest + 1);
+    CSRGraph::from_edges(node_count, edges)
+}
+
+fn csr_layout_group(c: &mut Criterion) {
+    let indirect = build_graph();
+    let dense = indirect.clone().with_dense_adjacency();
+
+    let mut group = c.benchmark_group("csr_layout");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Elements(indirect.edge_count() as u64));
+
+    for (label, graph) in [("indirect", &indirect), ("dense", &dense)] {
+        group.bench_with_input(BenchmarkId::new("find_cycle", label), graph, |b, graph| {
+            b.iter(|| black_box(find_profitable_cycle_with_graph(graph, HOP_CAP)));
+        });
+    }
+
+    group.finish();
+}
+
+/// The pre-SoA storage: CSR rows over `(from, to, rate)` tuples, so every neighbor visit pulls a
+/// whole 24-byte edge just to read `to`.
+struct AosGraph {
+    edge_offsets: Vec<usize>,
+    edge_indices: Vec<usize>,
+    edges: Vec<InputEdge>,
+    weights: Vec<f64>,
+}
+
+impl AosGraph {
+    fn mirror(graph: &CSRGraph) -> Self {
+        let mut edge_offsets = vec![0];
+        let mut edge_indices = Vec::with_capacity(graph.edge_count());
+        for node in 0..graph.node_count() {
+            edge_indices.extend(graph.neighbors(node).map(|(edge_index, _, _)| edge_index));
+            edge_offsets.push(edge_indices.len());
+        }
+        let edges = (0..graph.edge_index_bound())
+            .map(|edge_index| graph.edge(edge_index))
+            .collect();
+        Self {
+            edge_offsets,
+            edge_indices,
+            edges,
+            weights: graph.weights_in_neglog.clone(),
+        }
+    }
+}
+
+impl WeightedGraph for AosGraph {
+    fn node_count(&self) -> usize {
+        self.edge_offsets.len() - 1
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edge_indices.len()
+    }
+
+    fn edge(&self, edge_index: usize) -> InputEdge {
+        self.edges[edge_index]
+    }
+
+    fn weight(&self, edge_index: usize) -> f64 {
+        self.weights[edge_index]
+    }
+
+    fn for_each_neighbor<F>(&self, from_node: usize, mut f: F)
+    where
+        F: FnMut(usize, usize, f64),
+    {
+        let row =
+            &self.edge_indices[self.edge_offsets[from_node]..self.edge_offsets[from_node + 1]];
+        for &edge_index in row {
+            let (_, to_node, _) = self.edges[edge_index];
+            f(edge_index, to_node, self.weights[edge_index]);
+        }
+    }
+}
+
+/// One Bellman-Ford hop from every node, the loop the cycle finder spends its time in.
+fn relax_all<G: WeightedGraph>(graph: &G, best_previous: &[f64], best_current: &mut [f64]) {
+    best_current.fill(f64::INFINITY);
+    for (from_node, &distance) in best_previous.iter().enumerate() {
+        graph.for_each_neighbor(from_node, |_, to_node, w| {
+            let d = distance + w;
+            if d < best_current[to_node] {
+                best_current[to_node] = d;
+            }
+        });
+    }
+}
+
+fn edge_storage_group(c: &mut Criterion) {
+    let soa = build_graph();
+    let aos = AosGraph::mirror(&soa);
+    let n = soa.node_count();
+    let best_previous = vec![0.0; n];
+
+    let mut group = c.benchmark_group("edge_storage");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(soa.edge_count() as u64));
+
+    let mut best_current = vec![f64::INFINITY; n];
+    group.bench_function(BenchmarkId::new("relax_hop", "aos"), |b| {
+        b.iter(|| relax_all(&aos, &best_previous, black_box(&mut best_current)));
+    });
+    group.bench_function(BenchmarkId::new("relax_hop", "soa"), |b| {
+        b.iter(|| relax_all(&soa, &best_previous, black_box(&mut best_current)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, csr_layout_group, edge_storage_group);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use std::{hint::black_box, time::Duration};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use optimizer::{
+    csr_graph::{CSRGraph, InputEdge},
+    cycle_finder::{find_profitable_cycle_spfa, find_profitable_cycle_with_graph},
+};
+
+#[path = "common/mod.rs"]
+#[allow(dead_code)]
+mod common;
+
+use common::load_edges;
+
+const DETECTION_EDGE_COUNT: usize = 50_000;
+const HOP_CAP: usize = 4;
+
+fn build_graph() -> CSRGraph {
+    let edges: Vec<InputEdge> = load_edges(DETECTION_EDGE_COUNT)
+        .expect("Datasets required to run benchmarks")
+        .into_iter()
+        .map(|edge| (edge.from, edge.to, edge.rate))
+        .collect();
+    let node_count = edges
+        .iter()
+        .map(|&(from, to, _)| from.max(to))
+        .max()
+        .map_or(0, |highest| highest + 1);
+    CSRGraph::from_edges(node_count, edges)
+}
+
+fn cycle_detection_group(c: &mut Criterion) {
+    let graph = build_graph();
+
+    let mut group = c.benchmark_group("cycle_detection");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Elements(graph.edge_count() as u64));
+
+    group.bench_with_input(
+        BenchmarkId::new("hop_capped", HOP_CAP),
+        &graph,
+        |b, graph| {
+            b.iter(|| black_box(find_profitable_cycle_with_graph(graph, HOP_CAP)));
+        },
+    );
+    group.bench_with_input(BenchmarkId::new("spfa", "uncapped"), &graph, |b, graph| {
+        b.iter(|| black_box(find_profitable_cycle_spfa(graph)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, cycle_detection_group);
+criterion_main!(benches);
@@ -49,7 +49,6 @@ fn pipeline_hop_cap_group(c: &mut Criterion) {
             |b, &_hop| {
                 let dataset = Arc::clone(&dataset);
                 let config = Arc::clone(&config);
-                let expected_updates = expected_updates;
                 b.to_async(&runtime).iter(move || {
                     let dataset = Arc::clone(&dataset);
                     let config = Arc::clone(&config);
@@ -109,7 +108,6 @@ fn pipeline_throughput_group(c: &mut Criterion) {
         group.bench_function(BenchmarkId::new("pipeline", config_id), |b| {
             let dataset = Arc::clone(&dataset);
             let config = Arc::clone(&config);
-            let expected_updates = expected_updates;
             b.to_async(&runtime).iter(move || {
                 let dataset = Arc::clone(&dataset);
                 let config = Arc::clone(&config);
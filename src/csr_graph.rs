@@ -1,25 +1,81 @@
-use std::fmt;
+use crate::{
+    dataset::{Dataset, Edge, BIDIRECTIONAL_EDGE_KIND, REVERSED_EDGE_KIND},
+    edge_layouts::EdgeSoA,
+    error::PipelineError,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    num::TryFromIntError,
+};
 
 /// Edge list item: (from, to, rate)
 pub type InputEdge = (usize, usize, f64);
 
 /// Compact sparse-row adjacency encoding used across the crate.
 ///
-/// Owns the edge list and keeps two helper arrays:
+/// Owns the edge list, stored as parallel `from` / `to` / `rate` arrays ([`EdgeSoA`]) so hot
+/// loops that only need destinations stream through `to` alone, and keeps two helper arrays:
 /// - `edge_offsets` marks, for every node, the start/end positions of its outgoing edges inside
 ///   `edge_indices`.
 /// - `edge_indices` stores the indices of edges (relative to the original slice) laid out
 ///   contiguously per node.
 ///
 /// `weights_in_neglog` caches the `-ln(rate)` value per edge which is the working cost for
-/// arbitrage detection.
+/// arbitrage detection. Edges may also carry a fee in basis points (see
+/// [`CSRGraph::update_fee`]); the cached weight is then that of the net rate
+/// `rate * (1 - fee_bps / 10_000)`.
+///
+/// Edges can be appended with [`CSRGraph::add_edge`] and tombstoned with
+/// [`CSRGraph::remove_edge`]: a removed edge keeps its index (so indices held elsewhere stay
+/// valid) but is dropped from `edge_indices`, its weight becomes `+inf`, and it no longer shows
+/// up in `neighbors` or `edge_count`.
+///
+/// Each edge also carries the `pool_id` and `kind` of the dataset row it came from (see
+/// [`CSRGraph::edge_pool`]), so cycle legs can be traced back to pools; graphs built from bare
+/// triples report pool 0, kind 0.
+///
+/// Every successful [`CSRGraph::update_rate`] advances a graph-wide tick and stamps the edge
+/// with it, so [`CSRGraph::stale_edges`] can report pools whose rates stopped moving.
+///
+/// Optionally (see [`CSRGraph::with_dense_adjacency`]) the graph also keeps a dense copy of
+/// destinations and weights laid out in CSR slot order, so traversal reads contiguous memory
+/// instead of hopping through `edge_indices` → `edges`.
 #[derive(Clone)]
 pub struct CSRGraph {
     edge_offsets: Vec<usize>,
     edge_indices: Vec<usize>,
-    edges: Vec<InputEdge>,
+    edges: EdgeSoA,
     pub weights_in_neglog: Vec<f64>,
+    fees_bps: Vec<f64>,
+    removed: Vec<bool>,
+    pool_ids: Vec<u64>,
+    kinds: Vec<u8>,
+    /// Tick at which each edge's rate was last written; 0 means never updated since build.
+    last_updated: Vec<u64>,
+    update_tick: u64,
     node_count: usize,
+    log_base: LogBase,
+    dense: Option<DenseAdjacency>,
+}
+
+/// Per-slot `(to, weight)` arrays mirroring `edge_indices`, plus the inverse slot lookup used to
+/// keep them in sync on rate updates.
+#[derive(Clone)]
+struct DenseAdjacency {
+    targets: Vec<usize>,
+    weights: Vec<f64>,
+    slot_by_edge: Vec<usize>,
+}
+
+/// Result of [`CSRGraph::prune_sinks`]: the reduced graph plus the maps back to the original.
+#[derive(Debug, Clone)]
+pub struct PrunedGraph {
+    pub graph: CSRGraph,
+    /// `new_node[old]` is the node's index in `graph`, or `None` if it was pruned.
+    pub new_node: Vec<Option<usize>>,
+    /// `original_edge[new]` is the edge index in the source graph.
+    pub original_edge: Vec<usize>,
 }
 
 impl fmt::Debug for CSRGraph {
@@ -27,22 +83,315 @@ impl fmt::Debug for CSRGraph {
         f.debug_struct("CSRGraph")
             .field("node_count", &self.node_count)
             .field("edge_count", &self.edge_count())
+            .field("dense_adjacency", &self.dense.is_some())
             .finish()
     }
 }
 
+/// Read-only adjacency interface the cycle finder runs against.
+///
+/// Implemented by the owning [`CSRGraph`] and by the borrowing [`CSRGraphView`], so searches
+/// don't care whether the graph sits behind a lock, is cloned, or is merely borrowed.
+pub trait WeightedGraph {
+    fn node_count(&self) -> usize;
+
+    fn edge_count(&self) -> usize;
+
+    /// `(from, to, rate)` for `edge_index`.
+    fn edge(&self, edge_index: usize) -> InputEdge;
+
+    /// Working `-log(rate)` cost for `edge_index`, in the base given by
+    /// [`WeightedGraph::log_base`].
+    fn weight(&self, edge_index: usize) -> f64;
+
+    /// Base of the logarithm behind [`WeightedGraph::weight`]; turn a summed cost back into a
+    /// rate product with [`LogBase::rate_for`].
+    #[inline]
+    fn log_base(&self) -> LogBase {
+        LogBase::E
+    }
+
+    /// Visit every `(edge_index, to, neg_log_weight)` leaving `from_node`.
+    fn for_each_neighbor<F>(&self, from_node: usize, f: F)
+    where
+        F: FnMut(usize, usize, f64);
+}
+
+/// Zero-copy borrow of a [`CSRGraph`]'s arrays for lock-free, single-threaded searches.
+#[derive(Debug, Clone, Copy)]
+pub struct CSRGraphView<'a> {
+    edge_offsets: &'a [usize],
+    edge_indices: &'a [usize],
+    edges: &'a EdgeSoA,
+    weights_in_neglog: &'a [f64],
+    log_base: LogBase,
+}
+
+impl<'a> CSRGraphView<'a> {
+    /// Borrow neighbors of `from_node` as (edge_index, to, neg_log_weight)
+    #[inline]
+    pub fn neighbors(&self, from_node: usize) -> impl Iterator<Item = (usize, usize, f64)> + 'a {
+        let start = self.edge_offsets[from_node];
+        let end = self.edge_offsets[from_node + 1];
+        let to = self.edges.to.as_slice();
+        let weights = self.weights_in_neglog;
+        self.edge_indices[start..end]
+            .iter()
+            .copied()
+            .map(move |edge_index| (edge_index, to[edge_index], weights[edge_index]))
+    }
+}
+
+impl WeightedGraph for CSRGraphView<'_> {
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.edge_offsets.len() - 1
+    }
+
+    #[inline]
+    fn edge_count(&self) -> usize {
+        self.edge_indices.len()
+    }
+
+    #[inline]
+    fn edge(&self, edge_index: usize) -> InputEdge {
+        edge_triple(self.edges, edge_index)
+    }
+
+    #[inline]
+    fn weight(&self, edge_index: usize) -> f64 {
+        self.weights_in_neglog[edge_index]
+    }
+
+    #[inline]
+    fn log_base(&self) -> LogBase {
+        self.log_base
+    }
+
+    #[inline]
+    fn for_each_neighbor<F>(&self, from_node: usize, mut f: F)
+    where
+        F: FnMut(usize, usize, f64),
+    {
+        for (edge_index, to_node, w) in self.neighbors(from_node) {
+            f(edge_index, to_node, w);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum UpdateError {
     IndexOutOfBounds(usize),
     InvalidRate(f64),
+    InvalidFee(f64),
+    EdgeRemoved(usize),
+}
+
+/// Which edge survives when several share the same `(from, to)` pair; see [`dedup_edges`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupPolicy {
+    /// Keep the first occurrence in input order.
+    KeepFirst,
+    /// Keep the highest rate, at the position of the pair's first occurrence.
+    KeepBest,
+}
+
+/// Collapse edges sharing a `(from, to)` pair down to one according to `policy`, preserving the
+/// order of first occurrences. Returns how many edges were removed.
+pub fn dedup_edges(edges: &mut Vec<InputEdge>, policy: DedupPolicy) -> usize {
+    let (kept, _) = dedup_edge_sources(edges, policy);
+    let removed = edges.len() - kept.len();
+    *edges = kept;
+    removed
+}
+
+/// [`dedup_edges`] that also reports, for every kept edge, the input index whose rate it kept.
+fn dedup_edge_sources(edges: &[InputEdge], policy: DedupPolicy) -> (Vec<InputEdge>, Vec<usize>) {
+    let mut slot_by_pair = HashMap::with_capacity(edges.len());
+    let mut kept: Vec<InputEdge> = Vec::with_capacity(edges.len());
+    let mut sources = Vec::with_capacity(edges.len());
+    for (index, &(from, to, rate)) in edges.iter().enumerate() {
+        match slot_by_pair.entry((from, to)) {
+            Entry::Vacant(entry) => {
+                entry.insert(kept.len());
+                kept.push((from, to, rate));
+                sources.push(index);
+            }
+            Entry::Occupied(entry) => {
+                let slot = *entry.get();
+                if policy == DedupPolicy::KeepBest && rate > kept[slot].2 {
+                    kept[slot].2 = rate;
+                    sources[slot] = index;
+                }
+            }
+        }
+    }
+    (kept, sources)
+}
+
+/// A dataset's validated edges with the pool metadata of each, ready for [`CSRGraph`].
+pub(crate) struct DatasetEdges {
+    pub edges: Vec<InputEdge>,
+    pub pool_ids: Vec<u64>,
+    pub kinds: Vec<u8>,
+    /// Highest node index + 1.
+    pub node_count: usize,
+}
+
+impl DatasetEdges {
+    /// [`dedup_edges`] in place, keeping each survivor's pool metadata with its rate; with
+    /// [`DedupPolicy::KeepBest`] that is the pool quoting the best rate. Returns how many
+    /// edges were removed.
+    pub fn dedup(&mut self, policy: DedupPolicy) -> usize {
+        let (kept, sources) = dedup_edge_sources(&self.edges, policy);
+        let removed = self.edges.len() - kept.len();
+        self.edges = kept;
+        self.pool_ids = sources
+            .iter()
+            .map(|&source| self.pool_ids[source])
+            .collect();
+        self.kinds = sources.iter().map(|&source| self.kinds[source]).collect();
+        removed
+    }
+
+    pub fn into_graph(self) -> CSRGraph {
+        let mut graph = CSRGraph::from_edges(self.node_count, self.edges);
+        graph.pool_ids = self.pool_ids;
+        graph.kinds = self.kinds;
+        graph
+    }
+}
+
+/// Validated `(from, to, rate)` triples for `dataset` in edge order, with their pool ids and
+/// kinds. With `expand_bidirectional`, edges of kind [`BIDIRECTIONAL_EDGE_KIND`] are followed
+/// by their reverse at `1 / rate`, on the same pool and of kind [`REVERSED_EDGE_KIND`].
+pub(crate) fn dataset_input_edges(
+    dataset: &Dataset,
+    expand_bidirectional: bool,
+) -> Result<DatasetEdges, PipelineError> {
+    if dataset.edges.is_empty() {
+        return Err(PipelineError::EmptyDataset);
+    }
+
+    let mut edges = Vec::with_capacity(dataset.edges.len());
+    let mut pool_ids = Vec::with_capacity(dataset.edges.len());
+    let mut kinds = Vec::with_capacity(dataset.edges.len());
+    let mut highest_node_index = 0usize;
+    for edge in &dataset.edges {
+        let (from, to) = edge_endpoints(edge)?;
+        if !edge.rate.is_finite() || edge.rate <= 0.0 {
+            return Err(PipelineError::InvalidRate {
+                edge_id: edge.id,
+                rate: edge.rate,
+            });
+        }
+        edges.push((from, to, edge.rate));
+        pool_ids.push(edge.pool_id);
+        kinds.push(edge.kind);
+        if expand_bidirectional && edge.kind == BIDIRECTIONAL_EDGE_KIND {
+            edges.push((to, from, edge.rate.recip()));
+            pool_ids.push(edge.pool_id);
+            kinds.push(REVERSED_EDGE_KIND);
+        }
+        highest_node_index = highest_node_index.max(from.max(to));
+    }
+    Ok(DatasetEdges {
+        edges,
+        pool_ids,
+        kinds,
+        node_count: highest_node_index + 1,
+    })
+}
+
+/// `edge`'s endpoints as node indices.
+fn edge_endpoints(edge: &Edge) -> Result<(usize, usize), PipelineError> {
+    endpoint_indices(
+        edge.id,
+        usize::try_from(edge.from),
+        usize::try_from(edge.to),
+    )
+}
+
+/// Attribute failed endpoint conversions to `edge_id`. Split out of [`edge_endpoints`] because
+/// every `u64` fits a 64-bit `usize`, so tests feed it failed conversions directly.
+fn endpoint_indices(
+    edge_id: u64,
+    from: Result<usize, TryFromIntError>,
+    to: Result<usize, TryFromIntError>,
+) -> Result<(usize, usize), PipelineError> {
+    let from = from.map_err(|source| PipelineError::FromIndex { edge_id, source })?;
+    let to = to.map_err(|source| PipelineError::ToIndex { edge_id, source })?;
+    Ok((from, to))
+}
+
+/// Base of the logarithm used for edge costs. Whether a cycle is profitable (negative summed
+/// cost) does not depend on it; only the magnitude of costs does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogBase {
+    #[default]
+    E,
+    Two,
+    Ten,
+}
+
+impl LogBase {
+    /// `ln(base)`, the factor between natural-log costs and costs in this base.
+    #[inline]
+    pub fn ln(self) -> f64 {
+        match self {
+            LogBase::E => 1.0,
+            LogBase::Two => std::f64::consts::LN_2,
+            LogBase::Ten => std::f64::consts::LN_10,
+        }
+    }
+
+    /// `-log_base(rate)`.
+    #[inline]
+    pub fn cost_for(self, rate: f64) -> f64 {
+        -rate.ln() / self.ln()
+    }
+
+    /// Rate product for a summed cost, i.e. `base^(-cost)`.
+    #[inline]
+    pub fn rate_for(self, cost: f64) -> f64 {
+        (-cost * self.ln()).exp()
+    }
+}
+
+/// Fees are basis points of the traded amount, so 10 000 bps takes everything.
+pub const MAX_FEE_BPS: f64 = 10_000.0;
+
+#[inline]
+fn edge_triple(edges: &EdgeSoA, edge_index: usize) -> InputEdge {
+    (
+        edges.from[edge_index],
+        edges.to[edge_index],
+        edges.rate[edge_index],
+    )
+}
+
+#[inline]
+fn net_weight(rate: f64, fee_bps: f64, log_base: LogBase) -> f64 {
+    log_base.cost_for(rate * (1.0 - fee_bps / MAX_FEE_BPS))
 }
 
 impl CSRGraph {
     /// Build a CSR graph from owned `edges` with `(from, to, rate)` triples.
     pub fn from_edges(node_count: usize, edges: Vec<InputEdge>) -> Self {
+        let mut soa = EdgeSoA::with_capacity(edges.len());
+        for (from, to, rate) in edges {
+            soa.push(from, to, rate);
+        }
+        Self::from_soa(node_count, soa)
+    }
+
+    /// Build a CSR graph straight from column-oriented edges; edge `i` is
+    /// `(edges.from[i], edges.to[i], edges.rate[i])`, exactly as with [`CSRGraph::from_edges`].
+    pub fn from_soa(node_count: usize, edges: EdgeSoA) -> Self {
+        debug_assert!(edges.from.len() == edges.to.len() && edges.to.len() == edges.rate.len());
         let mut outgoing_edges_count_by_node = vec![0usize; node_count];
-        for (node, _, _) in &edges {
-            outgoing_edges_count_by_node[*node] += 1;
+        for &node in &edges.from {
+            outgoing_edges_count_by_node[node] += 1;
         }
 
         let mut edge_offsets = Vec::with_capacity(node_count + 1);
@@ -57,7 +406,7 @@ impl CSRGraph {
         let mut weights_in_neglog = Vec::with_capacity(edges.len());
 
         let mut offsets_so_far = vec![0usize; node_count];
-        for (edge_index, (from_node, _to, rate)) in edges.iter().enumerate() {
+        for (edge_index, (from_node, rate)) in edges.from.iter().zip(&edges.rate).enumerate() {
             let slot = edge_offsets[*from_node] + offsets_so_far[*from_node];
             edge_indices[slot] = edge_index;
             offsets_so_far[*from_node] += 1;
@@ -72,22 +421,157 @@ impl CSRGraph {
         Self {
             edge_offsets,
             edge_indices,
+            removed: vec![false; edges.len()],
+            fees_bps: vec![0.0; edges.len()],
+            pool_ids: vec![0; edges.len()],
+            kinds: vec![0; edges.len()],
+            last_updated: vec![0; edges.len()],
+            update_tick: 0,
             edges,
             weights_in_neglog,
             node_count,
+            log_base: LogBase::E,
+            dense: None,
+        }
+    }
+
+    /// Like [`CSRGraph::from_edges`], but for sparse node ids: the ids the edges reference are
+    /// compacted into `0..k` in ascending order, so memory scales with the nodes actually used
+    /// rather than the largest id. Returns the graph plus `remap`, where `remap[dense]` is the
+    /// original id; cycle vertices come back in dense ids and map back through it. Edge indices
+    /// are unchanged.
+    pub fn from_edges_remapped(mut edges: Vec<InputEdge>) -> (Self, Vec<usize>) {
+        let mut remap: Vec<usize> = edges.iter().flat_map(|&(from, to, _)| [from, to]).collect();
+        remap.sort_unstable();
+        remap.dedup();
+        let dense = |node: usize| {
+            remap
+                .binary_search(&node)
+                .expect("every endpoint is in remap")
+        };
+        for (from, to, _) in &mut edges {
+            *from = dense(*from);
+            *to = dense(*to);
+        }
+        (Self::from_edges(remap.len(), edges), remap)
+    }
+
+    /// Like [`CSRGraph::from_edges`], but costs are `-log_base(rate)`. Finders work unchanged
+    /// and report `neg_log_sum` in this base, with `profit = base^(-neg_log_sum)`.
+    pub fn from_edges_with_base(node_count: usize, edges: Vec<InputEdge>, base: LogBase) -> Self {
+        let mut graph = Self::from_edges(node_count, edges);
+        graph.set_log_base(base);
+        graph
+    }
+
+    /// Base of the logarithm behind `weights_in_neglog`.
+    #[inline]
+    pub fn log_base(&self) -> LogBase {
+        self.log_base
+    }
+
+    /// Switch the cost base and recompute every live edge's weight.
+    fn set_log_base(&mut self, base: LogBase) {
+        if self.log_base == base {
+            return;
+        }
+        self.log_base = base;
+        for edge_index in 0..self.edges.len() {
+            if !self.removed[edge_index] {
+                self.sync_weight(edge_index);
+            }
+        }
+    }
+
+    /// Like [`CSRGraph::from_edges`], but parallel edges sharing a `(from, to)` pair collapse to
+    /// the one with the highest rate (see [`dedup_edges`] with [`DedupPolicy::KeepBest`]). Edge
+    /// indices then refer to the collapsed list, not the input, so pool identity is lost;
+    /// `from_edges` stays the default for anything that maps results back to pools.
+    pub fn from_edges_best_rate(node_count: usize, mut edges: Vec<InputEdge>) -> Self {
+        dedup_edges(&mut edges, DedupPolicy::KeepBest);
+        Self::from_edges(node_count, edges)
+    }
+
+    /// Build the graph for `dataset` with the same checks the pipeline runs: the dataset must
+    /// have edges, every `from`/`to` must fit `usize`, and every rate must be finite and
+    /// positive. Returns the graph plus the baseline rate of each edge, by edge index. Edge
+    /// `i` is dataset row `i` and keeps its `pool_id` and `kind`.
+    pub fn from_dataset(dataset: &Dataset) -> Result<(CSRGraph, Vec<f64>), PipelineError> {
+        let edges = dataset_input_edges(dataset, false)?;
+        let baseline_rates = edges.edges.iter().map(|&(_, _, rate)| rate).collect();
+        Ok((edges.into_graph(), baseline_rates))
+    }
+
+    /// Materialise the dense adjacency layout, trading one extra `(usize, f64)` per edge for
+    /// contiguous neighbor reads. Rate updates keep both layouts in sync; writing
+    /// `weights_in_neglog` directly bypasses the dense copy.
+    pub fn with_dense_adjacency(mut self) -> Self {
+        self.build_dense_adjacency();
+        self
+    }
+
+    fn build_dense_adjacency(&mut self) {
+        let mut targets = Vec::with_capacity(self.edge_indices.len());
+        let mut weights = Vec::with_capacity(self.edge_indices.len());
+        let mut slot_by_edge = vec![0usize; self.edges.len()];
+        for (slot, &edge_index) in self.edge_indices.iter().enumerate() {
+            targets.push(self.edges.to[edge_index]);
+            weights.push(self.weights_in_neglog[edge_index]);
+            slot_by_edge[edge_index] = slot;
+        }
+        self.dense = Some(DenseAdjacency {
+            targets,
+            weights,
+            slot_by_edge,
+        });
+    }
+
+    /// Structural edits shift CSR slots, so rebuild the dense copy wholesale if present.
+    fn refresh_dense_adjacency(&mut self) {
+        if self.dense.is_some() {
+            self.build_dense_adjacency();
+        }
+    }
+
+    /// Borrow the CSR arrays without cloning; see [`CSRGraphView`].
+    #[inline]
+    pub fn view(&self) -> CSRGraphView<'_> {
+        CSRGraphView {
+            edge_offsets: &self.edge_offsets,
+            edge_indices: &self.edge_indices,
+            edges: &self.edges,
+            weights_in_neglog: &self.weights_in_neglog,
+            log_base: self.log_base,
         }
     }
 
+    #[inline]
+    pub fn has_dense_adjacency(&self) -> bool {
+        self.dense.is_some()
+    }
+
     #[inline]
     pub fn node_count(&self) -> usize {
         self.node_count
     }
 
+    /// Number of live edges (tombstoned edges excluded).
     #[inline]
     pub fn edge_count(&self) -> usize {
+        self.edge_indices.len()
+    }
+
+    /// Exclusive upper bound on edge indices, including tombstoned edges.
+    #[inline]
+    pub fn edge_index_bound(&self) -> usize {
         self.edges.len()
     }
 
+    #[inline]
+    pub fn is_removed(&self, edge_index: usize) -> bool {
+        self.removed[edge_index]
+    }
+
     /// Borrow neighbors of `from_node` as (edge_index, to, neg_log_weight)
     #[inline]
     pub fn neighbors(&self, from_node: usize) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
@@ -97,27 +581,69 @@ impl CSRGraph {
             .iter()
             .copied()
             .map(move |edge_index| {
-                let (_, to_node, _rate) = self.edges[edge_index];
-                (edge_index, to_node, self.weights_in_neglog[edge_index])
+                (
+                    edge_index,
+                    self.edges.to[edge_index],
+                    self.weights_in_neglog[edge_index],
+                )
             })
     }
 
+    /// Indices of the live edges `from -> to`, in CSR row order (insertion order, with edges
+    /// added later at the end). Parallel pools yield several; an unknown `from` yields none.
+    pub fn edges_between(&self, from: usize, to: usize) -> Vec<usize> {
+        if from >= self.node_count {
+            return Vec::new();
+        }
+        self.neighbors(from)
+            .filter(|&(_, neighbor, _)| neighbor == to)
+            .map(|(edge_index, _, _)| edge_index)
+            .collect()
+    }
+
+    /// Contiguous (edge_index, to, neg_log_weight) rows, available when the dense layout is built.
+    #[inline]
+    pub fn dense_neighbors(
+        &self,
+        from_node: usize,
+    ) -> Option<impl Iterator<Item = (usize, usize, f64)> + '_> {
+        let dense = self.dense.as_ref()?;
+        let start = self.edge_offsets[from_node];
+        let end = self.edge_offsets[from_node + 1];
+        Some(
+            self.edge_indices[start..end]
+                .iter()
+                .copied()
+                .zip(dense.targets[start..end].iter().copied())
+                .zip(dense.weights[start..end].iter().copied())
+                .map(|((edge_index, to_node), w)| (edge_index, to_node, w)),
+        )
+    }
+
+    /// Full `(from, to, rate)` triple in one lookup; panics on an out-of-range index.
+    #[inline]
+    pub fn edge(&self, edge_index: usize) -> InputEdge {
+        edge_triple(&self.edges, edge_index)
+    }
+
+    #[inline]
+    pub fn try_edge(&self, edge_index: usize) -> Option<InputEdge> {
+        (edge_index < self.edges.len()).then(|| edge_triple(&self.edges, edge_index))
+    }
+
     #[inline]
     pub fn edge_src(&self, edge_index: usize) -> usize {
-        let (src, _, _) = self.edges[edge_index];
-        src
+        self.edges.from[edge_index]
     }
 
     #[inline]
     pub fn edge_dst(&self, edge_index: usize) -> usize {
-        let (_, dst, _) = self.edges[edge_index];
-        dst
+        self.edges.to[edge_index]
     }
 
     #[inline]
     pub fn edge_rate(&self, edge_index: usize) -> f64 {
-        let (_, _, rate) = self.edges[edge_index];
-        rate
+        self.edges.rate[edge_index]
     }
 
     #[inline]
@@ -125,20 +651,608 @@ impl CSRGraph {
         if edge_index >= self.edges.len() {
             return Err(UpdateError::IndexOutOfBounds(edge_index));
         }
+        if self.removed[edge_index] {
+            return Err(UpdateError::EdgeRemoved(edge_index));
+        }
         if new_rate <= 0.0 || !new_rate.is_finite() {
             return Err(UpdateError::InvalidRate(new_rate));
         }
-        let (src, dst, _) = self.edges[edge_index];
-        self.edges[edge_index] = (src, dst, new_rate);
-        self.weights_in_neglog[edge_index] = -new_rate.ln();
+        self.edges.rate[edge_index] = new_rate;
+        self.sync_weight(edge_index);
+        self.update_tick += 1;
+        self.last_updated[edge_index] = self.update_tick;
         Ok(())
     }
+
+    /// Clamp every live edge's rate into `[min, max]` and refresh its weight, returning how many
+    /// edges changed. Bounds are sanitized like the pipeline's: `min` is raised to the smallest
+    /// positive `f64` and `max` to at least `min`. Clamping is not a new quote, so
+    /// [`CSRGraph::last_updated`] is left alone.
+    pub fn reclamp_rates(&mut self, min: f64, max: f64) -> usize {
+        let min = min.max(f64::MIN_POSITIVE);
+        let max = max.max(min);
+        let mut changed = 0;
+        for edge_index in 0..self.edges.len() {
+            if self.removed[edge_index] {
+                continue;
+            }
+            let rate = self.edges.rate[edge_index];
+            let clamped = rate.clamp(min, max);
+            if clamped != rate {
+                self.edges.rate[edge_index] = clamped;
+                self.sync_weight(edge_index);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Number of successful rate updates applied so far; the clock behind
+    /// [`CSRGraph::last_updated`].
+    #[inline]
+    pub fn current_tick(&self) -> u64 {
+        self.update_tick
+    }
+
+    /// Tick of the last rate update on `edge_index`, or the tick it was added at.
+    #[inline]
+    pub fn last_updated(&self, edge_index: usize) -> u64 {
+        self.last_updated[edge_index]
+    }
+
+    /// Live edges whose rate has not been updated in more than `max_age` ticks as of
+    /// `current_tick`, in edge-index order.
+    pub fn stale_edges(&self, current_tick: u64, max_age: u64) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|&edge_index| {
+                !self.removed[edge_index]
+                    && current_tick.saturating_sub(self.last_updated[edge_index]) > max_age
+            })
+            .collect()
+    }
+
+    /// Set the fee charged on `edge_index`, in basis points within `0..=10_000`.
+    pub fn update_fee(&mut self, edge_index: usize, fee_bps: f64) -> Result<(), UpdateError> {
+        if edge_index >= self.edges.len() {
+            return Err(UpdateError::IndexOutOfBounds(edge_index));
+        }
+        if self.removed[edge_index] {
+            return Err(UpdateError::EdgeRemoved(edge_index));
+        }
+        if !(0.0..=MAX_FEE_BPS).contains(&fee_bps) {
+            return Err(UpdateError::InvalidFee(fee_bps));
+        }
+        self.fees_bps[edge_index] = fee_bps;
+        self.sync_weight(edge_index);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn edge_fee_bps(&self, edge_index: usize) -> f64 {
+        self.fees_bps[edge_index]
+    }
+
+    /// `pool_id` of the dataset row behind `edge_index`, for routing a cycle leg to its pool.
+    #[inline]
+    pub fn edge_pool(&self, edge_index: usize) -> u64 {
+        self.pool_ids[edge_index]
+    }
+
+    /// `kind` of the dataset row behind `edge_index`.
+    #[inline]
+    pub fn edge_kind(&self, edge_index: usize) -> u8 {
+        self.kinds[edge_index]
+    }
+
+    /// Recompute the cached net weight of a live edge and mirror it into the dense rows.
+    fn sync_weight(&mut self, edge_index: usize) {
+        self.weights_in_neglog[edge_index] = net_weight(
+            self.edges.rate[edge_index],
+            self.fees_bps[edge_index],
+            self.log_base,
+        );
+        if let Some(dense) = self.dense.as_mut() {
+            dense.weights[dense.slot_by_edge[edge_index]] = self.weights_in_neglog[edge_index];
+        }
+    }
+
+    /// Append `from -> to` at the end of `from`'s row and return its edge index. Node ids past
+    /// the current `node_count` grow the graph.
+    pub fn add_edge(&mut self, from: usize, to: usize, rate: f64) -> Result<usize, UpdateError> {
+        if rate <= 0.0 || !rate.is_finite() {
+            return Err(UpdateError::InvalidRate(rate));
+        }
+
+        let highest_node = from.max(to);
+        if highest_node >= self.node_count {
+            let live_edges = self.edge_indices.len();
+            self.edge_offsets.resize(highest_node + 2, live_edges);
+            self.node_count = highest_node + 1;
+        }
+
+        let edge_index = self.edges.len();
+        self.edges.push(from, to, rate);
+        self.weights_in_neglog
+            .push(net_weight(rate, 0.0, self.log_base));
+        self.fees_bps.push(0.0);
+        self.removed.push(false);
+        self.pool_ids.push(0);
+        self.kinds.push(0);
+        self.last_updated.push(self.update_tick);
+
+        let slot = self.edge_offsets[from + 1];
+        self.edge_indices.insert(slot, edge_index);
+        for offset in &mut self.edge_offsets[from + 1..] {
+            *offset += 1;
+        }
+
+        self.refresh_dense_adjacency();
+        Ok(edge_index)
+    }
+
+    /// Tombstone `edge_index`: it leaves its CSR row and its weight becomes `+inf`, but the
+    /// index is never reused.
+    pub fn remove_edge(&mut self, edge_index: usize) -> Result<(), UpdateError> {
+        if edge_index >= self.edges.len() {
+            return Err(UpdateError::IndexOutOfBounds(edge_index));
+        }
+        if self.removed[edge_index] {
+            return Err(UpdateError::EdgeRemoved(edge_index));
+        }
+
+        let from = self.edges.from[edge_index];
+        let row_start = self.edge_offsets[from];
+        let row_end = self.edge_offsets[from + 1];
+        let slot = self.edge_indices[row_start..row_end]
+            .iter()
+            .position(|&candidate| candidate == edge_index)
+            .map(|position| row_start + position)
+            .expect("live edge must sit in its CSR row");
+        self.edge_indices.remove(slot);
+        for offset in &mut self.edge_offsets[from + 1..] {
+            *offset -= 1;
+        }
+
+        self.removed[edge_index] = true;
+        self.weights_in_neglog[edge_index] = f64::INFINITY;
+        self.refresh_dense_adjacency();
+        Ok(())
+    }
+
+    /// Reverse every edge: `(u, v, r)` becomes `(v, u, r)` under the same edge index, with fees,
+    /// tombstones and the dense layout carried over. Rows of the transpose are the incoming
+    /// edges of the original, so callers needing in-neighbors should transpose once and reuse
+    /// the result rather than scanning all rows per node.
+    pub fn transpose(&self) -> CSRGraph {
+        let reversed = EdgeSoA {
+            from: self.edges.to.clone(),
+            to: self.edges.from.clone(),
+            rate: self.edges.rate.clone(),
+        };
+        let mut transposed = CSRGraph::from_soa(self.node_count, reversed);
+        for edge_index in 0..self.edges.len() {
+            if self.removed[edge_index] {
+                transposed
+                    .remove_edge(edge_index)
+                    .expect("edge exists in the transpose");
+            } else if self.fees_bps[edge_index] != 0.0 {
+                transposed.fees_bps[edge_index] = self.fees_bps[edge_index];
+                transposed.sync_weight(edge_index);
+            }
+        }
+        transposed.pool_ids.clone_from(&self.pool_ids);
+        transposed.kinds.clone_from(&self.kinds);
+        transposed.last_updated.clone_from(&self.last_updated);
+        transposed.update_tick = self.update_tick;
+        transposed.set_log_base(self.log_base);
+        if self.dense.is_some() {
+            transposed.build_dense_adjacency();
+        }
+        transposed
+    }
+
+    /// Live edges as `(from, to, rate)` in edge-index order, with the current (pre-fee) rates.
+    /// Feeding the result to [`CSRGraph::from_edges`] rebuilds the same topology; removed edges
+    /// are skipped, so edge indices are compacted.
+    pub fn to_edge_list(&self) -> Vec<InputEdge> {
+        self.edges
+            .iter()
+            .zip(&self.removed)
+            .filter(|(_, &removed)| !removed)
+            .map(|(edge, _)| edge)
+            .collect()
+    }
+
+    /// Graphviz `digraph` of the live edges, labelled with their current rates.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_highlighting(&[])
+    }
+
+    /// Like [`CSRGraph::to_dot`], drawing the edges in `edge_indexes` (e.g. a detected cycle's
+    /// `edge_indexes`) in red.
+    pub fn to_dot_highlighting(&self, edge_indexes: &[usize]) -> String {
+        use std::fmt::Write;
+
+        let mut dot = String::from("digraph csr {\n");
+        for node in 0..self.node_count {
+            let _ = writeln!(dot, "    {node};");
+        }
+        for (edge_index, (from, to, rate)) in self.edges.iter().enumerate() {
+            if self.removed[edge_index] {
+                continue;
+            }
+            let _ = write!(dot, "    {from} -> {to} [label=\"{rate}\"");
+            if edge_indexes.contains(&edge_index) {
+                dot.push_str(", color=red, fontcolor=red, penwidth=2");
+            }
+            dot.push_str("];\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Verify the CSR arrays are mutually consistent, describing the first violation found.
+    /// Meant for tests and debugging structural edits, not for hot paths.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let n = self.node_count;
+        if self.edge_offsets.len() != n + 1 {
+            return Err(format!(
+                "edge_offsets has length {} but node_count is {n}",
+                self.edge_offsets.len()
+            ));
+        }
+        if self.edge_offsets[0] != 0 {
+            return Err(format!(
+                "edge_offsets[0] is {}, expected 0",
+                self.edge_offsets[0]
+            ));
+        }
+        if let Some(node) =
+            (0..n).find(|&node| self.edge_offsets[node] > self.edge_offsets[node + 1])
+        {
+            return Err(format!(
+                "edge_offsets decreases at node {node}: {} > {}",
+                self.edge_offsets[node],
+                self.edge_offsets[node + 1]
+            ));
+        }
+        if self.edge_offsets[n] != self.edge_indices.len() {
+            return Err(format!(
+                "edge_offsets[{n}] is {} but edge_indices holds {} edges",
+                self.edge_offsets[n],
+                self.edge_indices.len()
+            ));
+        }
+
+        let mut placed = vec![false; self.edges.len()];
+        for node in 0..n {
+            for slot in self.edge_offsets[node]..self.edge_offsets[node + 1] {
+                let edge_index = self.edge_indices[slot];
+                if edge_index >= self.edges.len() {
+                    return Err(format!(
+                        "slot {slot} holds edge {edge_index}, past the {} known edges",
+                        self.edges.len()
+                    ));
+                }
+                if std::mem::replace(&mut placed[edge_index], true) {
+                    return Err(format!("edge {edge_index} appears more than once"));
+                }
+                if self.removed[edge_index] {
+                    return Err(format!("removed edge {edge_index} is still in row {node}"));
+                }
+                let from = self.edges.from[edge_index];
+                if from != node {
+                    return Err(format!(
+                        "edge {edge_index} from node {from} is stored in row {node}"
+                    ));
+                }
+            }
+        }
+        if let Some(edge_index) = (0..self.edges.len()).find(|&ei| !placed[ei] && !self.removed[ei])
+        {
+            return Err(format!(
+                "live edge {edge_index} is missing from edge_indices"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Repeatedly drop nodes with no live outgoing or no live incoming edges (they cannot sit
+    /// on any cycle) until a fixpoint, and rebuild a compact graph from what is left. Tombstoned
+    /// edges are dropped too; the dense layout is kept if this graph had one.
+    pub fn prune_sinks(&self) -> PrunedGraph {
+        let n = self.node_count;
+        let mut out_degree = vec![0usize; n];
+        let mut in_degree = vec![0usize; n];
+        let mut incoming: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for &edge_index in &self.edge_indices {
+            let (from, to) = (self.edges.from[edge_index], self.edges.to[edge_index]);
+            out_degree[from] += 1;
+            in_degree[to] += 1;
+            incoming[to].push(edge_index);
+        }
+
+        let mut alive = vec![true; n];
+        let mut queue: Vec<usize> = (0..n)
+            .filter(|&node| out_degree[node] == 0 || in_degree[node] == 0)
+            .collect();
+        while let Some(node) = queue.pop() {
+            if !alive[node] {
+                continue;
+            }
+            alive[node] = false;
+            for (_, to, _) in self.neighbors(node) {
+                if alive[to] {
+                    in_degree[to] -= 1;
+                    if in_degree[to] == 0 {
+                        queue.push(to);
+                    }
+                }
+            }
+            for &edge_index in &incoming[node] {
+                let from = self.edges.from[edge_index];
+                if alive[from] {
+                    out_degree[from] -= 1;
+                    if out_degree[from] == 0 {
+                        queue.push(from);
+                    }
+                }
+            }
+        }
+
+        let mut new_node = vec![None; n];
+        let mut kept_nodes = 0usize;
+        for (node, slot) in new_node.iter_mut().enumerate() {
+            if alive[node] {
+                *slot = Some(kept_nodes);
+                kept_nodes += 1;
+            }
+        }
+
+        let mut edges = Vec::new();
+        let mut original_edge = Vec::new();
+        for (edge_index, (from, to, rate)) in self.edges.iter().enumerate() {
+            if self.removed[edge_index] {
+                continue;
+            }
+            if let (Some(new_from), Some(new_to)) = (new_node[from], new_node[to]) {
+                edges.push((new_from, new_to, rate));
+                original_edge.push(edge_index);
+            }
+        }
+
+        let mut graph = CSRGraph::from_edges(kept_nodes, edges);
+        for (new_index, &old_index) in original_edge.iter().enumerate() {
+            graph.fees_bps[new_index] = self.fees_bps[old_index];
+            graph.last_updated[new_index] = self.last_updated[old_index];
+            graph.pool_ids[new_index] = self.pool_ids[old_index];
+            graph.kinds[new_index] = self.kinds[old_index];
+            graph.sync_weight(new_index);
+        }
+        graph.update_tick = self.update_tick;
+        graph.set_log_base(self.log_base);
+        if self.dense.is_some() {
+            graph.build_dense_adjacency();
+        }
+        PrunedGraph {
+            graph,
+            new_node,
+            original_edge,
+        }
+    }
+
+    /// Every profitable round trip `u -> v -> u` over a single pair of tokens, as
+    /// `(u, v, edge_uv, edge_vu, profit)` with `u < v`, sorted by `(u, v)`. Uses the best live
+    /// edge in each direction and the net (post-fee) rates; a pair is kept when its rate product
+    /// exceeds `min_profit_ratio`. One pass over the live edges, no Bellman-Ford.
+    pub fn two_cycle_opportunities(
+        &self,
+        min_profit_ratio: f64,
+    ) -> Vec<(usize, usize, usize, usize, f64)> {
+        let mut best_edge: HashMap<(usize, usize), usize> = HashMap::new();
+        for from in 0..self.node_count {
+            for (edge_index, to, weight) in self.neighbors(from) {
+                if from == to {
+                    continue;
+                }
+                match best_edge.entry((from, to)) {
+                    Entry::Occupied(mut best) => {
+                        if weight < self.weights_in_neglog[*best.get()] {
+                            best.insert(edge_index);
+                        }
+                    }
+                    Entry::Vacant(slot) => {
+                        slot.insert(edge_index);
+                    }
+                }
+            }
+        }
+
+        let mut opportunities: Vec<_> = best_edge
+            .iter()
+            .filter(|(&(u, v), _)| u < v)
+            .filter_map(|(&(u, v), &edge_uv)| {
+                let edge_vu = *best_edge.get(&(v, u))?;
+                let profit = self
+                    .log_base
+                    .rate_for(self.weights_in_neglog[edge_uv] + self.weights_in_neglog[edge_vu]);
+                (profit > min_profit_ratio).then_some((u, v, edge_uv, edge_vu, profit))
+            })
+            .collect();
+        opportunities.sort_unstable_by_key(|&(u, v, ..)| (u, v));
+        opportunities
+    }
+
+    /// Strongly connected components over live edges (iterative Tarjan). Every node appears in
+    /// exactly one component; components come out in reverse topological order with their
+    /// nodes sorted ascending. Only components of two or more nodes (or a node with a
+    /// self-loop) can host a cycle.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        const UNVISITED: usize = usize::MAX;
+        let n = self.node_count;
+        let mut index = vec![UNVISITED; n];
+        let mut low_link = vec![0usize; n];
+        let mut on_stack = vec![false; n];
+        let mut component_stack = Vec::new();
+        // (node, next position in its CSR row) for the DFS in progress.
+        let mut call_stack: Vec<(usize, usize)> = Vec::new();
+        let mut next_index = 0usize;
+        let mut components = Vec::new();
+
+        for root in 0..n {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            call_stack.push((root, self.edge_offsets[root]));
+            index[root] = next_index;
+            low_link[root] = next_index;
+            next_index += 1;
+            component_stack.push(root);
+            on_stack[root] = true;
+
+            while let Some(&mut (node, ref mut cursor)) = call_stack.last_mut() {
+                if *cursor < self.edge_offsets[node + 1] {
+                    let to = self.edges.to[self.edge_indices[*cursor]];
+                    *cursor += 1;
+                    if index[to] == UNVISITED {
+                        index[to] = next_index;
+                        low_link[to] = next_index;
+                        next_index += 1;
+                        component_stack.push(to);
+                        on_stack[to] = true;
+                        call_stack.push((to, self.edge_offsets[to]));
+                    } else if on_stack[to] {
+                        low_link[node] = low_link[node].min(index[to]);
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if low_link[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = component_stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    component.sort_unstable();
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+}
+
+impl WeightedGraph for CSRGraph {
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    #[inline]
+    fn edge_count(&self) -> usize {
+        self.edge_indices.len()
+    }
+
+    #[inline]
+    fn edge(&self, edge_index: usize) -> InputEdge {
+        edge_triple(&self.edges, edge_index)
+    }
+
+    #[inline]
+    fn weight(&self, edge_index: usize) -> f64 {
+        self.weights_in_neglog[edge_index]
+    }
+
+    #[inline]
+    fn log_base(&self) -> LogBase {
+        self.log_base
+    }
+
+    /// Reads the contiguous dense rows when [`CSRGraph::with_dense_adjacency`] was used.
+    #[inline]
+    fn for_each_neighbor<F>(&self, from_node: usize, mut f: F)
+    where
+        F: FnMut(usize, usize, f64),
+    {
+        match self.dense_neighbors(from_node) {
+            Some(row) => {
+                for (edge_index, to_node, w) in row {
+                    f(edge_index, to_node, w);
+                }
+            }
+            None => {
+                for (edge_index, to_node, w) in self.neighbors(from_node) {
+                    f(edge_index, to_node, w);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reclamp_rates_clamps_out_of_range_edges() {
+        let mut graph = CSRGraph::from_edges(
+            3,
+            vec![(0, 1, 1e12), (1, 2, 1.5), (2, 0, 1e-12), (0, 2, 0.5)],
+        )
+        .with_dense_adjacency();
+        graph.update_fee(0, 30.0).unwrap();
+        graph.remove_edge(3).unwrap();
+
+        assert_eq!(graph.reclamp_rates(0.5, 2.0), 2);
+        assert_eq!(graph.edge_rate(0), 2.0);
+        assert_eq!(graph.edge_rate(1), 1.5);
+        assert_eq!(graph.edge_rate(2), 0.5);
+        assert_eq!(graph.edge_rate(3), 0.5);
+        assert_eq!(graph.weight(0), net_weight(2.0, 30.0, LogBase::E));
+        assert_eq!(graph.weight(2), -(0.5f64.ln()));
+        let dense_row: Vec<_> = graph.dense_neighbors(0).expect("dense layout").collect();
+        assert_eq!(dense_row, vec![(0, 1, graph.weight(0))]);
+        assert!(graph.weight(3).is_infinite());
+        assert_eq!(graph.reclamp_rates(0.5, 2.0), 0);
+    }
+
+    #[test]
+    fn edges_between_returns_parallel_edges_in_order() {
+        let mut graph =
+            CSRGraph::from_edges(3, vec![(0, 1, 1.0), (0, 2, 1.0), (1, 0, 1.0), (0, 1, 1.2)]);
+        assert_eq!(graph.edges_between(0, 1), vec![0, 3]);
+        assert_eq!(graph.edges_between(1, 0), vec![2]);
+        assert!(graph.edges_between(2, 0).is_empty());
+        assert!(graph.edges_between(9, 0).is_empty());
+
+        let added = graph.add_edge(0, 1, 1.1).unwrap();
+        graph.remove_edge(0).unwrap();
+        assert_eq!(graph.edges_between(0, 1), vec![3, added]);
+    }
+
+    #[test]
+    fn remapped_graph_compacts_sparse_node_ids() {
+        let (graph, remap) =
+            CSRGraph::from_edges_remapped(vec![(5, 1000, 1.1), (1000, 7, 1.0), (7, 5, 1.0)]);
+        assert_eq!(remap, vec![5, 7, 1000]);
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge(0), (0, 2, 1.1));
+        assert_eq!(graph.edge(1), (2, 1, 1.0));
+        assert_eq!(graph.edge(2), (1, 0, 1.0));
+
+        let cycle = crate::cycle_finder::find_profitable_cycle_with_graph(&graph, 3)
+            .expect("profitable cycle");
+        let original: Vec<usize> = cycle.vertices.iter().map(|&node| remap[node]).collect();
+        assert_eq!(original, vec![5, 1000, 7, 5]);
+    }
+
     #[test]
     fn neighbors_preserve_insertion_order() {
         let edges = vec![(0, 1, 1.2), (0, 2, 0.9), (1, 0, 1.1), (2, 1, 1.05)];
@@ -253,4 +1367,364 @@ mod tests {
             .any(|(ei, dst, rate)| *ei == 2 && *dst == 1 && (*rate - 1.05).abs() < 1e-12));
         assert!(neighbors_one.iter().filter(|(_, dst, _)| *dst == 2).count() == 2);
     }
+
+    #[test]
+    fn dense_adjacency_mirrors_indirect_neighbors() {
+        let edges = vec![(0, 1, 1.2), (1, 2, 0.9), (0, 2, 1.1), (2, 0, 1.05)];
+        let mut graph = CSRGraph::from_edges(3, edges).with_dense_adjacency();
+        graph.update_rate(2, 1.3).unwrap();
+
+        for node in 0..graph.node_count() {
+            let indirect: Vec<_> = graph.neighbors(node).collect();
+            let dense: Vec<_> = graph.dense_neighbors(node).unwrap().collect();
+            assert_eq!(indirect, dense);
+        }
+    }
+
+    #[test]
+    fn edge_matches_component_accessors() {
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 1.2), (2, 0, 0.8)]);
+
+        let (src, dst, rate) = graph.edge(1);
+        assert_eq!(src, graph.edge_src(1));
+        assert_eq!(dst, graph.edge_dst(1));
+        assert_eq!(rate, graph.edge_rate(1));
+        assert_eq!(graph.try_edge(1), Some((2, 0, 0.8)));
+        assert_eq!(graph.try_edge(2), None);
+    }
+
+    #[test]
+    fn view_borrows_the_same_adjacency() {
+        let edges = vec![(0, 1, 1.2), (0, 2, 0.9), (2, 0, 1.1)];
+        let graph = CSRGraph::from_edges(3, edges);
+        let view = graph.view();
+
+        assert_eq!(WeightedGraph::node_count(&view), graph.node_count());
+        assert_eq!(WeightedGraph::edge_count(&view), graph.edge_count());
+        for node in 0..graph.node_count() {
+            let owned: Vec<_> = graph.neighbors(node).collect();
+            let borrowed: Vec<_> = view.neighbors(node).collect();
+            assert_eq!(owned, borrowed);
+        }
+    }
+
+    #[test]
+    fn add_edge_appends_to_row_and_grows_nodes() {
+        let mut graph =
+            CSRGraph::from_edges(2, vec![(0, 1, 1.0), (1, 0, 1.0)]).with_dense_adjacency();
+
+        let added = graph.add_edge(0, 3, 1.5).unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        let row: Vec<_> = graph.neighbors(0).map(|(ei, dst, _)| (ei, dst)).collect();
+        assert_eq!(row, vec![(0, 1), (2, 3)]);
+        let dense: Vec<_> = graph.dense_neighbors(0).unwrap().collect();
+        assert_eq!(dense, graph.neighbors(0).collect::<Vec<_>>());
+        assert_eq!(graph.neighbors(3).count(), 0);
+        assert!(matches!(
+            graph.add_edge(0, 1, f64::NAN),
+            Err(UpdateError::InvalidRate(_))
+        ));
+    }
+
+    #[test]
+    fn remove_edge_tombstones_without_reindexing() {
+        let mut graph = CSRGraph::from_edges(3, vec![(0, 1, 1.0), (0, 2, 1.0), (1, 2, 1.0)]);
+
+        graph.remove_edge(0).unwrap();
+        assert!(graph.is_removed(0));
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.edge_index_bound(), 3);
+        assert_eq!(graph.weights_in_neglog[0], f64::INFINITY);
+        let row: Vec<_> = graph.neighbors(0).map(|(ei, _, _)| ei).collect();
+        assert_eq!(row, vec![1]);
+        assert_eq!(graph.neighbors(1).next().map(|(ei, _, _)| ei), Some(2));
+
+        assert!(matches!(
+            graph.remove_edge(0),
+            Err(UpdateError::EdgeRemoved(0))
+        ));
+        assert!(matches!(
+            graph.update_rate(0, 1.1),
+            Err(UpdateError::EdgeRemoved(0))
+        ));
+    }
+
+    #[test]
+    fn prune_sinks_drops_dangling_nodes_and_keeps_cycle() {
+        let graph = CSRGraph::from_edges(
+            6,
+            vec![
+                (0, 1, 1.02),
+                (1, 2, 1.02),
+                (2, 0, 0.98),
+                (2, 3, 1.0), // dangling chain 2 -> 3 -> 4
+                (3, 4, 1.0),
+                (5, 0, 1.0), // source with no incoming edges
+            ],
+        );
+
+        let pruned = graph.prune_sinks();
+        assert_eq!(pruned.graph.node_count(), 3);
+        assert_eq!(pruned.graph.edge_count(), 3);
+        assert_eq!(pruned.original_edge, vec![0, 1, 2]);
+        assert_eq!(&pruned.new_node[3..], &[None, None, None]);
+
+        let cycle = crate::cycle_finder::find_profitable_cycle_with_graph(&pruned.graph, 3)
+            .expect("cycle survives pruning");
+        let mut original: Vec<_> = cycle
+            .edge_indexes
+            .iter()
+            .map(|&ei| pruned.original_edge[ei])
+            .collect();
+        original.sort_unstable();
+        assert_eq!(original, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn check_invariants_flags_corruption() {
+        let mut graph = CSRGraph::from_edges(3, vec![(0, 1, 1.0), (1, 2, 1.0), (2, 0, 1.0)]);
+        graph.add_edge(0, 2, 1.5).expect("valid edge");
+        graph.remove_edge(1).expect("edge exists");
+        assert_eq!(graph.check_invariants(), Ok(()));
+
+        let mut swapped = graph.clone();
+        swapped.edge_indices.swap(0, 2);
+        let err = swapped
+            .check_invariants()
+            .expect_err("rows no longer match");
+        assert!(
+            err.contains("is stored in row"),
+            "unexpected message: {err}"
+        );
+
+        let mut duplicated = graph.clone();
+        duplicated.edge_indices[1] = duplicated.edge_indices[0];
+        let err = duplicated
+            .check_invariants()
+            .expect_err("edge listed twice");
+        assert!(err.contains("more than once"), "unexpected message: {err}");
+
+        let mut truncated = graph;
+        truncated.edge_offsets.pop();
+        assert!(truncated.check_invariants().is_err());
+    }
+
+    #[test]
+    fn transpose_reverses_edges_and_round_trips() {
+        let mut graph =
+            CSRGraph::from_edges(4, vec![(0, 1, 1.1), (1, 2, 0.9), (2, 0, 1.05), (3, 1, 2.0)]);
+        graph.update_fee(2, 25.0).expect("valid fee");
+        graph.remove_edge(3).expect("edge exists");
+
+        let transposed = graph.transpose();
+        assert_eq!(transposed.check_invariants(), Ok(()));
+        assert_eq!(transposed.edge(0), (1, 0, 1.1));
+        assert_eq!(transposed.weights_in_neglog[2], graph.weights_in_neglog[2]);
+        let into_one: Vec<usize> = transposed.neighbors(1).map(|(_, to, _)| to).collect();
+        assert_eq!(into_one, vec![0]);
+
+        let round_trip = transposed.transpose();
+        for edge_index in 0..graph.edge_index_bound() {
+            assert_eq!(round_trip.edge(edge_index), graph.edge(edge_index));
+            assert_eq!(
+                round_trip.is_removed(edge_index),
+                graph.is_removed(edge_index)
+            );
+            assert_eq!(
+                round_trip.weights_in_neglog[edge_index],
+                graph.weights_in_neglog[edge_index]
+            );
+        }
+        let mut original_rows: Vec<_> = (0..4).flat_map(|n| graph.neighbors(n)).collect();
+        let mut round_trip_rows: Vec<_> = (0..4).flat_map(|n| round_trip.neighbors(n)).collect();
+        original_rows.sort_by_key(|&(ei, _, _)| ei);
+        round_trip_rows.sort_by_key(|&(ei, _, _)| ei);
+        assert_eq!(original_rows, round_trip_rows);
+    }
+
+    #[test]
+    fn strongly_connected_components_split_cycle_from_chain() {
+        let graph = CSRGraph::from_edges(
+            6,
+            vec![
+                (0, 1, 1.0),
+                (1, 2, 1.0),
+                (2, 0, 1.0),
+                (2, 3, 1.0),
+                (3, 4, 1.0),
+            ],
+        );
+
+        let mut components = graph.strongly_connected_components();
+        components.sort();
+        assert_eq!(components, vec![vec![0, 1, 2], vec![3], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn best_rate_construction_keeps_only_the_better_parallel_edge() {
+        let graph = CSRGraph::from_edges_best_rate(
+            3,
+            vec![(0, 1, 1.05), (1, 2, 1.0), (0, 1, 1.10), (0, 2, 0.9)],
+        );
+
+        assert_eq!(graph.check_invariants(), Ok(()));
+        assert_eq!(graph.edge_count(), 3);
+        let from_zero: Vec<(usize, f64)> = graph
+            .neighbors(0)
+            .map(|(ei, to, _)| (to, graph.edge_rate(ei)))
+            .collect();
+        assert_eq!(from_zero, vec![(1, 1.10), (2, 0.9)]);
+    }
+
+    fn dataset_with_edges(edges: Vec<(u64, u64, f64)>) -> Dataset {
+        let edges = edges
+            .into_iter()
+            .zip(0..)
+            .map(|((from, to, rate), id)| Edge {
+                id,
+                from,
+                to,
+                rate,
+                pool_id: 0,
+                kind: 0,
+            })
+            .collect();
+        Dataset {
+            tokens: Vec::new(),
+            edges,
+        }
+    }
+
+    #[test]
+    fn from_dataset_returns_graph_and_baseline_rates() {
+        let dataset = dataset_with_edges(vec![(0, 1, 1.1), (1, 2, 0.9), (2, 0, 1.05)]);
+        let (graph, baseline_rates) = CSRGraph::from_dataset(&dataset).expect("valid dataset");
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge(1), (1, 2, 0.9));
+        assert_eq!(baseline_rates, vec![1.1, 0.9, 1.05]);
+    }
+
+    #[test]
+    fn edges_keep_their_dataset_pool_ids() {
+        let mut dataset = dataset_with_edges(vec![(0, 1, 1.1), (1, 2, 0.9), (1, 2, 0.95)]);
+        for (edge, pool_id) in dataset.edges.iter_mut().zip([70, 71, 72]) {
+            edge.pool_id = pool_id;
+            edge.kind = BIDIRECTIONAL_EDGE_KIND;
+        }
+
+        let (graph, _) = CSRGraph::from_dataset(&dataset).expect("valid dataset");
+        let pools: Vec<u64> = (0..graph.edge_count())
+            .map(|e| graph.edge_pool(e))
+            .collect();
+        assert_eq!(pools, vec![70, 71, 72]);
+        assert_eq!(graph.edge_kind(2), BIDIRECTIONAL_EDGE_KIND);
+        assert_eq!(graph.transpose().edge_pool(1), 71);
+
+        // Deduplication keeps the pool that quotes the surviving rate.
+        let mut edges = dataset_input_edges(&dataset, true).expect("valid dataset");
+        assert_eq!(edges.pool_ids, vec![70, 70, 71, 71, 72, 72]);
+        assert_eq!(edges.dedup(DedupPolicy::KeepBest), 2);
+        let graph = edges.into_graph();
+        assert_eq!(graph.edge(2), (1, 2, 0.95));
+        assert_eq!(graph.edge_pool(2), 72);
+        // In reverse, 1 / 0.9 beats 1 / 0.95, so pool 71 wins that direction.
+        assert_eq!(graph.edge(3).2, 0.9f64.recip());
+        assert_eq!(graph.edge_pool(3), 71);
+    }
+
+    #[test]
+    fn from_dataset_rejects_invalid_datasets() {
+        let dataset = dataset_with_edges(vec![(0, 1, 1.1), (1, 2, -0.5)]);
+        assert!(matches!(
+            CSRGraph::from_dataset(&dataset),
+            Err(PipelineError::InvalidRate { edge_id: 1, rate }) if rate == -0.5
+        ));
+
+        let empty = Dataset {
+            tokens: Vec::new(),
+            edges: Vec::new(),
+        };
+        assert!(matches!(
+            CSRGraph::from_dataset(&empty),
+            Err(PipelineError::EmptyDataset)
+        ));
+    }
+
+    #[test]
+    fn endpoints_outside_usize_are_rejected() {
+        let dataset = dataset_with_edges(vec![(0, 1, 1.1)]);
+        assert_eq!(edge_endpoints(&dataset.edges[0]).unwrap(), (0, 1));
+
+        let out_of_range = || u8::try_from(u64::MAX).map(usize::from);
+        assert!(matches!(
+            endpoint_indices(7, Ok(0), out_of_range()),
+            Err(PipelineError::ToIndex { edge_id: 7, .. })
+        ));
+        assert!(matches!(
+            endpoint_indices(7, out_of_range(), out_of_range()),
+            Err(PipelineError::FromIndex { edge_id: 7, .. })
+        ));
+        assert_eq!(endpoint_indices(7, Ok(3), Ok(4)).unwrap(), (3, 4));
+    }
+
+    #[test]
+    fn edge_list_round_trips_through_from_edges() {
+        let mut graph =
+            CSRGraph::from_edges(3, vec![(0, 1, 1.1), (1, 2, 0.9), (2, 0, 1.05), (2, 1, 0.5)]);
+        graph.update_rate(1, 1.2).expect("valid rate");
+        graph.remove_edge(3).expect("edge exists");
+
+        let edges = graph.to_edge_list();
+        assert_eq!(edges, vec![(0, 1, 1.1), (1, 2, 1.2), (2, 0, 1.05)]);
+
+        let rebuilt = CSRGraph::from_edges(graph.node_count(), edges.clone());
+        assert_eq!(rebuilt.check_invariants(), Ok(()));
+        assert_eq!(rebuilt.edge_count(), graph.edge_count());
+        assert_eq!(rebuilt.to_edge_list(), edges);
+        for edge_index in 0..edges.len() {
+            assert_eq!(
+                rebuilt.weights_in_neglog[edge_index],
+                graph.weights_in_neglog[edge_index]
+            );
+        }
+    }
+
+    #[test]
+    fn dot_output_labels_rates_and_highlights_edges() {
+        let mut graph = CSRGraph::from_edges(2, vec![(0, 1, 1.5), (1, 0, 0.7), (1, 0, 0.6)]);
+        graph.remove_edge(2).expect("edge exists");
+
+        let dot = graph.to_dot_highlighting(&[1]);
+        assert!(dot.starts_with("digraph csr {"));
+        assert!(dot.contains("0 -> 1 [label=\"1.5\"];"));
+        assert!(dot.contains("1 -> 0 [label=\"0.7\", color=red"));
+        assert!(!dot.contains("0.6"));
+        assert!(!graph.to_dot().contains("red"));
+    }
+
+    #[test]
+    fn two_cycle_scan_reports_only_profitable_pairs() {
+        let graph = CSRGraph::from_edges(
+            4,
+            vec![
+                (0, 1, 1.2),
+                (1, 0, 0.9), // 0 <-> 1: 1.08
+                (1, 0, 0.8),
+                (2, 3, 1.1),
+                (3, 2, 0.85), // 2 <-> 3: 0.935
+                (1, 2, 5.0),  // no way back
+                (3, 3, 2.0),  // self-loop
+            ],
+        );
+
+        let found = graph.two_cycle_opportunities(1.0);
+        assert_eq!(found.len(), 1);
+        let (u, v, edge_uv, edge_vu, profit) = found[0];
+        assert_eq!((u, v, edge_uv, edge_vu), (0, 1, 0, 1));
+        assert!((profit - 1.08).abs() < 1e-12);
+        assert!(graph.two_cycle_opportunities(1.1).is_empty());
+    }
 }
@@ -1,21 +1,187 @@
-use crate::csr_graph::CSRGraph;
+use crate::{
+    csr_graph::{CSRGraph, CSRGraphView, LogBase, WeightedGraph},
+    log_kernel::{quantize_ties_even_linear, DEFAULT_TIE_SLACK_ULPS},
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::ControlFlow,
+};
 
 pub use crate::csr_graph::InputEdge;
 
 const EPS: f64 = 1e-12;
 
-#[derive(Debug, Clone)]
+/// Per-call tuning for [`find_profitable_cycle_with_config`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FinderConfig {
+    /// A cycle is profitable only when its `neg_log_sum` is below `-eps`. Defaults to `1e-12`;
+    /// raise it (e.g. to `1e-9`) to ignore near-zero cycles that are just rounding noise on
+    /// quantized rates. Negative or NaN values behave like `0.0`.
+    pub eps: f64,
+}
+
+impl Default for FinderConfig {
+    fn default() -> Self {
+        Self { eps: EPS }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cycle {
     /// s -> ... -> s  (length = edge_indexes.len() + 1)
     pub vertices: Vec<usize>,
     /// indices into the `edges` slice, in the *cycle order*
     pub edge_indexes: Vec<usize>,
+    /// rate of each edge in `edge_indexes` (net of any edge fee) at the time the cycle was found
+    pub rates: Vec<f64>,
     /// product of rates along the cycle
     pub profit: f64,
-    /// sum of -ln(rate) along the cycle (negative ⇒ profitable)
+    /// sum of -log(rate) along the cycle in the graph's [`LogBase`] (negative ⇒ profitable)
     pub neg_log_sum: f64,
 }
 
+impl Cycle {
+    /// Hash of the cycle's sorted edge indices, so every rotation of a route (found from any
+    /// start node) shares one signature and distinct edge sets almost surely differ. Walks that
+    /// traverse the same edges in another order collide; the searcher's history compares edge rings
+    /// to tell those apart. Stable within a build, not across Rust releases; don't persist it.
+    pub fn signature(&self) -> u64 {
+        let mut edge_set = self.edge_indexes.clone();
+        edge_set.sort_unstable();
+        let mut hasher = DefaultHasher::new();
+        edge_set.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Gross amount out after routing `input_amount` through every leg at the captured rates.
+    pub fn simulate(&self, input_amount: f64) -> f64 {
+        self.rates
+            .iter()
+            .fold(input_amount, |amount, &rate| amount * rate)
+    }
+
+    /// Profit as a percentage margin: `(profit - 1) * 100`, so a 1.02 cycle reads as 2.0.
+    pub fn profit_margin_pct(&self) -> f64 {
+        (self.profit - 1.0) * 100.0
+    }
+
+    /// Whether the rate product clears `ratio` (inclusive), e.g. `1.01` for at least 1%.
+    pub fn is_profitable_above(&self, ratio: f64) -> bool {
+        self.profit >= ratio
+    }
+
+    /// Profit excess (`profit - 1`) minus `lambda` times the summed volatility of the legs.
+    /// `volatilities` is indexed by edge index; edges past its end count as perfectly stable.
+    pub fn risk_adjusted_profit(&self, volatilities: &[f64], lambda: f64) -> f64 {
+        let leg_volatility: f64 = self
+            .edge_indexes
+            .iter()
+            .map(|&ei| volatilities.get(ei).copied().unwrap_or(0.0))
+            .sum();
+        (self.profit - 1.0) - lambda * leg_volatility
+    }
+
+    /// `profit` rounded to a multiple of `tick` (ties-to-even, like the log kernel).
+    /// Non-positive or non-finite ticks return the raw profit.
+    pub fn profit_rounded(&self, tick: f64) -> f64 {
+        if !tick.is_finite() || tick <= 0.0 {
+            return self.profit;
+        }
+        quantize_ties_even_linear(self.profit, tick.recip(), tick, DEFAULT_TIE_SLACK_ULPS)
+    }
+
+    /// Rate `edge_index` would need (other legs at their current `graph` rates, net of fees)
+    /// for the cycle's net rate product to be exactly 1. The result is the gross rate to quote,
+    /// so the edge's own fee is already allowed for. `None` when the edge is not on the cycle.
+    pub fn breakeven_rate_for<G: WeightedGraph>(
+        &self,
+        graph: &G,
+        edge_index: usize,
+    ) -> Option<f64> {
+        let occurrences = self
+            .edge_indexes
+            .iter()
+            .filter(|&&ei| ei == edge_index)
+            .count();
+        if occurrences == 0 {
+            return None;
+        }
+
+        // Work in the graph's -log space like the finder: occurrences * cost(x) + others = 0.
+        let log_base = graph.log_base();
+        let others_neg_log: f64 = self
+            .edge_indexes
+            .iter()
+            .filter(|&&ei| ei != edge_index)
+            .map(|&ei| graph.weight(ei))
+            .sum();
+        let net_rate = log_base.rate_for(-others_neg_log / occurrences as f64);
+        let fee_factor = log_base.rate_for(graph.weight(edge_index)) / graph.edge(edge_index).2;
+        Some(net_rate / fee_factor)
+    }
+
+    /// Profit left after routing `input_amount` around the cycle at current `graph` rates (net
+    /// of edge fees) and paying `gas_cost_per_hop` per leg, both in the input token's units:
+    /// `out - input_amount - gas_cost_per_hop * legs`.
+    pub fn net_profit<G: WeightedGraph>(
+        &self,
+        input_amount: f64,
+        gas_cost_per_hop: f64,
+        graph: &G,
+    ) -> f64 {
+        let neg_log_sum: f64 = self.edge_indexes.iter().map(|&ei| graph.weight(ei)).sum();
+        let output_amount = input_amount * graph.log_base().rate_for(neg_log_sum);
+        output_amount - input_amount - gas_cost_per_hop * self.edge_indexes.len() as f64
+    }
+
+    /// Largest input (in the start token) that can be routed around the cycle without any leg
+    /// exceeding its pool depth. `liquidity` is indexed by edge index and holds each edge's
+    /// maximum input in that edge's input token; edges past its end are unbounded. Amounts move
+    /// through the legs at current `graph` rates (net of fees), so leg `i` sees
+    /// `input * rate_0 * ... * rate_{i-1}`. Returns `f64::INFINITY` when no leg is bounded.
+    pub fn max_feasible_input<G: WeightedGraph>(&self, graph: &G, liquidity: &[f64]) -> f64 {
+        let mut max_input = f64::INFINITY;
+        let mut input_to_leg = 1.0_f64;
+        for &ei in &self.edge_indexes {
+            if let Some(&limit) = liquidity.get(ei) {
+                max_input = max_input.min(limit / input_to_leg);
+            }
+            input_to_leg *= graph.log_base().rate_for(graph.weight(ei));
+        }
+        max_input
+    }
+
+    /// Profit of the cycle at current `graph` rates, plus the profit of walking it backwards
+    /// over the reverse edges (best-rate edge when a pair has several). The reverse profit is
+    /// `None` unless every leg has a reverse edge.
+    pub fn directional_profits<G: WeightedGraph>(&self, graph: &G) -> (f64, Option<f64>) {
+        let forward_neg_log: f64 = self.edge_indexes.iter().map(|&ei| graph.weight(ei)).sum();
+
+        let mut reverse_neg_log = 0.0_f64;
+        for &ei in &self.edge_indexes {
+            let (from, to, _) = graph.edge(ei);
+            let mut best_back = f64::INFINITY;
+            graph.for_each_neighbor(to, |_, back_to, w| {
+                if back_to == from {
+                    best_back = best_back.min(w);
+                }
+            });
+            if !best_back.is_finite() {
+                return (graph.log_base().rate_for(forward_neg_log), None);
+            }
+            reverse_neg_log += best_back;
+        }
+
+        let base = graph.log_base();
+        (
+            base.rate_for(forward_neg_log),
+            Some(base.rate_for(reverse_neg_log)),
+        )
+    }
+}
+
 /// Bellman–Ford with a hop cap (no super-source).
 /// For each start node, we run exact-hop DP up to `hop_cap`, relaxing in place and
 /// reusing buffers (swap) to minimize allocations. A cycle exists at hop `h` iff
@@ -39,81 +205,793 @@ pub fn find_profitable_cycle(
     find_profitable_cycle_with_graph(&graph, hop_cap)
 }
 
-/// Variant accepting a pre-built CSR graph to avoid rebuilding adjacency data on every call.
-pub fn find_profitable_cycle_with_graph(graph: &CSRGraph, hop_cap: usize) -> Option<Cycle> {
+/// Fee-aware variant: each edge's working weight is `-ln(rate * (1 - fee_bps / 10_000))`, so
+/// the returned `profit` is net of fees. `fees_bps` must line up with `edges`; mismatched
+/// lengths or fees outside `[0, 10_000)` bps yield `None` like any other invalid input.
+pub fn find_profitable_cycle_with_fees(
+    n: usize,
+    edges: &[(usize, usize, f64)],
+    fees_bps: &[f64],
+    hop_cap: usize,
+) -> Option<Cycle> {
+    if fees_bps.len() != edges.len()
+        || fees_bps
+            .iter()
+            .any(|&fee| !fee.is_finite() || !(0.0..10_000.0).contains(&fee))
+    {
+        return None;
+    }
+
+    let net_edges: Vec<InputEdge> = edges
+        .iter()
+        .zip(fees_bps)
+        .map(|(&(u, v, rate), &fee_bps)| (u, v, rate * (1.0 - fee_bps / 10_000.0)))
+        .collect();
+    find_profitable_cycle(n, &net_edges, hop_cap)
+}
+
+/// Variant accepting a pre-built graph (owned [`CSRGraph`] or a borrowed
+/// [`crate::csr_graph::CSRGraphView`]) to avoid rebuilding adjacency data on every call.
+pub fn find_profitable_cycle_with_graph<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+) -> Option<Cycle> {
     let n = graph.node_count();
     if n == 0 || graph.edge_count() == 0 || hop_cap == 0 {
         return None;
     }
 
-    // Try each start node separately (no virtual super-source).
+    first_profitable_cycle(graph, hop_cap, -EPS)
+}
+
+/// Like [`find_profitable_cycle_with_graph`], but a cycle only qualifies once its rate product
+/// reaches `min_profit_ratio` (e.g. `1.005` to ignore anything under 0.5%). Ratios at or below
+/// 1.0 fall back to the plain "any profit" test.
+pub fn find_profitable_cycle_with_min_profit<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    min_profit_ratio: f64,
+) -> Option<Cycle> {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 || hop_cap == 0 {
+        return None;
+    }
+
+    let bound = min_profit_neg_log(min_profit_ratio, graph.log_base());
+    first_profitable_cycle(graph, hop_cap, bound)
+}
+
+/// Like [`find_profitable_cycle_with_graph`], with the detection threshold taken from `config`.
+pub fn find_profitable_cycle_with_config<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    config: FinderConfig,
+) -> Option<Cycle> {
+    first_profitable_cycle(graph, hop_cap, -config.eps.max(0.0))
+}
+
+/// The `k` most profitable (lowest `neg_log_sum`) distinct cycles, best first. Every start node
+/// contributes the cheapest profitable closing walk of each hop count up to `hop_cap` that is a
+/// simple cycle, so the whole graph is always swept and several cycles through one node can all
+/// rank. Rotations of the same cycle (reached from different start nodes) are reported once.
+///
+/// Unlike [`find_profitable_cycle_with_graph`], which stops at the first profitable cycle it
+/// meets (the shortest, from the lowest start node) and so can be far cheaper, `k = 1` returns
+/// the most profitable candidate; the two agree only when that is also the first one found.
+pub fn find_profitable_cycles<G: WeightedGraph>(graph: &G, hop_cap: usize, k: usize) -> Vec<Cycle> {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 || hop_cap == 0 || k == 0 {
+        return Vec::new();
+    }
+
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
     for start in 0..n {
-        // hop 0: only `start` reachable with cost 0; others are ∞
-        let mut best_previous = vec![f64::INFINITY; n];
+        for_each_closing_cycle(graph, start, hop_cap, -EPS, |cycle| {
+            if is_simple_cycle(&cycle) && seen.insert(canonical_edge_ring(&cycle)) {
+                found.push(cycle);
+            }
+            ControlFlow::Continue(())
+        });
+    }
+    // Stable, so equal candidates keep their discovery order.
+    found.sort_by(|a, b| a.neg_log_sum.total_cmp(&b.neg_log_sum));
+    found.truncate(k);
+    found
+}
+
+/// Whether no vertex repeats along the ring (a walk looping a shorter cycle is not simple).
+fn is_simple_cycle(cycle: &Cycle) -> bool {
+    let ring = &cycle.vertices[..cycle.edge_indexes.len()];
+    let mut visited = HashSet::with_capacity(ring.len());
+    ring.iter().all(|&vertex| visited.insert(vertex))
+}
+
+/// What a hop-capped search optimises for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchObjective {
+    /// Stop at the first profitable cycle, found at the smallest hop count from the lowest
+    /// start node (the behaviour of [`find_profitable_cycle_with_graph`]).
+    #[default]
+    ShortestProfitable,
+    /// Return the simple cycle within the hop cap with the lowest `neg_log_sum`.
+    MostProfitable,
+}
+
+/// Hop-capped search under an explicit [`SearchObjective`]. `MostProfitable` enumerates the
+/// simple cycles of at most `hop_cap` hops, so a looped walk never hides a simple cycle; its
+/// cost grows exponentially with `hop_cap`.
+pub fn find_profitable_cycle_with_objective<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    objective: SearchObjective,
+) -> Option<Cycle> {
+    match objective {
+        SearchObjective::ShortestProfitable => find_profitable_cycle_with_graph(graph, hop_cap),
+        SearchObjective::MostProfitable => most_profitable_simple_cycle(graph, hop_cap),
+    }
+}
+
+/// Like [`find_profitable_cycle_with_min_profit`], but start nodes in `priority_nodes` are swept
+/// first (in the given order) before the rest in index order. Only the scan order changes, so a
+/// cycle through a recently-changed node can be found before the others; any returned cycle is
+/// one the plain finder could also return.
+pub fn find_profitable_cycle_starting_with<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    min_profit_ratio: f64,
+    priority_nodes: &[usize],
+) -> Option<Cycle> {
+    let bound = min_profit_neg_log(min_profit_ratio, graph.log_base());
+    let start_order = sweep_order(graph.node_count(), priority_nodes);
+    profitable_cycles_in_order(graph, hop_cap, bound, start_order).next()
+}
+
+/// Start nodes in the order [`find_profitable_cycle_starting_with`] sweeps them:
+/// `priority_nodes` first (skipping repeats and out-of-range ids), then the rest by index.
+pub(crate) fn sweep_order(node_count: usize, priority_nodes: &[usize]) -> Vec<usize> {
+    distinct_starts(
+        node_count,
+        priority_nodes.iter().copied().chain(0..node_count),
+    )
+}
+
+/// `nodes` in order, without repeats or ids outside `0..node_count`.
+fn distinct_starts(node_count: usize, nodes: impl IntoIterator<Item = usize>) -> Vec<usize> {
+    let mut queued = vec![false; node_count];
+    nodes
+        .into_iter()
+        .filter(|&node| node < node_count && !std::mem::replace(&mut queued[node], true))
+        .collect()
+}
+
+/// The cycle a sweep with `min_profit_ratio` takes from `start`, or `None` if that start node
+/// has none; a sweep returns the first `Some` in its start order.
+pub(crate) fn find_cycle_through<G: WeightedGraph>(
+    graph: &G,
+    start: usize,
+    hop_cap: usize,
+    min_profit_ratio: f64,
+) -> Option<Cycle> {
+    let bound = min_profit_neg_log(min_profit_ratio, graph.log_base());
+    find_cycle_from_start(graph, start, hop_cap, bound)
+}
+
+/// Run the exact-hop DP from `start` alone and return the shortest profitable cycle that
+/// begins and ends there, e.g. anchored at a settlement token. One start instead of
+/// `node_count`, so roughly `node_count` times cheaper than a full sweep. This is
+/// [`find_profitable_cycle_from_starts`] with the single start and no profit threshold; an
+/// out-of-range `start` yields `None`.
+pub fn find_profitable_cycle_from_start<G: WeightedGraph>(
+    graph: &G,
+    start: usize,
+    hop_cap: usize,
+) -> Option<Cycle> {
+    find_profitable_cycle_from_starts(graph, hop_cap, 1.0, &[start])
+}
+
+/// Like [`find_profitable_cycle_with_min_profit`], but only sweeps the given start nodes (in
+/// order, skipping repeats and out-of-range ids). Finds exactly the cycles through those nodes
+/// that the full sweep would find from them.
+pub fn find_profitable_cycle_from_starts<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    min_profit_ratio: f64,
+    starts: &[usize],
+) -> Option<Cycle> {
+    let bound = min_profit_neg_log(min_profit_ratio, graph.log_base());
+    let start_order = distinct_starts(graph.node_count(), starts.iter().copied());
+    profitable_cycles_in_order(graph, hop_cap, bound, start_order).next()
+}
+
+/// Like [`find_profitable_cycle_with_graph`], but edges whose rate is more than `max_age` ticks
+/// old as of `current_tick` (see [`CSRGraph::stale_edges`]) are searched as if their weight were
+/// `+inf`, so a pool that stopped updating cannot complete a cycle.
+pub fn find_profitable_cycle_excluding_stale(
+    graph: &CSRGraph,
+    hop_cap: usize,
+    current_tick: u64,
+    max_age: u64,
+) -> Option<Cycle> {
+    let mut stale = vec![false; graph.edge_index_bound()];
+    for edge_index in graph.stale_edges(current_tick, max_age) {
+        stale[edge_index] = true;
+    }
+    let fresh = FreshEdges {
+        graph: graph.view(),
+        stale: &stale,
+    };
+    find_profitable_cycle_with_graph(&fresh, hop_cap)
+}
+
+/// A graph view whose stale edges cost `+inf`.
+struct FreshEdges<'a> {
+    graph: CSRGraphView<'a>,
+    stale: &'a [bool],
+}
+
+impl WeightedGraph for FreshEdges<'_> {
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    fn edge(&self, edge_index: usize) -> InputEdge {
+        self.graph.edge(edge_index)
+    }
+
+    fn weight(&self, edge_index: usize) -> f64 {
+        if self.stale[edge_index] {
+            f64::INFINITY
+        } else {
+            self.graph.weight(edge_index)
+        }
+    }
+
+    fn log_base(&self) -> LogBase {
+        self.graph.log_base()
+    }
+
+    fn for_each_neighbor<F>(&self, from_node: usize, mut f: F)
+    where
+        F: FnMut(usize, usize, f64),
+    {
+        self.graph
+            .for_each_neighbor(from_node, |edge_index, to_node, _| {
+                f(edge_index, to_node, self.weight(edge_index));
+            });
+    }
+}
+
+/// Like [`find_profitable_cycle_with_graph`], but only seeds start nodes that sit in a strongly
+/// connected component of two or more nodes. Every cycle through two distinct nodes lies inside
+/// one such component, so nodes outside them are skipped without losing any of those cycles.
+pub fn find_profitable_cycle_scc_pruned(graph: &CSRGraph, hop_cap: usize) -> Option<Cycle> {
+    if graph.node_count() == 0 || graph.edge_count() == 0 || hop_cap == 0 {
+        return None;
+    }
+
+    let mut start_order: Vec<usize> = graph
+        .strongly_connected_components()
+        .into_iter()
+        .filter(|component| component.len() >= 2)
+        .flatten()
+        .collect();
+    // Keep the index-order scan of the unpruned search so both report the same cycle.
+    start_order.sort_unstable();
+    profitable_cycles_in_order(graph, hop_cap, -EPS, start_order).next()
+}
+
+fn first_profitable_cycle<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    max_neg_log_sum: f64,
+) -> Option<Cycle> {
+    profitable_cycles_in_order(graph, hop_cap, max_neg_log_sum, 0..graph.node_count()).next()
+}
+
+/// Shortest profitable cycle from each start node in `start_order` that has one, computed
+/// lazily so a caller taking the first only pays for the starts it scans. Every finder that
+/// sweeps start nodes goes through here; `start_order` must hold valid node indices.
+fn profitable_cycles_in_order<'g, G: WeightedGraph>(
+    graph: &'g G,
+    hop_cap: usize,
+    max_neg_log_sum: f64,
+    start_order: impl IntoIterator<Item = usize> + 'g,
+) -> impl Iterator<Item = Cycle> + 'g {
+    let searchable = graph.node_count() > 0 && graph.edge_count() > 0 && hop_cap > 0;
+
+    // Try each start node separately (no virtual super-source).
+    searchable
+        .then_some(start_order)
+        .into_iter()
+        .flatten()
+        .filter_map(move |start| find_cycle_from_start(graph, start, hop_cap, max_neg_log_sum))
+}
+
+/// Variant that, among the profitable cycles found across start nodes, prefers the first one
+/// touching an edge in `recent` (e.g. edges whose rates just moved). Falls back to the cycle
+/// [`find_profitable_cycle_with_graph`] would return when none of them overlap `recent`.
+pub fn find_profitable_cycle_prefer_recent<G: WeightedGraph>(
+    graph: &G,
+    hop_cap: usize,
+    recent: &HashSet<usize>,
+) -> Option<Cycle> {
+    let touches_recent = |cycle: &Cycle| cycle.edge_indexes.iter().any(|ei| recent.contains(ei));
+    let mut candidates = profitable_cycles_in_order(graph, hop_cap, -EPS, 0..graph.node_count());
+    let first = candidates.next()?;
+    if recent.is_empty() || touches_recent(&first) {
+        return Some(first);
+    }
+    candidates.find(touches_recent).or(Some(first))
+}
+
+/// Best achievable conversion rate from every node to every node using 1..=`hop_cap` hops,
+/// i.e. `rates[from][to]` is the maximum rate product over walks of at most `hop_cap` edges
+/// (0.0 when unreachable). Diagonal entries are the best round trip, so values above 1.0 flag
+/// self-arbitrage through that node.
+pub fn best_conversion_rates<G: WeightedGraph>(graph: &G, hop_cap: usize) -> Vec<Vec<f64>> {
+    let n = graph.node_count();
+    let mut rates = vec![vec![0.0; n]; n];
+    if hop_cap == 0 {
+        return rates;
+    }
+
+    let mut best_previous = vec![f64::INFINITY; n];
+    let mut best_current = vec![f64::INFINITY; n];
+    let mut best_any_hop = vec![f64::INFINITY; n];
+    let mut predecessor_at_hop = vec![None; n];
+
+    for (start, row) in rates.iter_mut().enumerate() {
+        best_previous.fill(f64::INFINITY);
         best_previous[start] = 0.0;
+        best_any_hop.fill(f64::INFINITY);
+
+        for _ in 1..=hop_cap {
+            best_current.fill(f64::INFINITY);
+            relax_hop_inplace(
+                graph,
+                &best_previous,
+                &mut best_current,
+                &mut predecessor_at_hop,
+            );
+            for (best, &cost) in best_any_hop.iter_mut().zip(&best_current) {
+                *best = best.min(cost);
+            }
+            std::mem::swap(&mut best_previous, &mut best_current);
+        }
 
-        // Preallocate next-hop buffer and the predecessor-edge buffer (reused each hop).
-        let mut best_current = vec![f64::INFINITY; n];
-        let mut predecessor_at_hop = vec![None; n];
+        for (rate, &cost) in row.iter_mut().zip(&best_any_hop) {
+            *rate = if cost.is_finite() {
+                graph.log_base().rate_for(cost)
+            } else {
+                0.0
+            };
+        }
+    }
+
+    rates
+}
 
-        // History of per-hop predecessors for backtracking (snapshot per hop).
-        // At hop 0 there is no incoming edge.
-        let mut predecessors_by_hop: Vec<Vec<Option<usize>>> = Vec::with_capacity(hop_cap + 1);
-        predecessors_by_hop.push(vec![None; n]);
+/// One-number arbitrage gauge: the log (in the graph's [`LogBase`]) of the best rate product a
+/// trader could come back with in at most `hop_cap` hops, or 0.0 when no round trip is
+/// profitable. It is the cheapest closing cost of the exact-hop DP over every start node, as in
+/// [`best_conversion_rates`], so it costs a sweep rather than a cycle enumeration. Round trips
+/// are walks, not simple cycles: a profitable cycle that fits within `hop_cap` several times
+/// counts looped, so the index grows with `hop_cap`.
+pub fn arbitrage_index<G: WeightedGraph>(graph: &G, hop_cap: usize) -> f64 {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 || hop_cap == 0 {
+        return 0.0;
+    }
 
-        for hop in 1..=hop_cap {
+    let mut best_previous = vec![f64::INFINITY; n];
+    let mut best_current = vec![f64::INFINITY; n];
+    let mut predecessor_at_hop = vec![None; n];
+    let mut best_closing = f64::INFINITY;
+    for start in 0..n {
+        best_previous.fill(f64::INFINITY);
+        best_previous[start] = 0.0;
+        for _ in 1..=hop_cap {
+            best_current.fill(f64::INFINITY);
             relax_hop_inplace(
                 graph,
                 &best_previous,
                 &mut best_current,
                 &mut predecessor_at_hop,
             );
+            best_closing = best_closing.min(best_current[start]);
+            std::mem::swap(&mut best_previous, &mut best_current);
+        }
+    }
+
+    if best_closing < -EPS {
+        -best_closing
+    } else {
+        0.0
+    }
+}
+
+/// The simple cycle of at most `hop_cap` edges with the lowest `neg_log_sum` (below `-EPS`).
+/// Enumerates simple paths depth-first from each start node through higher-numbered nodes only,
+/// so every cycle is visited once, from its smallest vertex; exponential in `hop_cap`, but exact
+/// where the exact-hop DP would prefer a looped walk. Ties keep the first cycle visited.
+fn most_profitable_simple_cycle<G: WeightedGraph>(graph: &G, hop_cap: usize) -> Option<Cycle> {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 || hop_cap == 0 {
+        return None;
+    }
 
-            // Detect cycle: cost to return to `start` after exactly `hop` hops is negative.
-            let cost_to_start = best_current[start];
-            if cost_to_start.is_finite() && cost_to_start < -EPS {
-                // Reconstruct the cycle of exactly `hop` edges ending at `start`.
-                let used_edges = reconstruct_edge_path(
-                    hop,
-                    start,
-                    &predecessors_by_hop,
-                    &predecessor_at_hop,
-                    graph,
-                )?;
-                let (vertices, neg_log_sum, profit) = assemble_cycle_metrics(&used_edges, graph)?;
-
-                debug_assert_eq!(vertices.first(), vertices.last());
-
-                return Some(Cycle {
-                    vertices,
-                    edge_indexes: used_edges,
-                    profit,
-                    neg_log_sum,
-                });
+    let mut search = SimpleCycleSearch {
+        graph,
+        hop_cap,
+        start: 0,
+        on_path: vec![false; n],
+        path_edges: Vec::with_capacity(hop_cap),
+        best_neg_log_sum: -EPS,
+        best_edges: None,
+    };
+    for start in 0..n {
+        search.start = start;
+        search.on_path[start] = true;
+        search.extend(start, 0.0);
+        search.on_path[start] = false;
+    }
+
+    let used_edges = search.best_edges?;
+    let (vertices, rates, neg_log_sum, profit) = assemble_cycle_metrics(&used_edges, graph)?;
+    Some(Cycle {
+        vertices,
+        edge_indexes: used_edges,
+        rates,
+        profit,
+        neg_log_sum,
+    })
+}
+
+/// Depth-first state for [`most_profitable_simple_cycle`].
+struct SimpleCycleSearch<'g, G> {
+    graph: &'g G,
+    hop_cap: usize,
+    start: usize,
+    on_path: Vec<bool>,
+    path_edges: Vec<usize>,
+    best_neg_log_sum: f64,
+    best_edges: Option<Vec<usize>>,
+}
+
+impl<G: WeightedGraph> SimpleCycleSearch<'_, G> {
+    fn extend(&mut self, tail: usize, neg_log_sum: f64) {
+        let mut next_hops = Vec::new();
+        self.graph
+            .for_each_neighbor(tail, |edge_index, to_node, w| {
+                next_hops.push((edge_index, to_node, w));
+            });
+
+        for (edge_index, to_node, w) in next_hops {
+            let neg_log_sum = neg_log_sum + w;
+            if to_node == self.start {
+                if neg_log_sum < self.best_neg_log_sum {
+                    self.best_neg_log_sum = neg_log_sum;
+                    let mut used_edges = self.path_edges.clone();
+                    used_edges.push(edge_index);
+                    self.best_edges = Some(used_edges);
+                }
+            } else if to_node > self.start
+                && !self.on_path[to_node]
+                && self.path_edges.len() + 1 < self.hop_cap
+            {
+                self.on_path[to_node] = true;
+                self.path_edges.push(edge_index);
+                self.extend(to_node, neg_log_sum);
+                self.path_edges.pop();
+                self.on_path[to_node] = false;
             }
+        }
+    }
+}
+
+/// Classic Bellman-Ford from a virtual super-source (distance 0 to every node) with no hop cap:
+/// returns a profitable cycle whenever one exists anywhere in the graph. Unlike the hop-capped
+/// finders the result is not the shortest cycle, just the first one the predecessor walk lands on.
+pub fn find_any_negative_cycle<G: WeightedGraph>(graph: &G) -> Option<Cycle> {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 {
+        return None;
+    }
 
-            // Snapshot predecessors for this hop (for backtracking later).
-            predecessors_by_hop.push(predecessor_at_hop.clone());
+    let mut distance = vec![0.0_f64; n];
+    let mut predecessor: Vec<Option<usize>> = vec![None; n];
 
-            // Reuse allocations next round:
-            // - swap best_current <-> best_previous (so `best_previous` holds the latest),
-            // - reset current buffers in place.
-            std::mem::swap(&mut best_previous, &mut best_current);
-            best_current.fill(f64::INFINITY);
-            predecessor_at_hop.fill(None);
+    // Shortest paths from the super-source use at most n - 1 real edges, so anything still
+    // relaxing on pass n sits on (or hangs off) a negative cycle.
+    let mut relaxed_on_last_pass = None;
+    for _ in 0..n {
+        relaxed_on_last_pass = None;
+        for from_node in 0..n {
+            let distance_of_from_node = distance[from_node];
+            graph.for_each_neighbor(from_node, |edge_index, to_node, w| {
+                let d = distance_of_from_node + w;
+                if d < distance[to_node] - EPS {
+                    distance[to_node] = d;
+                    predecessor[to_node] = Some(edge_index);
+                    relaxed_on_last_pass = Some(to_node);
+                }
+            });
+        }
+        // Converged before pass n: no negative cycle.
+        relaxed_on_last_pass?;
+    }
+
+    cycle_through_predecessors(graph, &predecessor, relaxed_on_last_pass?)
+}
+
+/// Queue-based Bellman-Ford (SPFA) from a virtual super-source, suited to large sparse graphs:
+/// only nodes whose distance just improved are re-relaxed. A node relaxed more than `n` times
+/// proves a negative cycle, which is then recovered from the predecessor array. Like
+/// [`find_any_negative_cycle`] it has no hop cap and is not shortest-first.
+pub fn find_profitable_cycle_spfa<G: WeightedGraph>(graph: &G) -> Option<Cycle> {
+    let n = graph.node_count();
+    if n == 0 || graph.edge_count() == 0 {
+        return None;
+    }
+
+    let mut distance = vec![0.0_f64; n];
+    let mut predecessor: Vec<Option<usize>> = vec![None; n];
+    let mut relax_count = vec![0usize; n];
+    let mut queued = vec![true; n];
+    let mut queue: VecDeque<usize> = (0..n).collect();
+
+    while let Some(from_node) = queue.pop_front() {
+        queued[from_node] = false;
+        let distance_of_from_node = distance[from_node];
+        let mut overflowed = None;
+        graph.for_each_neighbor(from_node, |edge_index, to_node, w| {
+            if overflowed.is_some() {
+                return;
+            }
+            let d = distance_of_from_node + w;
+            if d < distance[to_node] - EPS {
+                distance[to_node] = d;
+                predecessor[to_node] = Some(edge_index);
+                relax_count[to_node] += 1;
+                if relax_count[to_node] > n {
+                    overflowed = Some(to_node);
+                } else if !queued[to_node] {
+                    queued[to_node] = true;
+                    queue.push_back(to_node);
+                }
+            }
+        });
+        if let Some(node) = overflowed {
+            return cycle_through_predecessors(graph, &predecessor, node);
         }
     }
 
     None
 }
 
+/// Recover the cycle that `node`'s predecessor chain runs into. Walking back `n` edges first
+/// guarantees we are on the cycle rather than on a tail leading into it.
+fn cycle_through_predecessors<G: WeightedGraph>(
+    graph: &G,
+    predecessor: &[Option<usize>],
+    node: usize,
+) -> Option<Cycle> {
+    let n = graph.node_count();
+    let mut on_cycle = node;
+    for _ in 0..n {
+        on_cycle = graph.edge(predecessor[on_cycle]?).0;
+    }
+
+    let mut used_edges = Vec::new();
+    let mut node = on_cycle;
+    loop {
+        let edge_index = predecessor[node]?;
+        used_edges.push(edge_index);
+        node = graph.edge(edge_index).0;
+        if node == on_cycle {
+            break;
+        }
+        if used_edges.len() > n {
+            return None;
+        }
+    }
+    used_edges.reverse();
+
+    let (vertices, rates, neg_log_sum, profit) = assemble_cycle_metrics(&used_edges, graph)?;
+    if neg_log_sum >= -EPS {
+        return None;
+    }
+    Some(Cycle {
+        vertices,
+        edge_indexes: used_edges,
+        rates,
+        profit,
+        neg_log_sum,
+    })
+}
+
+/// Every simple cycle of at most `max_len` edges, profitable or not, as closed vertex rings
+/// (`s -> ... -> s`, like [`Cycle::vertices`]). Each ring is reported once, rotated to start at
+/// its smallest vertex; the reverse direction is a distinct ring. Stops after `cap` rings.
+pub fn enumerate_simple_cycles<G: WeightedGraph>(
+    graph: &G,
+    max_len: usize,
+    cap: usize,
+) -> Vec<Vec<usize>> {
+    let n = graph.node_count();
+    let mut rings = Vec::new();
+    if n == 0 || max_len == 0 || cap == 0 {
+        return rings;
+    }
+
+    let mut seen = HashSet::new();
+    let mut on_path = vec![false; n];
+    let mut path = Vec::with_capacity(max_len + 1);
+    for start in 0..n {
+        path.push(start);
+        on_path[start] = true;
+        // Only walk through vertices above `start` so each ring is found from its minimum.
+        extend_simple_paths(
+            graph,
+            start,
+            max_len,
+            cap,
+            &mut path,
+            &mut on_path,
+            &mut seen,
+            &mut rings,
+        );
+        on_path[start] = false;
+        path.pop();
+        if rings.len() >= cap {
+            break;
+        }
+    }
+    rings
+}
+
+#[allow(clippy::too_many_arguments)]
+fn extend_simple_paths<G: WeightedGraph>(
+    graph: &G,
+    start: usize,
+    max_len: usize,
+    cap: usize,
+    path: &mut Vec<usize>,
+    on_path: &mut [bool],
+    seen: &mut HashSet<Vec<usize>>,
+    rings: &mut Vec<Vec<usize>>,
+) {
+    let Some(&tail) = path.last() else {
+        return;
+    };
+    let mut next_hops = Vec::new();
+    graph.for_each_neighbor(tail, |_, to_node, _| next_hops.push(to_node));
+
+    for to_node in next_hops {
+        if rings.len() >= cap {
+            return;
+        }
+        if to_node == start {
+            let mut ring = path.clone();
+            ring.push(start);
+            // Parallel edges would otherwise yield the same vertex ring twice.
+            if seen.insert(ring.clone()) {
+                rings.push(ring);
+            }
+        } else if to_node > start && !on_path[to_node] && path.len() < max_len {
+            path.push(to_node);
+            on_path[to_node] = true;
+            extend_simple_paths(graph, start, max_len, cap, path, on_path, seen, rings);
+            on_path[to_node] = false;
+            path.pop();
+        }
+    }
+}
+
+/// `-log_base(min_profit_ratio)` as a detection bound, never looser than the default `-EPS`.
+/// `f64::min` ignores NaN, so a NaN ratio also lands on `-EPS`.
+fn min_profit_neg_log(min_profit_ratio: f64, log_base: LogBase) -> f64 {
+    log_base.cost_for(min_profit_ratio).min(-EPS)
+}
+
+/// Exact-hop DP for a single `start` node; returns the shortest cycle through it whose
+/// `neg_log_sum` is below `max_neg_log_sum` (`-EPS` for "any profit").
+fn find_cycle_from_start<G: WeightedGraph>(
+    graph: &G,
+    start: usize,
+    hop_cap: usize,
+    max_neg_log_sum: f64,
+) -> Option<Cycle> {
+    let mut found = None;
+    for_each_closing_cycle(graph, start, hop_cap, max_neg_log_sum, |cycle| {
+        found = Some(cycle);
+        ControlFlow::Break(())
+    });
+    found
+}
+
+/// Exact-hop DP for a single `start` node, handing `on_cycle` the cheapest closing walk of each
+/// hop count (shortest first) whose `neg_log_sum` is below `max_neg_log_sum`, until it breaks.
+fn for_each_closing_cycle<G: WeightedGraph>(
+    graph: &G,
+    start: usize,
+    hop_cap: usize,
+    max_neg_log_sum: f64,
+    mut on_cycle: impl FnMut(Cycle) -> ControlFlow<()>,
+) {
+    let n = graph.node_count();
+
+    // hop 0: only `start` reachable with cost 0; others are ∞
+    let mut best_previous = vec![f64::INFINITY; n];
+    best_previous[start] = 0.0;
+
+    // Preallocate next-hop buffer and the predecessor-edge buffer (reused each hop).
+    let mut best_current = vec![f64::INFINITY; n];
+    let mut predecessor_at_hop = vec![None; n];
+
+    // History of per-hop predecessors for backtracking (snapshot per hop).
+    // At hop 0 there is no incoming edge.
+    let mut predecessors_by_hop: Vec<Vec<Option<usize>>> = Vec::with_capacity(hop_cap + 1);
+    predecessors_by_hop.push(vec![None; n]);
+
+    for hop in 1..=hop_cap {
+        relax_hop_inplace(
+            graph,
+            &best_previous,
+            &mut best_current,
+            &mut predecessor_at_hop,
+        );
+
+        // Detect cycle: cost to return to `start` after exactly `hop` hops is negative enough.
+        let cost_to_start = best_current[start];
+        if cost_to_start.is_finite() && cost_to_start < max_neg_log_sum {
+            // Reconstruct the cycle of exactly `hop` edges ending at `start`.
+            let cycle =
+                reconstruct_edge_path(hop, start, &predecessors_by_hop, &predecessor_at_hop, graph)
+                    .and_then(|used_edges| {
+                        let (vertices, rates, neg_log_sum, profit) =
+                            assemble_cycle_metrics(&used_edges, graph)?;
+                        debug_assert_eq!(vertices.first(), vertices.last());
+                        Some(Cycle {
+                            vertices,
+                            edge_indexes: used_edges,
+                            rates,
+                            profit,
+                            neg_log_sum,
+                        })
+                    });
+            let Some(cycle) = cycle else {
+                return;
+            };
+            if on_cycle(cycle).is_break() {
+                return;
+            }
+        }
+
+        // Snapshot predecessors for this hop (for backtracking later).
+        predecessors_by_hop.push(predecessor_at_hop.clone());
+
+        // Reuse allocations next round:
+        // - swap best_current <-> best_previous (so `best_previous` holds the latest),
+        // - reset current buffers in place.
+        std::mem::swap(&mut best_previous, &mut best_current);
+        best_current.fill(f64::INFINITY);
+        predecessor_at_hop.fill(None);
+    }
+}
+
 /// In-place relaxation from hop-1 → hop.
 /// - `best_previous` is read-only (costs for exactly h-1 hops).
 /// - `best_current` is overwritten with costs for exactly h hops.
 /// - `predecessor_at_hop[v]` becomes the winning predecessor edge index for (hop, v), or None.
 #[inline]
-fn relax_hop_inplace(
-    graph: &CSRGraph,
+fn relax_hop_inplace<G: WeightedGraph>(
+    graph: &G,
     best_previous: &[f64],
     best_current: &mut [f64],
     predecessor_at_hop: &mut [Option<usize>],
@@ -123,7 +1001,7 @@ fn relax_hop_inplace(
         if !distance_of_from_node.is_finite() {
             continue;
         }
-        for (edge_index, to_node, w) in graph.neighbors(from_node) {
+        graph.for_each_neighbor(from_node, |edge_index, to_node, w| {
             let d = distance_of_from_node + w;
             if d < best_current[to_node] {
                 // Every successful relaxation indicates a new pathway/edge to get the shortest distance from start node
@@ -132,25 +1010,25 @@ fn relax_hop_inplace(
                 best_current[to_node] = d;
                 predecessor_at_hop[to_node] = Some(edge_index); // predecessor (argmin) for (hop, v)
             }
-        }
+        });
     }
 }
 
 /// Backtrack exactly `hop` steps along predecessor edges to recover the edge sequence
 /// (in forward order) that ends at `end_node` after `hop` hops.
 #[inline]
-fn reconstruct_edge_path(
+fn reconstruct_edge_path<G: WeightedGraph>(
     mut hop: usize,
     mut end_node: usize, // here `end_node` is the start node (cycle end/start)
     predecessors_by_hop: &[Vec<Option<usize>>],
     predecessor_at_hop: &[Option<usize>],
-    graph: &CSRGraph,
+    graph: &G,
 ) -> Option<Vec<usize>> {
     let mut used = Vec::with_capacity(hop);
     while hop > 0 {
         let ei = predecessor_edge_at_hop(predecessors_by_hop, predecessor_at_hop, hop, end_node)?;
         used.push(ei);
-        end_node = graph.edge_src(ei);
+        (end_node, _, _) = graph.edge(ei);
         hop -= 1;
     }
     used.reverse();
@@ -174,29 +1052,54 @@ fn predecessor_edge_at_hop(
     }
 }
 
+/// Edge sequence rotated to start at the lowest vertex id (smallest such rotation if that
+/// vertex repeats), so rotations of one cycle compare equal.
+pub(crate) fn canonical_edge_ring(cycle: &Cycle) -> Vec<usize> {
+    let len = cycle.edge_indexes.len();
+    let ring = &cycle.vertices[..len];
+    let Some(&lowest) = ring.iter().min() else {
+        return Vec::new();
+    };
+    (0..len)
+        .filter(|&i| ring[i] == lowest)
+        .map(|i| {
+            let mut rotated = Vec::with_capacity(len);
+            rotated.extend_from_slice(&cycle.edge_indexes[i..]);
+            rotated.extend_from_slice(&cycle.edge_indexes[..i]);
+            rotated
+        })
+        .min()
+        .unwrap_or_default()
+}
+
 /// Convert used edge IDs into vertex ring and metrics (neg_log_sum, profit).
 #[inline]
-fn assemble_cycle_metrics(
+fn assemble_cycle_metrics<G: WeightedGraph>(
     used_edges: &[usize],
-    graph: &CSRGraph,
-) -> Option<(Vec<usize>, f64, f64)> {
+    graph: &G,
+) -> Option<(Vec<usize>, Vec<f64>, f64, f64)> {
     if used_edges.is_empty() {
         return None;
     }
     let mut vertices = Vec::with_capacity(used_edges.len() + 1);
-    vertices.push(graph.edge_src(used_edges[0]));
+    let mut rates = Vec::with_capacity(used_edges.len());
 
     let mut neg_log_sum = 0.0_f64;
     for &ei in used_edges {
-        let v2 = graph.edge_dst(ei);
+        let (v1, v2, _) = graph.edge(ei);
+        if vertices.is_empty() {
+            vertices.push(v1);
+        }
         vertices.push(v2);
-        neg_log_sum += graph.weights_in_neglog[ei];
+        // Derive from the working weight so per-edge fees are reflected like in `profit`.
+        rates.push(graph.log_base().rate_for(graph.weight(ei)));
+        neg_log_sum += graph.weight(ei);
     }
-    let profit = (-neg_log_sum).exp();
+    let profit = graph.log_base().rate_for(neg_log_sum);
     if !profit.is_finite() {
         return None;
     }
-    Some((vertices, neg_log_sum, profit))
+    Some((vertices, rates, neg_log_sum, profit))
 }
 
 #[cfg(test)]
@@ -324,4 +1227,703 @@ mod tests {
         let cyc = find_profitable_cycle_with_graph(&graph, 8).expect("should find");
         assert!(cyc.profit > 1.0);
     }
+
+    #[test]
+    fn prefer_recent_returns_cycle_touching_recent_edge() {
+        let n = 4;
+        let edges = vec![
+            (0, 1, 1.05),
+            (1, 0, 1.05), // 0 <-> 1: product ≈ 1.1025
+            (2, 3, 1.02),
+            (3, 2, 1.02), // 2 <-> 3: product ≈ 1.0404 (less profitable)
+        ];
+        let graph = CSRGraph::from_edges(n, edges);
+
+        let default = find_profitable_cycle_with_graph(&graph, 4).expect("should find");
+        assert!(default.edge_indexes.contains(&0));
+
+        let recent: HashSet<usize> = [3].into_iter().collect();
+        let cyc = find_profitable_cycle_prefer_recent(&graph, 4, &recent).expect("should find");
+        assert!(cyc.edge_indexes.contains(&3));
+        assert!(cyc.profit < default.profit);
+
+        let unrelated: HashSet<usize> = [42].into_iter().collect();
+        let fallback =
+            find_profitable_cycle_prefer_recent(&graph, 4, &unrelated).expect("should fall back");
+        assert_eq!(fallback.edge_indexes, default.edge_indexes);
+    }
+
+    #[test]
+    fn dense_and_indirect_layouts_find_identical_cycles() {
+        let n = 101;
+        let edges = vec![
+            (83, 40, 1.011538),
+            (40, 22, 1.006524),
+            (22, 83, 1.00674),
+            (90, 71, 1.003291),
+            (71, 88, 1.008421),
+            (88, 90, 1.013105),
+            (40, 90, 0.97),
+        ];
+        let indirect = CSRGraph::from_edges(n, edges.clone());
+        let dense = CSRGraph::from_edges(n, edges).with_dense_adjacency();
+
+        for hop_cap in 1..=4 {
+            let a = find_profitable_cycle_with_graph(&indirect, hop_cap);
+            let b = find_profitable_cycle_with_graph(&dense, hop_cap);
+            assert_eq!(
+                a.as_ref().map(|c| &c.edge_indexes),
+                b.as_ref().map(|c| &c.edge_indexes)
+            );
+            assert_eq!(a.map(|c| c.neg_log_sum), b.map(|c| c.neg_log_sum));
+        }
+    }
+
+    #[test]
+    fn simulate_compounds_captured_rates() {
+        let edges = [(0, 1, 1.02), (1, 2, 1.03), (2, 0, 0.97)];
+        let cyc = find_profitable_cycle(3, &edges, 3).expect("should find");
+
+        assert_eq!(cyc.rates.len(), cyc.edge_indexes.len());
+        assert!((cyc.simulate(1.0) - cyc.profit).abs() < 1e-9);
+        assert!((cyc.simulate(1000.0) - 1000.0 * cyc.profit).abs() < 1e-6);
+    }
+
+    #[test]
+    fn profit_margin_reads_as_percent() {
+        let edges = [(0, 1, 1.02), (1, 0, 1.0)];
+        let cyc = find_profitable_cycle(2, &edges, 2).expect("should find");
+
+        assert!((cyc.profit_margin_pct() - 2.0).abs() < 1e-9);
+        assert!(cyc.is_profitable_above(1.01));
+        assert!(!cyc.is_profitable_above(1.03));
+    }
+
+    #[test]
+    fn full_bellman_ford_finds_cycle_beyond_hop_cap() {
+        let edges = vec![
+            (0, 1, 1.01),
+            (1, 2, 1.01),
+            (2, 3, 1.01),
+            (3, 4, 1.01),
+            (4, 0, 1.01),
+            (2, 0, 0.5),
+        ];
+        let graph = CSRGraph::from_edges(5, edges);
+
+        assert!(find_profitable_cycle_with_graph(&graph, 4).is_none());
+
+        let cyc = find_any_negative_cycle(&graph).expect("5-cycle is profitable");
+        assert_eq!(cyc.edge_indexes.len(), 5);
+        assert_eq!(cyc.vertices.first(), cyc.vertices.last());
+        assert!((cyc.profit - 1.01_f64.powi(5)).abs() < 1e-9);
+
+        let acyclic = CSRGraph::from_edges(3, vec![(0, 1, 2.0), (1, 2, 2.0)]);
+        assert!(find_any_negative_cycle(&acyclic).is_none());
+    }
+
+    #[test]
+    fn spfa_agrees_with_full_bellman_ford() {
+        let profitable = CSRGraph::from_edges(
+            5,
+            vec![
+                (0, 1, 1.01),
+                (1, 2, 1.01),
+                (2, 3, 1.01),
+                (3, 4, 1.01),
+                (4, 0, 1.01),
+                (2, 0, 0.5),
+            ],
+        );
+        let cyc = find_profitable_cycle_spfa(&profitable).expect("5-cycle is profitable");
+        assert_eq!(cyc.edge_indexes.len(), 5);
+        assert!(cyc.profit > 1.0);
+        assert_eq!(cyc.vertices.first(), cyc.vertices.last());
+
+        let fair = CSRGraph::from_edges(3, vec![(0, 1, 2.0), (1, 2, 0.5), (2, 0, 0.99)]);
+        assert!(find_profitable_cycle_spfa(&fair).is_none());
+        assert!(find_any_negative_cycle(&fair).is_none());
+    }
+
+    #[test]
+    fn directional_profits_cover_both_directions() {
+        let graph = CSRGraph::from_edges(
+            3,
+            vec![
+                (0, 1, 1.02),
+                (1, 2, 1.02),
+                (2, 0, 0.98),
+                (1, 0, 0.97),
+                (2, 1, 0.97),
+                (0, 2, 1.01),
+            ],
+        );
+        let cyc = find_profitable_cycle_with_graph(&graph, 3).expect("forward is profitable");
+
+        let (forward, reverse) = cyc.directional_profits(&graph);
+        assert!((forward - cyc.profit).abs() < 1e-12);
+        assert!(forward > 1.0);
+        assert!(reverse.expect("all reverse legs exist") < 1.0);
+
+        let one_way = CSRGraph::from_edges(3, vec![(0, 1, 1.02), (1, 2, 1.02), (2, 0, 0.98)]);
+        let cyc = find_profitable_cycle_with_graph(&one_way, 3).expect("forward is profitable");
+        assert_eq!(cyc.directional_profits(&one_way).1, None);
+    }
+
+    #[test]
+    fn min_profit_ratio_filters_thin_cycles() {
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 1.002), (1, 2, 1.0), (2, 0, 1.0)]);
+
+        assert!(find_profitable_cycle_with_min_profit(&graph, 3, 1.005).is_none());
+        let cyc = find_profitable_cycle_with_min_profit(&graph, 3, 1.001).expect("0.2% > 0.1%");
+        assert!((cyc.profit - 1.002).abs() < 1e-12);
+        assert!(find_profitable_cycle_with_min_profit(&graph, 3, f64::NAN).is_some());
+    }
+
+    #[test]
+    fn risk_adjusted_profit_penalises_volatile_legs() {
+        let edges = [(0, 1, 1.02), (1, 2, 1.02), (2, 0, 0.98)];
+        let cyc = find_profitable_cycle(3, &edges, 3).expect("should find");
+        let raw_profit = cyc.profit;
+
+        let calm = cyc.risk_adjusted_profit(&[0.001, 0.001, 0.001], 1.0);
+        let volatile = cyc.risk_adjusted_profit(&[0.001, 0.01, 0.001], 1.0);
+
+        assert!((cyc.risk_adjusted_profit(&[], 1.0) - (raw_profit - 1.0)).abs() < 1e-12);
+        assert!(volatile < calm);
+        assert_eq!(cyc.profit, raw_profit);
+    }
+
+    #[test]
+    fn enumerates_each_simple_cycle_once() {
+        let one_way = CSRGraph::from_edges(3, vec![(0, 1, 1.0), (1, 2, 1.0), (2, 0, 0.5)]);
+        assert_eq!(
+            enumerate_simple_cycles(&one_way, 3, 10),
+            vec![vec![0, 1, 2, 0]]
+        );
+        assert!(enumerate_simple_cycles(&one_way, 2, 10).is_empty());
+
+        let both_ways = CSRGraph::from_edges(
+            3,
+            vec![
+                (0, 1, 1.0),
+                (1, 2, 1.0),
+                (2, 0, 1.0),
+                (0, 2, 1.0),
+                (2, 1, 1.0),
+                (1, 0, 1.0),
+            ],
+        );
+        let mut rings = enumerate_simple_cycles(&both_ways, 3, 10);
+        rings.sort();
+        assert_eq!(
+            rings,
+            vec![
+                vec![0, 1, 0],
+                vec![0, 1, 2, 0],
+                vec![0, 2, 0],
+                vec![0, 2, 1, 0],
+                vec![1, 2, 1],
+            ]
+        );
+        assert_eq!(enumerate_simple_cycles(&both_ways, 3, 2).len(), 2);
+    }
+
+    #[test]
+    fn priority_nodes_are_swept_first() {
+        let graph = CSRGraph::from_edges(
+            6,
+            vec![
+                (0, 1, 1.02),
+                (1, 2, 1.02),
+                (2, 0, 0.98),
+                (3, 4, 1.02),
+                (4, 5, 1.02),
+                (5, 3, 0.98),
+            ],
+        );
+
+        let default_order = find_profitable_cycle_starting_with(&graph, 3, 1.0, &[])
+            .expect("both triangles are profitable");
+        assert!(default_order.vertices.contains(&0));
+
+        let recent_first = find_profitable_cycle_starting_with(&graph, 3, 1.0, &[4, 99])
+            .expect("both triangles are profitable");
+        assert_eq!(recent_first.vertices.first(), Some(&4));
+    }
+
+    #[test]
+    fn profit_rounds_to_tick() {
+        let cyc = Cycle {
+            vertices: vec![0, 1, 0],
+            edge_indexes: vec![0, 1],
+            rates: vec![1.01967, 1.0],
+            profit: 1.01967,
+            neg_log_sum: -(1.01967_f64.ln()),
+        };
+
+        assert!((cyc.profit_rounded(0.0001) - 1.0197).abs() < 1e-12);
+        assert_eq!(cyc.profit_rounded(0.0), cyc.profit);
+        assert_eq!(cyc.profit_rounded(-0.01), cyc.profit);
+    }
+
+    #[test]
+    fn finds_top_k_cycles_across_disjoint_triangles() {
+        let n = 6;
+        let edges = vec![
+            (0, 1, 1.01),
+            (1, 2, 1.01),
+            (2, 0, 1.01), // ≈ 1.0303
+            (3, 4, 1.05),
+            (4, 5, 1.05),
+            (5, 3, 1.05), // ≈ 1.1576
+        ];
+        let graph = CSRGraph::from_edges(n, edges);
+
+        let cycles = find_profitable_cycles(&graph, 4, 5);
+        assert_eq!(cycles.len(), 2, "rotations must be deduplicated");
+        assert!(cycles[0].neg_log_sum <= cycles[1].neg_log_sum);
+        assert!(cycles[0].vertices.contains(&3));
+        assert!(cycles[1].vertices.contains(&0));
+
+        let single = find_profitable_cycles(&graph, 4, 1);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].edge_indexes, cycles[0].edge_indexes);
+        assert!(find_profitable_cycles(&graph, 4, 0).is_empty());
+    }
+
+    #[test]
+    fn top_k_sweeps_past_the_first_k_start_nodes() {
+        let edges = vec![
+            (0, 1, 1.01),
+            (1, 0, 1.0), // ≈ 1.01
+            (2, 3, 1.02),
+            (3, 2, 1.0), // ≈ 1.02
+            (4, 5, 1.5),
+            (5, 4, 1.0), // 1.5, only reachable from the last start nodes
+        ];
+        let graph = CSRGraph::from_edges(6, edges);
+
+        let cycles = find_profitable_cycles(&graph, 3, 2);
+        assert_eq!(cycles.len(), 2);
+        assert!((cycles[0].profit - 1.5).abs() < 1e-12);
+        assert!((cycles[1].profit - 1.02).abs() < 1e-12);
+
+        let best = find_profitable_cycles(&graph, 3, 1);
+        assert_eq!(best[0].edge_indexes, cycles[0].edge_indexes);
+    }
+
+    #[test]
+    fn top_k_ranks_cycles_that_are_never_the_shortest_from_any_start() {
+        // Each vertex of the rich triangle 0 -> 2 -> 3 -> 0 also closes a thin 2-cycle first.
+        let edges = vec![
+            (0, 2, 1.2),
+            (2, 3, 1.0),
+            (3, 0, 1.0), // 1.2
+            (0, 1, 1.01),
+            (1, 0, 1.0),
+            (2, 4, 1.01),
+            (4, 2, 1.0),
+            (3, 5, 1.01),
+            (5, 3, 1.0),
+        ];
+        let graph = CSRGraph::from_edges(6, edges);
+
+        let cycles = find_profitable_cycles(&graph, 4, 2);
+        assert_eq!(cycles.len(), 2);
+        assert!((cycles[0].profit - 1.2).abs() < 1e-12);
+        assert_eq!(cycles[0].edge_indexes.len(), 3);
+        // Looping a 2-cycle twice is not reported as a cycle of its own.
+        assert!(find_profitable_cycles(&graph, 4, 10)
+            .iter()
+            .all(|cycle| cycle.edge_indexes.len() <= 3));
+    }
+
+    #[test]
+    fn finds_profitable_cycle_through_borrowed_view() {
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 1.02), (1, 2, 1.02), (2, 0, 0.98)]);
+
+        let owned = find_profitable_cycle_with_graph(&graph, 8).expect("should find");
+        let viewed = find_profitable_cycle_with_graph(&graph.view(), 8).expect("should find");
+        assert_eq!(owned.edge_indexes, viewed.edge_indexes);
+        assert_eq!(owned.neg_log_sum, viewed.neg_log_sum);
+    }
+
+    #[test]
+    fn finds_cycles_after_adding_and_removing_edges() {
+        let mut graph = CSRGraph::from_edges(3, vec![(0, 1, 1.02), (1, 2, 1.02)]);
+        assert!(find_profitable_cycle_with_graph(&graph, 4).is_none());
+
+        let closing = graph.add_edge(2, 0, 0.98).unwrap();
+        let cyc = find_profitable_cycle_with_graph(&graph, 4).expect("added edge closes cycle");
+        assert!(cyc.edge_indexes.contains(&closing));
+
+        graph.remove_edge(closing).unwrap();
+        assert!(find_profitable_cycle_with_graph(&graph, 4).is_none());
+    }
+
+    #[test]
+    fn breakeven_rate_restores_unit_profit() {
+        let edges = vec![(0, 1, 1.01), (1, 2, 1.0), (2, 0, 0.995), (1, 0, 0.5)];
+        let mut graph = CSRGraph::from_edges(3, edges);
+        let cyc = find_profitable_cycle_with_graph(&graph, 3).expect("≈1.005 triangle");
+        assert_eq!(cyc.edge_indexes.len(), 3);
+
+        let target = cyc
+            .breakeven_rate_for(&graph, 2)
+            .expect("edge 2 is on the cycle");
+        assert!(cyc.breakeven_rate_for(&graph, 3).is_none());
+
+        graph.update_rate(2, target).unwrap();
+        let product: f64 = cyc
+            .edge_indexes
+            .iter()
+            .map(|&ei| graph.edge_rate(ei))
+            .product();
+        assert!((product - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn breakeven_rate_accounts_for_fees_and_log_base() {
+        let edges = vec![(0, 1, 1.02), (1, 2, 1.0), (2, 0, 1.0)];
+        let mut graph = CSRGraph::from_edges_with_base(3, edges, LogBase::Two);
+        graph.update_fee(0, 30.0).unwrap();
+        graph.update_fee(2, 50.0).unwrap();
+        let cyc = find_profitable_cycle_with_graph(&graph, 3).expect("≈1.012 net triangle");
+
+        let target = cyc.breakeven_rate_for(&graph, 2).expect("on the cycle");
+        graph.update_rate(2, target).unwrap();
+        let net_cost: f64 = cyc.edge_indexes.iter().map(|&ei| graph.weight(ei)).sum();
+        assert!(net_cost.abs() < 1e-12);
+        // Gross product alone would still clear 1: the fees are what the target makes up for.
+        assert!(1.02 * target > 1.0);
+    }
+
+    #[test]
+    fn fees_flip_marginal_cycle_to_unprofitable() {
+        let n = 3;
+        let edges = [(0, 1, 1.002), (1, 2, 1.002), (2, 0, 1.002)]; // gross ≈ 1.006
+
+        let gross = find_profitable_cycle_with_fees(n, &edges, &[0.0; 3], 4)
+            .expect("profitable before fees");
+        assert!(gross.profit > 1.0);
+        assert!(find_profitable_cycle_with_fees(n, &edges, &[30.0; 3], 4).is_none());
+
+        assert!(find_profitable_cycle_with_fees(n, &edges, &[30.0; 2], 4).is_none());
+        assert!(find_profitable_cycle_with_fees(n, &edges, &[-1.0, 0.0, 0.0], 4).is_none());
+    }
+
+    #[test]
+    fn best_conversion_rates_follow_direct_edges_and_round_trips() {
+        let edges = vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98)];
+        let graph = CSRGraph::from_edges(3, edges);
+
+        let rates = best_conversion_rates(&graph, 3);
+        assert!((rates[0][1] - 1.10).abs() < 1e-12);
+        assert!((rates[0][2] - 1.10 * 1.05).abs() < 1e-12);
+        assert!(rates[0][0] > 1.0, "triangle is a self-arbitrage loop");
+
+        let one_hop = best_conversion_rates(&graph, 1);
+        assert_eq!(one_hop[0][2], 0.0, "two hops needed to reach C");
+    }
+
+    #[test]
+    fn scc_pruned_search_skips_isolated_nodes_but_finds_cycle() {
+        // Nodes 0..=2 dangle off the 3-cycle 3 -> 4 -> 5 -> 3; node 6 is isolated.
+        let graph = CSRGraph::from_edges(
+            7,
+            vec![
+                (0, 1, 2.0),
+                (1, 3, 2.0),
+                (2, 3, 0.5),
+                (3, 4, 1.1),
+                (4, 5, 1.1),
+                (5, 3, 1.1),
+            ],
+        );
+
+        let cycle = find_profitable_cycle_scc_pruned(&graph, 4).expect("cycle inside the SCC");
+        let expected = find_profitable_cycle_with_graph(&graph, 4).expect("unpruned cycle");
+        assert_eq!(cycle.vertices, expected.vertices);
+        assert_eq!(cycle.edge_indexes.len(), 3);
+        assert!((cycle.profit - 1.1f64.powi(3)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn arbitrage_index_is_log_of_best_round_trip() {
+        let triangle = CSRGraph::from_edges(3, vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98)]);
+        let index = arbitrage_index(&triangle, 3);
+        assert!(index > 0.0);
+        assert!((index - (1.10f64 * 1.05 * 0.98).ln()).abs() < 1e-12);
+
+        // Room for the triangle three times over: the looped walk counts.
+        assert!((arbitrage_index(&triangle, 9) - 3.0 * index).abs() < 1e-12);
+        assert!((arbitrage_index(&triangle, 5) - index).abs() < 1e-12);
+
+        let acyclic = CSRGraph::from_edges(3, vec![(0, 1, 1.10), (1, 2, 1.20)]);
+        assert_eq!(arbitrage_index(&acyclic, 6), 0.0);
+        assert_eq!(arbitrage_index(&triangle, 0), 0.0);
+    }
+
+    #[test]
+    fn gas_turns_marginal_cycle_unprofitable() {
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 1.01), (1, 2, 1.0), (2, 0, 1.0)]);
+        let cycle = find_profitable_cycle_with_graph(&graph, 3).expect("1% cycle");
+
+        let gross = cycle.net_profit(1_000.0, 0.0, &graph);
+        assert!((gross - 10.0).abs() < 1e-9);
+        assert!(cycle.net_profit(1_000.0, 3.0, &graph) > 0.0);
+        assert!(cycle.net_profit(1_000.0, 4.0, &graph) < 0.0);
+        // Gas is fixed per hop, so a larger trade can carry it again.
+        assert!(cycle.net_profit(10_000.0, 4.0, &graph) > 0.0);
+    }
+
+    #[test]
+    fn objective_picks_shortest_or_most_profitable_cycle() {
+        let graph = CSRGraph::from_edges(
+            6,
+            vec![
+                // 2-cycle returning 1%.
+                (0, 1, 1.01),
+                (1, 0, 1.0),
+                // 4-cycle returning ~20%.
+                (2, 3, 1.2),
+                (3, 4, 1.0),
+                (4, 5, 1.0),
+                (5, 2, 1.0),
+            ],
+        );
+
+        let shortest =
+            find_profitable_cycle_with_objective(&graph, 4, SearchObjective::ShortestProfitable)
+                .expect("profitable cycle");
+        assert_eq!(shortest.edge_indexes.len(), 2);
+
+        let most = find_profitable_cycle_with_objective(&graph, 4, SearchObjective::MostProfitable)
+            .expect("profitable cycle");
+        assert_eq!(most.edge_indexes.len(), 4);
+        assert!((most.profit - 1.2).abs() < 1e-12);
+
+        // Under a cap that cannot close the 4-cycle, the 2-cycle is the best available.
+        let capped =
+            find_profitable_cycle_with_objective(&graph, 3, SearchObjective::MostProfitable)
+                .expect("profitable cycle");
+        assert_eq!(capped.vertices[..2], [0, 1]);
+    }
+
+    #[test]
+    fn most_profitable_is_not_hidden_by_looped_walks() {
+        // A 4-cycle of 1.07 legs (≈ 1.311) whose every node also has a 1.2 spoke round trip.
+        // Looping a spoke twice (1.44) beats the 4-cycle at four hops from every start node.
+        let mut edges: Vec<InputEdge> = (0..4).map(|i| (i, (i + 1) % 4, 1.07)).collect();
+        for i in 0..4 {
+            edges.push((i, 4 + i, 1.2));
+            edges.push((4 + i, i, 1.0));
+        }
+        let graph = CSRGraph::from_edges(8, edges);
+
+        let most = find_profitable_cycle_with_objective(&graph, 4, SearchObjective::MostProfitable)
+            .expect("profitable cycle");
+        assert_eq!(most.edge_indexes, vec![0, 1, 2, 3]);
+        assert!((most.profit - 1.07f64.powi(4)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tightest_pool_bounds_feasible_input() {
+        // A -> B at 2000 (A = ETH, B = USDC), B -> C at 1.0, C -> A at 0.00051.
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 2_000.0), (1, 2, 1.0), (2, 0, 0.000_51)]);
+        let cycle = find_profitable_cycle_with_graph(&graph, 3).expect("2% cycle");
+        assert_eq!(cycle.edge_indexes, vec![0, 1, 2]);
+
+        assert_eq!(cycle.max_feasible_input(&graph, &[]), f64::INFINITY);
+
+        // First leg alone would allow 100 ETH, but the middle pool only takes 5000 USDC, which
+        // is what 2.5 ETH turns into.
+        let liquidity = [100.0, 5_000.0, 1e9];
+        let max_input = cycle.max_feasible_input(&graph, &liquidity);
+        assert!((max_input - 2.5).abs() < 1e-12);
+        assert!(max_input < liquidity[0]);
+    }
+
+    #[test]
+    fn profit_is_invariant_to_log_base() {
+        let edges = vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98), (1, 0, 0.80)];
+        let expected = 1.10 * 1.05 * 0.98;
+
+        let natural = find_profitable_cycle_with_graph(&CSRGraph::from_edges(3, edges.clone()), 3)
+            .expect("profitable cycle");
+        for base in [LogBase::E, LogBase::Two, LogBase::Ten] {
+            let graph = CSRGraph::from_edges_with_base(3, edges.clone(), base);
+            let cycle = find_profitable_cycle_with_graph(&graph, 3).expect("profitable cycle");
+            assert_eq!(cycle.edge_indexes, natural.edge_indexes);
+            assert!((cycle.profit - expected).abs() < 1e-12, "{base:?}");
+            assert!((cycle.neg_log_sum * base.ln() - natural.neg_log_sum).abs() < 1e-12);
+            for (rate, natural_rate) in cycle.rates.iter().zip(&natural.rates) {
+                assert!((rate - natural_rate).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn single_start_search_is_anchored_at_start() {
+        // Profitable triangle 0 -> 1 -> 2 -> 0; node 3 only has a losing round trip with 0.
+        let graph = CSRGraph::from_edges(
+            4,
+            vec![
+                (0, 1, 1.10),
+                (1, 2, 1.05),
+                (2, 0, 0.98),
+                (0, 3, 1.0),
+                (3, 0, 0.9),
+            ],
+        );
+
+        let cycle = find_profitable_cycle_from_start(&graph, 1, 4).expect("cycle through 1");
+        assert_eq!(cycle.vertices, vec![1, 2, 0, 1]);
+        assert_eq!(cycle.edge_indexes, vec![1, 2, 0]);
+        assert_eq!(find_profitable_cycle_from_start(&graph, 3, 4), None);
+        assert_eq!(find_profitable_cycle_from_start(&graph, 9, 4), None);
+    }
+
+    #[test]
+    fn restricted_and_reordered_sweeps_agree_with_single_starts() {
+        let graph = CSRGraph::from_edges(
+            4,
+            vec![
+                (0, 1, 1.10),
+                (1, 2, 1.05),
+                (2, 0, 0.98),
+                (0, 3, 1.0),
+                (3, 0, 0.9),
+            ],
+        );
+        let from_two = find_profitable_cycle_from_start(&graph, 2, 4);
+        assert!(from_two.is_some());
+
+        // Out-of-range, repeated and cycle-less starts are passed over in order.
+        assert_eq!(
+            find_profitable_cycle_from_starts(&graph, 4, 1.0, &[9, 3, 2, 3]),
+            from_two
+        );
+        assert_eq!(
+            find_profitable_cycle_starting_with(&graph, 4, 1.0, &[3, 2]),
+            from_two
+        );
+        assert_eq!(
+            find_profitable_cycle_prefer_recent(&graph, 4, &HashSet::new()),
+            find_profitable_cycle_with_graph(&graph, 4)
+        );
+    }
+
+    #[test]
+    fn signature_ignores_rotation() {
+        let triangle = vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98), (1, 0, 0.80)];
+        let graph = CSRGraph::from_edges(3, triangle);
+        let from_zero = find_profitable_cycle_from_start(&graph, 0, 3).expect("cycle");
+        let from_two = find_profitable_cycle_from_start(&graph, 2, 3).expect("cycle");
+        assert_ne!(from_zero.edge_indexes, from_two.edge_indexes);
+        assert_eq!(from_zero.signature(), from_two.signature());
+
+        let other = Cycle {
+            edge_indexes: vec![0, 3],
+            ..from_zero.clone()
+        };
+        assert_ne!(other.signature(), from_zero.signature());
+
+        // Three loops through node 0 taken in another order use the same edge set, so they
+        // share a signature; only their canonical rings tell the routes apart.
+        let loops = |vertices: Vec<usize>, edge_indexes: Vec<usize>| Cycle {
+            vertices,
+            edge_indexes,
+            ..from_zero.clone()
+        };
+        let forward = loops(vec![0, 1, 0, 2, 0, 3, 0], vec![0, 1, 2, 3, 4, 5]);
+        let swapped = loops(vec![0, 1, 0, 3, 0, 2, 0], vec![0, 1, 4, 5, 2, 3]);
+        let rotated = loops(vec![0, 2, 0, 3, 0, 1, 0], vec![2, 3, 4, 5, 0, 1]);
+        assert_eq!(forward.signature(), swapped.signature());
+        assert_eq!(forward.signature(), rotated.signature());
+        assert_ne!(canonical_edge_ring(&forward), canonical_edge_ring(&swapped));
+        assert_eq!(canonical_edge_ring(&forward), canonical_edge_ring(&rotated));
+    }
+
+    #[test]
+    fn eps_threshold_is_configurable() {
+        // A two-hop round trip with neg_log_sum = -5e-10.
+        let graph = CSRGraph::from_edges(2, vec![(0, 1, 5e-10f64.exp()), (1, 0, 1.0)]);
+
+        let cycle = find_profitable_cycle_with_config(&graph, 2, FinderConfig::default())
+            .expect("above the default 1e-12 threshold");
+        assert!((cycle.neg_log_sum + 5e-10).abs() < 1e-15);
+        assert_eq!(
+            find_profitable_cycle_with_config(&graph, 2, FinderConfig { eps: 1e-9 }),
+            None
+        );
+    }
+
+    #[test]
+    fn stale_edges_are_excluded_from_cycles() {
+        let mut graph = CSRGraph::from_edges(
+            3,
+            vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98), (1, 0, 0.80)],
+        );
+        // Edges 0 and 1 keep ticking; the closing edge 2 -> 0 never updates.
+        for _ in 0..3 {
+            graph.update_rate(0, 1.10).unwrap();
+            graph.update_rate(1, 1.05).unwrap();
+        }
+        let tick = graph.current_tick();
+        assert_eq!(tick, 6);
+        assert_eq!(graph.last_updated(1), 6);
+        assert_eq!(graph.stale_edges(tick, 4), vec![2, 3]);
+        assert!(graph.stale_edges(tick, 6).is_empty());
+
+        let cycle = find_profitable_cycle_excluding_stale(&graph, 3, tick, 6)
+            .expect("fresh enough to trade");
+        assert_eq!(cycle.edge_indexes, vec![0, 1, 2]);
+        assert!(find_profitable_cycle_with_graph(&graph, 3).is_some());
+        assert_eq!(
+            find_profitable_cycle_excluding_stale(&graph, 3, tick, 4),
+            None
+        );
+    }
+
+    #[test]
+    fn soa_built_graph_finds_identical_cycles() {
+        use crate::edge_layouts::EdgeSoA;
+
+        let edges = vec![
+            (0, 1, 1.10),
+            (1, 2, 1.05),
+            (2, 0, 0.98),
+            (1, 3, 0.99),
+            (3, 0, 1.02),
+            (2, 3, 1.01),
+        ];
+        let mut soa = EdgeSoA::with_capacity(edges.len());
+        for &(from, to, rate) in &edges {
+            soa.push(from, to, rate);
+        }
+        let from_tuples = CSRGraph::from_edges(4, edges);
+        let from_columns = CSRGraph::from_soa(4, soa);
+
+        for hop_cap in 2..=4 {
+            let expected = find_profitable_cycle_with_graph(&from_tuples, hop_cap);
+            let actual = find_profitable_cycle_with_graph(&from_columns, hop_cap);
+            assert_eq!(
+                actual
+                    .as_ref()
+                    .map(|c| (&c.edge_indexes, &c.vertices, c.neg_log_sum)),
+                expected
+                    .as_ref()
+                    .map(|c| (&c.edge_indexes, &c.vertices, c.neg_log_sum)),
+            );
+        }
+        let expected = find_profitable_cycles(&from_tuples, 4, 8);
+        let actual = find_profitable_cycles(&from_columns.view(), 4, 8);
+        let routes = |cycles: &[Cycle]| -> Vec<Vec<usize>> {
+            cycles.iter().map(|c| c.edge_indexes.clone()).collect()
+        };
+        assert!(!expected.is_empty());
+        assert_eq!(routes(&actual), routes(&expected));
+    }
 }
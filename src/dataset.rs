@@ -1,16 +1,34 @@
-use crate::error::DatasetError;
-use serde::Deserialize;
-use std::{fs::File, path::Path};
+use crate::{csr_graph::CSRGraph, error::DatasetError};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+use thiserror::Error;
 
 pub const DEFAULT_DATASET_PATH: &str = "datasets/dataset.json";
 
-#[derive(Debug, Deserialize, Clone)]
+/// [`Edge::kind`] marking a pool row that trades both ways; with
+/// [`crate::pipeline::PipelineConfig::expand_bidirectional`] it also yields the reverse edge at
+/// `1 / rate`.
+pub const BIDIRECTIONAL_EDGE_KIND: u8 = 2;
+
+/// [`Edge::kind`] of the reverse edge synthesized for a [`BIDIRECTIONAL_EDGE_KIND`] row;
+/// [`Dataset::from_graph`] drops these so the rebuilt dataset is not expanded twice.
+pub const REVERSED_EDGE_KIND: u8 = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Token {
     pub id: u64,
     pub symbol: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Edge {
     pub id: u64,
     pub from: u64,
@@ -20,15 +38,39 @@ pub struct Edge {
     pub kind: u8,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Dataset {
     pub tokens: Vec<Token>,
     pub edges: Vec<Edge>,
 }
 
+/// Non-fatal issue reported by [`Dataset::validate`].
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DatasetWarning {
+    #[error("edge id {edge_id} is used by {count} edges")]
+    DuplicateEdgeId { edge_id: u64, count: usize },
+    #[error("edge {edge_id} loops from token {token_id} to itself")]
+    SelfLoop { edge_id: u64, token_id: u64 },
+    #[error("edge {edge_id} has invalid rate {rate}")]
+    InvalidRate { edge_id: u64, rate: f64 },
+    #[error("edge {edge_id} references unknown token {token_id}")]
+    UnknownToken { edge_id: u64, token_id: u64 },
+    #[error("token {token_id} is not referenced by any edge")]
+    UnreferencedToken { token_id: u64 },
+}
+
 impl Dataset {
+    /// Load a JSON dataset. With the `gzip` feature, a path ending in `.gz` is decompressed
+    /// on the fly (see [`Dataset::load_from_path_gz`]).
     pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, DatasetError> {
         let path_ref = path.as_ref();
+        #[cfg(feature = "gzip")]
+        if path_ref
+            .extension()
+            .is_some_and(|extension| extension == "gz")
+        {
+            return Self::load_from_path_gz(path_ref);
+        }
         let path_buf = path_ref.to_path_buf();
         let file = File::open(path_ref).map_err(|source| DatasetError::Open {
             path: path_buf.clone(),
@@ -39,8 +81,905 @@ impl Dataset {
             source,
         })
     }
+
+    /// Load a gzip-compressed JSON dataset, whatever its extension. A damaged or non-gzip
+    /// stream is reported as [`DatasetError::Decompress`] and any other I/O failure as
+    /// [`DatasetError::Read`]; valid gzip holding bad JSON is still
+    /// [`DatasetError::Deserialize`].
+    #[cfg(feature = "gzip")]
+    pub fn load_from_path_gz<P: AsRef<Path>>(path: P) -> Result<Self, DatasetError> {
+        use std::io::ErrorKind;
+
+        let path_ref = path.as_ref();
+        let file = File::open(path_ref).map_err(|source| DatasetError::Open {
+            path: path_ref.to_path_buf(),
+            source,
+        })?;
+        let decoder = flate2::read::GzDecoder::new(BufReader::new(file));
+        serde_json::from_reader(decoder).map_err(|source| {
+            if source.is_io() {
+                let source = std::io::Error::from(source);
+                let path = path_ref.to_path_buf();
+                // flate2 reports a bad header or deflate stream as invalid input/data.
+                match source.kind() {
+                    ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+                        DatasetError::Decompress { path, source }
+                    }
+                    _ => DatasetError::Read { path, source },
+                }
+            } else {
+                DatasetError::Deserialize {
+                    path: path_ref.to_path_buf(),
+                    source,
+                }
+            }
+        })
+    }
+
+    /// Load edges from CSV with columns `id,from,to,rate,pool_id,kind` (a header row is
+    /// optional). Tokens are derived from the `from`/`to` ids, named like the bundled dataset
+    /// (`T000`, `T001`, ...).
+    pub fn load_from_csv<P: AsRef<Path>>(path: P) -> Result<Self, DatasetError> {
+        let path_ref = path.as_ref();
+        let file = File::open(path_ref).map_err(|source| DatasetError::Open {
+            path: path_ref.to_path_buf(),
+            source,
+        })?;
+
+        let mut edges = Vec::new();
+        for (line_index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|source| DatasetError::Read {
+                path: path_ref.to_path_buf(),
+                source,
+            })?;
+            let line_number = line_index + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || (line_number == 1 && trimmed.starts_with("id")) {
+                continue;
+            }
+            let edge = parse_csv_edge(trimmed).map_err(|reason| DatasetError::CsvParse {
+                path: path_ref.to_path_buf(),
+                line: line_number,
+                reason,
+            })?;
+            edges.push(edge);
+        }
+
+        let token_ids: BTreeSet<u64> = edges.iter().flat_map(|e| [e.from, e.to]).collect();
+        let tokens = token_ids
+            .into_iter()
+            .map(|id| Token {
+                id,
+                symbol: format!("T{id:03}"),
+            })
+            .collect();
+
+        Ok(Self { tokens, edges })
+    }
+
+    /// Lint the dataset without failing on the first problem: duplicate edge ids, self-loops,
+    /// non-positive or non-finite rates, edges pointing at unknown tokens and tokens no edge
+    /// uses. Warnings come out grouped in that order; an empty vec means the dataset is clean.
+    pub fn validate(&self) -> Vec<DatasetWarning> {
+        let mut warnings = Vec::new();
+
+        let mut edges_by_id: BTreeMap<u64, usize> = BTreeMap::new();
+        for edge in &self.edges {
+            *edges_by_id.entry(edge.id).or_default() += 1;
+        }
+        warnings.extend(
+            edges_by_id
+                .into_iter()
+                .filter(|&(_, count)| count > 1)
+                .map(|(edge_id, count)| DatasetWarning::DuplicateEdgeId { edge_id, count }),
+        );
+
+        warnings.extend(
+            self.edges
+                .iter()
+                .filter(|edge| edge.from == edge.to)
+                .map(|edge| DatasetWarning::SelfLoop {
+                    edge_id: edge.id,
+                    token_id: edge.from,
+                }),
+        );
+
+        warnings.extend(
+            self.edges
+                .iter()
+                .filter(|edge| !edge.rate.is_finite() || edge.rate <= 0.0)
+                .map(|edge| DatasetWarning::InvalidRate {
+                    edge_id: edge.id,
+                    rate: edge.rate,
+                }),
+        );
+
+        let token_ids: BTreeSet<u64> = self.tokens.iter().map(|token| token.id).collect();
+        for edge in &self.edges {
+            let endpoints: &[u64] = if edge.from == edge.to {
+                &[edge.from]
+            } else {
+                &[edge.from, edge.to]
+            };
+            warnings.extend(
+                endpoints
+                    .iter()
+                    .filter(|token_id| !token_ids.contains(token_id))
+                    .map(|&token_id| DatasetWarning::UnknownToken {
+                        edge_id: edge.id,
+                        token_id,
+                    }),
+            );
+        }
+
+        let referenced: BTreeSet<u64> = self.edges.iter().flat_map(|e| [e.from, e.to]).collect();
+        warnings.extend(
+            self.tokens
+                .iter()
+                .filter(|token| !referenced.contains(&token.id))
+                .map(|token| DatasetWarning::UnreferencedToken { token_id: token.id }),
+        );
+
+        warnings
+    }
+
+    /// Hash index over the tokens for repeated symbol and node lookups. It is a snapshot: edits
+    /// to `tokens` afterwards are not reflected.
+    pub fn token_index(&self) -> TokenIndex {
+        TokenIndex::new(&self.tokens)
+    }
+
+    /// Symbol of the token whose id is `node_id` (token ids are the node indices used by edges
+    /// and need not be contiguous). A one-off scan of the tokens; use [`Dataset::token_index`]
+    /// for repeated lookups.
+    pub fn symbol_for(&self, node_id: usize) -> Option<&str> {
+        self.tokens
+            .iter()
+            .find(|token| token.id == node_id as u64)
+            .map(|token| token.symbol.as_str())
+    }
+
+    /// Symbols along a vertex path (e.g. [`crate::cycle_finder::Cycle::vertices`]); `None` if
+    /// any vertex has no matching token.
+    pub fn symbol_path(&self, vertices: &[usize]) -> Option<Vec<String>> {
+        self.token_index().symbol_path(vertices)
+    }
+
+    /// Node index of the token called `symbol`. A one-off scan of the tokens; use
+    /// [`Dataset::token_index`] for repeated lookups.
+    pub fn node_for_symbol(&self, symbol: &str) -> Option<usize> {
+        self.tokens
+            .iter()
+            .find(|token| token.symbol == symbol)
+            .and_then(|token| usize::try_from(token.id).ok())
+    }
+
+    /// Combine two datasets (e.g. pools from two DEXes) so cycles can cross between them.
+    /// A token of `other` whose symbol matches one of `self`'s is unified with it, which is what
+    /// links the two venues; `other`'s remaining token ids and all of its edge ids are shifted
+    /// past `self`'s largest so nothing collides. `self`'s ids, and every pool id and kind, are
+    /// kept. Fails with [`DatasetError::AmbiguousSymbol`] when a symbol names several tokens in
+    /// either dataset, since there would be no single token to unify with, and with
+    /// [`DatasetError::IdOverflow`] when a shifted id would not fit in a `u64`.
+    pub fn merge(mut self, other: Dataset) -> Result<Dataset, DatasetError> {
+        let mut own_symbols: HashMap<&str, u64> = HashMap::new();
+        for token in &self.tokens {
+            if own_symbols.insert(&token.symbol, token.id).is_some() {
+                return Err(DatasetError::AmbiguousSymbol {
+                    symbol: token.symbol.clone(),
+                });
+            }
+        }
+
+        let token_offset = self
+            .tokens
+            .iter()
+            .map(|token| token.id)
+            .chain(self.edges.iter().flat_map(|edge| [edge.from, edge.to]))
+            .max()
+            .map_or(Ok(0), |id| shift_id(id, 1))?;
+        let edge_offset = self
+            .edges
+            .iter()
+            .map(|edge| edge.id)
+            .max()
+            .map_or(Ok(0), |id| shift_id(id, 1))?;
+
+        let mut unified: HashMap<u64, u64> = HashMap::new();
+        let mut other_symbols: HashSet<&str> = HashSet::new();
+        let mut new_tokens = Vec::new();
+        for token in &other.tokens {
+            if !other_symbols.insert(&token.symbol) {
+                return Err(DatasetError::AmbiguousSymbol {
+                    symbol: token.symbol.clone(),
+                });
+            }
+            match own_symbols.get(token.symbol.as_str()) {
+                Some(&own_id) => {
+                    unified.insert(token.id, own_id);
+                }
+                None => new_tokens.push(Token {
+                    id: shift_id(token.id, token_offset)?,
+                    symbol: token.symbol.clone(),
+                }),
+            }
+        }
+        let rebase = |token_id: u64| match unified.get(&token_id) {
+            Some(&own_id) => Ok(own_id),
+            None => shift_id(token_id, token_offset),
+        };
+        let new_edges = other
+            .edges
+            .iter()
+            .map(|edge| {
+                Ok(Edge {
+                    id: shift_id(edge.id, edge_offset)?,
+                    from: rebase(edge.from)?,
+                    to: rebase(edge.to)?,
+                    ..edge.clone()
+                })
+            })
+            .collect::<Result<Vec<Edge>, DatasetError>>()?;
+
+        self.tokens.extend(new_tokens);
+        self.edges.extend(new_edges);
+        Ok(self)
+    }
+
+    /// Parse every JSON dataset in `paths` concurrently, returning them in input order. On
+    /// failure the error for the earliest failing path (in input order) is returned.
+    #[cfg(feature = "rayon")]
+    pub fn load_many_parallel(paths: &[std::path::PathBuf]) -> Result<Vec<Self>, DatasetError> {
+        use rayon::prelude::*;
+
+        // Collecting straight into a `Result` would stop at whichever failure a worker hit
+        // first; gather every outcome so the earliest failing path wins deterministically.
+        let loaded: Vec<Result<Self, DatasetError>> =
+            paths.par_iter().map(Self::load_from_path).collect();
+        loaded.into_iter().collect()
+    }
+
+    /// Write the dataset as pretty-printed JSON readable by [`Dataset::load_from_path`].
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), DatasetError> {
+        let path_ref = path.as_ref();
+        let write_error = |source| DatasetError::Write {
+            path: path_ref.to_path_buf(),
+            source,
+        };
+        let file = File::create(path_ref).map_err(write_error)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self).map_err(|source| {
+            if source.is_io() {
+                write_error(source.into())
+            } else {
+                DatasetError::Serialize {
+                    path: path_ref.to_path_buf(),
+                    source,
+                }
+            }
+        })?;
+        writer.flush().map_err(write_error)
+    }
+
+    /// Rebuild a dataset from the graph's current live edges and the original `tokens`.
+    /// Edges are numbered by edge index and keep the graph's [`CSRGraph::edge_pool`] and
+    /// [`CSRGraph::edge_kind`]. Reverse edges of kind [`REVERSED_EDGE_KIND`] are left out, as
+    /// their bidirectional row re-creates them when expanded.
+    pub fn from_graph(graph: &CSRGraph, tokens: Vec<Token>) -> Self {
+        let edges = (0..graph.edge_index_bound())
+            .filter(|&edge_index| {
+                !graph.is_removed(edge_index) && graph.edge_kind(edge_index) != REVERSED_EDGE_KIND
+            })
+            .map(|edge_index| {
+                let (from, to, rate) = graph.edge(edge_index);
+                Edge {
+                    id: edge_index as u64,
+                    from: from as u64,
+                    to: to as u64,
+                    rate,
+                    pool_id: graph.edge_pool(edge_index),
+                    kind: graph.edge_kind(edge_index),
+                }
+            })
+            .collect();
+        Self { tokens, edges }
+    }
+}
+
+fn parse_csv_edge(line: &str) -> Result<Edge, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != 6 {
+        return Err(format!("expected 6 columns, found {}", fields.len()));
+    }
+    Ok(Edge {
+        id: parse_csv_field(fields[0], "id")?,
+        from: parse_csv_field(fields[1], "from")?,
+        to: parse_csv_field(fields[2], "to")?,
+        rate: parse_csv_field(fields[3], "rate")?,
+        pool_id: parse_csv_field(fields[4], "pool_id")?,
+        kind: parse_csv_field(fields[5], "kind")?,
+    })
+}
+
+fn parse_csv_field<T: FromStr>(raw: &str, column: &str) -> Result<T, String> {
+    raw.parse()
+        .map_err(|_| format!("invalid {column} value {raw:?}"))
 }
 
 pub fn load_default_dataset() -> Result<Dataset, DatasetError> {
     Dataset::load_from_path(DEFAULT_DATASET_PATH)
 }
+
+/// Reproducible synthetic dataset: `num_tokens` tokens (`T000`, `T001`, ...) and `num_edges`
+/// edges between uniformly drawn distinct tokens, with rates drawn uniformly from `rate_range`.
+/// The same `seed` always yields the same dataset. Each edge is its own pool. With fewer than
+/// two tokens no edge can be drawn, so the dataset has none.
+///
+/// # Panics
+///
+/// If `rate_range` is empty.
+pub fn generate_random(
+    num_tokens: usize,
+    num_edges: usize,
+    seed: u64,
+    rate_range: Range<f64>,
+) -> Dataset {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let tokens = (0..num_tokens as u64)
+        .map(|id| Token {
+            id,
+            symbol: format!("T{id:03}"),
+        })
+        .collect();
+    let edges = if num_tokens < 2 {
+        Vec::new()
+    } else {
+        (0..num_edges as u64)
+            .map(|id| {
+                let from = rng.random_range(0..num_tokens);
+                // Draw from the other `num_tokens - 1` ids so edges never loop.
+                let mut to = rng.random_range(0..num_tokens - 1);
+                if to >= from {
+                    to += 1;
+                }
+                Edge {
+                    id,
+                    from: from as u64,
+                    to: to as u64,
+                    rate: rng.random_range(rate_range.clone()),
+                    pool_id: id,
+                    kind: 0,
+                }
+            })
+            .collect()
+    };
+    Dataset { tokens, edges }
+}
+
+/// Rescale every edge rate by `10^(to_decimals - from_decimals)` so rates quoted in whole tokens
+/// convert between base units. Edges touching a token missing from `decimals` are left as is.
+/// The factors telescope around any closed cycle, so cycle profits only move when some legs
+/// were already in base units and others were not.
+pub fn adjust_rates_for_decimals(dataset: &mut Dataset, decimals: &HashMap<u64, u8>) {
+    for edge in &mut dataset.edges {
+        if let (Some(&from_decimals), Some(&to_decimals)) =
+            (decimals.get(&edge.from), decimals.get(&edge.to))
+        {
+            edge.rate *= 10f64.powi(i32::from(to_decimals) - i32::from(from_decimals));
+        }
+    }
+}
+
+/// `id + offset`, or [`DatasetError::IdOverflow`] when that does not fit in a `u64`.
+fn shift_id(id: u64, offset: u64) -> Result<u64, DatasetError> {
+    id.checked_add(offset)
+        .ok_or(DatasetError::IdOverflow { id, offset })
+}
+
+/// Token symbols keyed by node index and node indices keyed by symbol, built once from a token
+/// list. Where ids or symbols repeat, the first token wins, as with the [`Dataset`] lookups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenIndex {
+    symbols: HashMap<u64, String>,
+    nodes: HashMap<String, usize>,
+}
+
+impl TokenIndex {
+    pub fn new(tokens: &[Token]) -> Self {
+        let mut index = Self::default();
+        for token in tokens {
+            index
+                .symbols
+                .entry(token.id)
+                .or_insert_with(|| token.symbol.clone());
+            if let Ok(node_id) = usize::try_from(token.id) {
+                index.nodes.entry(token.symbol.clone()).or_insert(node_id);
+            }
+        }
+        index
+    }
+
+    /// See [`Dataset::symbol_for`].
+    pub fn symbol_for(&self, node_id: usize) -> Option<&str> {
+        self.symbols.get(&(node_id as u64)).map(String::as_str)
+    }
+
+    /// See [`Dataset::node_for_symbol`].
+    pub fn node_for_symbol(&self, symbol: &str) -> Option<usize> {
+        self.nodes.get(symbol).copied()
+    }
+
+    /// See [`Dataset::symbol_path`].
+    pub fn symbol_path(&self, vertices: &[usize]) -> Option<Vec<String>> {
+        vertices
+            .iter()
+            .map(|&node_id| self.symbol_for(node_id).map(str::to_owned))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{csr_graph::dataset_input_edges, cycle_finder::find_profitable_cycle_from_start};
+
+    fn write_temp(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().expect("temp file");
+        file.write_all(contents.as_bytes())
+            .expect("write temp file");
+        file
+    }
+
+    #[test]
+    fn loads_edges_and_derives_tokens_from_csv() {
+        let file = write_temp("id,from,to,rate,pool_id,kind\n0,0,1,1.1,7,0\n1,1,4,0.9,8,1\n");
+
+        let dataset = Dataset::load_from_csv(file.path()).expect("csv parses");
+        assert_eq!(dataset.edges.len(), 2);
+        assert_eq!(dataset.edges[1].to, 4);
+        assert_eq!(dataset.edges[1].pool_id, 8);
+        assert_eq!(dataset.edges[1].kind, 1);
+        let ids: Vec<_> = dataset.tokens.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![0, 1, 4]);
+        assert_eq!(dataset.tokens[2].symbol, "T004");
+    }
+
+    fn edge(id: u64, from: u64, to: u64, rate: f64) -> Edge {
+        Edge {
+            id,
+            from,
+            to,
+            rate,
+            pool_id: 0,
+            kind: 0,
+        }
+    }
+
+    fn token(id: u64) -> Token {
+        Token {
+            id,
+            symbol: format!("T{id:03}"),
+        }
+    }
+
+    #[test]
+    fn validate_reports_every_issue() {
+        let clean = Dataset {
+            tokens: vec![token(0), token(1)],
+            edges: vec![edge(0, 0, 1, 1.1), edge(1, 1, 0, 0.9)],
+        };
+        assert!(clean.validate().is_empty());
+
+        let dataset = Dataset {
+            tokens: vec![token(0), token(1), token(2), token(3)],
+            edges: vec![
+                edge(0, 0, 1, 1.1),
+                edge(0, 1, 0, 0.9),
+                edge(1, 1, 1, 1.0),
+                edge(2, 0, 2, 0.0),
+                edge(3, 2, 0, f64::NAN),
+                edge(4, 2, 9, 1.2),
+            ],
+        };
+        let warnings = dataset.validate();
+        assert_eq!(warnings.len(), 6, "{warnings:?}");
+        assert_eq!(
+            warnings[..3],
+            [
+                DatasetWarning::DuplicateEdgeId {
+                    edge_id: 0,
+                    count: 2
+                },
+                DatasetWarning::SelfLoop {
+                    edge_id: 1,
+                    token_id: 1
+                },
+                DatasetWarning::InvalidRate {
+                    edge_id: 2,
+                    rate: 0.0
+                },
+            ]
+        );
+        assert!(matches!(
+            warnings[3],
+            DatasetWarning::InvalidRate { edge_id: 3, rate } if rate.is_nan()
+        ));
+        assert_eq!(
+            warnings[4..],
+            [
+                DatasetWarning::UnknownToken {
+                    edge_id: 4,
+                    token_id: 9
+                },
+                DatasetWarning::UnreferencedToken { token_id: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn decimal_adjustment_rescales_rates_into_base_units() {
+        // 0 = USDC (6), 1 = ETH (18), 2 = DAI (18). Leg 2 -> 0 was already quoted in base
+        // units, so only the whole-token legs should be rescaled.
+        let mut dataset = Dataset {
+            tokens: vec![token(0), token(1), token(2)],
+            edges: vec![
+                edge(0, 0, 1, 1.0 / 2_000.0),
+                edge(1, 1, 2, 2_010.0),
+                edge(2, 2, 0, 1e-12),
+            ],
+        };
+        let profit =
+            |dataset: &Dataset| dataset.edges.iter().map(|edge| edge.rate).product::<f64>();
+        assert!(profit(&dataset) < 1e-6, "mixed units hide the cycle");
+
+        let decimals = HashMap::from([(0, 6), (1, 18), (2, 18)]);
+        let mut whole_token_legs = dataset.clone();
+        whole_token_legs.edges.truncate(2);
+        adjust_rates_for_decimals(&mut whole_token_legs, &decimals);
+        dataset.edges[..2].clone_from_slice(&whole_token_legs.edges);
+
+        assert!((dataset.edges[0].rate - 1e12 / 2_000.0).abs() < 1e-3);
+        assert_eq!(dataset.edges[1].rate, 2_010.0);
+        assert!((profit(&dataset) - 1.005).abs() < 1e-12);
+
+        let mut unknown = Dataset {
+            tokens: vec![token(0), token(7)],
+            edges: vec![edge(0, 0, 7, 1.5)],
+        };
+        adjust_rates_for_decimals(&mut unknown, &decimals);
+        assert_eq!(unknown.edges[0].rate, 1.5);
+    }
+
+    #[test]
+    fn csv_errors_report_line_number() {
+        let file = write_temp("id,from,to,rate,pool_id,kind\n0,0,1,1.1,7,0\n1,1,x,0.9,8,1\n");
+
+        let err = Dataset::load_from_csv(file.path()).expect_err("malformed row");
+        match err {
+            DatasetError::CsvParse { line, reason, .. } => {
+                assert_eq!(line, 3);
+                assert!(reason.contains("to"), "unexpected reason: {reason}");
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn save_and_reload_round_trips() {
+        let dataset = load_default_dataset().expect("bundled dataset");
+        let file = tempfile::NamedTempFile::new().expect("temp file");
+
+        dataset.save_to_path(file.path()).expect("dataset saves");
+        let reloaded = Dataset::load_from_path(file.path()).expect("saved dataset loads");
+        assert_eq!(reloaded, dataset);
+    }
+
+    #[test]
+    fn save_reports_io_failures_as_write_errors() {
+        let dataset = load_default_dataset().expect("bundled dataset");
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("missing").join("graph.json");
+
+        match dataset.save_to_path(&path) {
+            Err(DatasetError::Write {
+                path: failed,
+                source,
+            }) => {
+                assert_eq!(failed, path);
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn from_graph_captures_current_rates() {
+        let mut graph = CSRGraph::from_edges(2, vec![(0, 1, 1.5), (1, 0, 0.5)]);
+        graph.update_rate(1, 0.75).expect("valid update");
+        graph.remove_edge(0).expect("edge exists");
+        let tokens = vec![
+            Token {
+                id: 0,
+                symbol: "A".into(),
+            },
+            Token {
+                id: 1,
+                symbol: "B".into(),
+            },
+        ];
+
+        let dataset = Dataset::from_graph(&graph, tokens.clone());
+        assert_eq!(dataset.tokens, tokens);
+        assert_eq!(dataset.edges.len(), 1);
+        assert_eq!((dataset.edges[0].id, dataset.edges[0].rate), (1, 0.75));
+    }
+
+    #[test]
+    fn from_graph_does_not_expand_synthesized_reverse_edges_again() {
+        let dataset = Dataset {
+            tokens: Vec::new(),
+            edges: vec![Edge {
+                kind: BIDIRECTIONAL_EDGE_KIND,
+                ..edge(0, 0, 1, 2.0)
+            }],
+        };
+        let expanded = dataset_input_edges(&dataset, true).expect("valid dataset");
+        assert_eq!(
+            expanded.kinds,
+            vec![BIDIRECTIONAL_EDGE_KIND, REVERSED_EDGE_KIND]
+        );
+
+        let rebuilt = Dataset::from_graph(&expanded.into_graph(), Vec::new());
+        assert_eq!(rebuilt.edges, dataset.edges);
+        let reexpanded = dataset_input_edges(&rebuilt, true).expect("valid dataset");
+        assert_eq!(reexpanded.edges, vec![(0, 1, 2.0), (1, 0, 0.5)]);
+    }
+
+    #[test]
+    fn resolves_symbols_and_node_ids_with_gaps() {
+        let token = |id, symbol: &str| Token {
+            id,
+            symbol: symbol.into(),
+        };
+        let dataset = Dataset {
+            tokens: vec![token(0, "A"), token(1, "B"), token(5, "C")],
+            edges: Vec::new(),
+        };
+
+        assert_eq!(dataset.symbol_for(5), Some("C"));
+        assert_eq!(dataset.node_for_symbol("B"), Some(1));
+        assert_eq!(dataset.symbol_for(2), None);
+        assert_eq!(dataset.node_for_symbol("Z"), None);
+
+        let mut shadowed = dataset.clone();
+        shadowed.tokens.push(token(7, "A"));
+        let index = shadowed.token_index();
+        for node_id in [0, 1, 2, 5, 7] {
+            assert_eq!(index.symbol_for(node_id), shadowed.symbol_for(node_id));
+        }
+        for symbol in ["A", "B", "C", "Z"] {
+            assert_eq!(
+                index.node_for_symbol(symbol),
+                shadowed.node_for_symbol(symbol)
+            );
+        }
+        assert_eq!(
+            index.symbol_path(&[0, 5, 7, 0]),
+            Some(
+                vec!["A", "C", "A", "A"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+        assert_eq!(index.symbol_path(&[0, 2]), None);
+    }
+
+    #[test]
+    fn merged_datasets_keep_both_cycles() {
+        let named = |id, symbol: &str| Token {
+            id,
+            symbol: symbol.into(),
+        };
+        let first = Dataset {
+            tokens: vec![named(0, "A"), named(1, "B"), named(2, "C")],
+            edges: vec![edge(0, 0, 1, 1.1), edge(1, 1, 2, 1.0), edge(2, 2, 0, 1.0)],
+        };
+        let second = Dataset {
+            tokens: vec![named(0, "X"), named(1, "Y"), named(2, "Z")],
+            edges: vec![edge(0, 0, 1, 1.0), edge(1, 1, 2, 1.05), edge(2, 2, 0, 1.0)],
+        };
+
+        let merged = first.merge(second).expect("no ambiguous symbols");
+        assert!(merged.validate().is_empty());
+        assert_eq!(merged.node_for_symbol("X"), Some(3));
+        assert_eq!(
+            merged.edges.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+
+        let (graph, _) = CSRGraph::from_dataset(&merged).expect("valid graph");
+        for (symbol, profit) in [("A", 1.1), ("X", 1.05)] {
+            let start = merged.node_for_symbol(symbol).expect("token kept");
+            let cycle = find_profitable_cycle_from_start(&graph, start, 3).expect("cycle kept");
+            assert!((cycle.profit - profit).abs() < 1e-9);
+        }
+
+        // A shared symbol links the venues instead of being duplicated.
+        let venue = Dataset {
+            tokens: vec![named(4, "A"), named(5, "W")],
+            edges: vec![edge(9, 4, 5, 2.0)],
+        };
+        let linked = merged.clone().merge(venue).expect("no ambiguous symbols");
+        assert_eq!(linked.tokens.len(), 7);
+        let bridge = linked.edges.last().expect("edge merged");
+        assert_eq!((bridge.id, bridge.from, bridge.to), (15, 0, 11));
+
+        let clash = Dataset {
+            tokens: vec![named(0, "A"), named(1, "A")],
+            edges: Vec::new(),
+        };
+        assert!(matches!(
+            merged.merge(clash),
+            Err(DatasetError::AmbiguousSymbol { symbol }) if symbol == "A"
+        ));
+    }
+
+    #[test]
+    fn merge_rejects_ids_shifted_past_u64() {
+        let named = |id, symbol: &str| Token {
+            id,
+            symbol: symbol.into(),
+        };
+        let first = Dataset {
+            tokens: vec![named(0, "A"), named(1, "B")],
+            edges: vec![edge(0, 0, 1, 1.1)],
+        };
+        let huge_token = Dataset {
+            tokens: vec![named(u64::MAX, "Z")],
+            edges: Vec::new(),
+        };
+        assert!(matches!(
+            first.clone().merge(huge_token),
+            Err(DatasetError::IdOverflow {
+                id: u64::MAX,
+                offset: 2
+            })
+        ));
+
+        let huge_edge = Dataset {
+            tokens: vec![named(0, "A"), named(1, "B")],
+            edges: vec![edge(u64::MAX, 0, 1, 1.0)],
+        };
+        assert!(matches!(
+            first.merge(huge_edge),
+            Err(DatasetError::IdOverflow {
+                id: u64::MAX,
+                offset: 1
+            })
+        ));
+
+        let full = Dataset {
+            tokens: vec![named(u64::MAX, "A")],
+            edges: Vec::new(),
+        };
+        assert!(matches!(
+            full.merge(Dataset {
+                tokens: Vec::new(),
+                edges: Vec::new(),
+            }),
+            Err(DatasetError::IdOverflow {
+                id: u64::MAX,
+                offset: 1
+            })
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn loads_many_datasets_in_input_order() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let token = |id, symbol: &str| Token {
+            id,
+            symbol: symbol.into(),
+        };
+        let first = Dataset {
+            tokens: vec![token(0, "A")],
+            edges: Vec::new(),
+        };
+        let second = Dataset {
+            tokens: vec![token(0, "B"), token(1, "C")],
+            edges: Vec::new(),
+        };
+        let paths = vec![
+            dir.path().join("first.json"),
+            dir.path().join("second.json"),
+        ];
+        first.save_to_path(&paths[0]).expect("write first");
+        second.save_to_path(&paths[1]).expect("write second");
+
+        let loaded = Dataset::load_many_parallel(&paths).expect("both parse");
+        assert_eq!(loaded, vec![first, second]);
+
+        let missing = vec![paths[0].clone(), dir.path().join("missing.json")];
+        match Dataset::load_many_parallel(&missing) {
+            Err(DatasetError::Open { path, .. }) => assert_eq!(path, missing[1]),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_load_reports_the_earliest_failing_path() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let corrupt = dir.path().join("corrupt.json");
+        std::fs::write(&corrupt, "{ not json").expect("write corrupt");
+        let missing = dir.path().join("missing.json");
+
+        for _ in 0..16 {
+            match Dataset::load_many_parallel(&[corrupt.clone(), missing.clone()]) {
+                Err(DatasetError::Deserialize { path, .. }) => assert_eq!(path, corrupt),
+                other => panic!("unexpected result: {other:?}"),
+            }
+            match Dataset::load_many_parallel(&[missing.clone(), corrupt.clone()]) {
+                Err(DatasetError::Open { path, .. }) => assert_eq!(path, missing),
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn generate_random_is_deterministic_and_in_bounds() {
+        let dataset = generate_random(12, 500, 42, 0.5..2.0);
+        assert_eq!(dataset, generate_random(12, 500, 42, 0.5..2.0));
+        assert_ne!(dataset, generate_random(12, 500, 43, 0.5..2.0));
+
+        assert_eq!(dataset.tokens.len(), 12);
+        assert_eq!(dataset.edges.len(), 500);
+        let token_ids: BTreeSet<u64> = dataset.tokens.iter().map(|token| token.id).collect();
+        for edge in &dataset.edges {
+            assert!(token_ids.contains(&edge.from) && token_ids.contains(&edge.to));
+            assert_ne!(edge.from, edge.to);
+            assert!((0.5..2.0).contains(&edge.rate));
+        }
+        assert!(dataset
+            .validate()
+            .iter()
+            .all(|warning| matches!(warning, DatasetWarning::UnreferencedToken { .. })));
+        assert!(generate_random(1, 10, 42, 0.5..2.0).edges.is_empty());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_dataset_round_trips_and_rejects_corrupt_streams() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let dataset = Dataset {
+            tokens: vec![token(0), token(1), token(2)],
+            edges: vec![edge(0, 0, 1, 1.5), edge(1, 1, 2, 0.25), edge(2, 2, 0, 2.75)],
+        };
+        let mut file = tempfile::Builder::new()
+            .suffix(".json.gz")
+            .tempfile()
+            .expect("temp file");
+        let mut encoder = GzEncoder::new(&mut file, Compression::default());
+        serde_json::to_writer(&mut encoder, &dataset).expect("serialize");
+        encoder.finish().expect("finish gzip");
+
+        assert_eq!(Dataset::load_from_path(file.path()).unwrap(), dataset);
+        assert_eq!(Dataset::load_from_path_gz(file.path()).unwrap(), dataset);
+
+        let corrupt = write_temp("{\"tokens\": [], \"edges\": []}");
+        assert!(matches!(
+            Dataset::load_from_path_gz(corrupt.path()),
+            Err(DatasetError::Decompress { .. })
+        ));
+
+        // A failing read is not a decompression problem.
+        let dir = tempfile::tempdir().expect("temp dir");
+        assert!(matches!(
+            Dataset::load_from_path_gz(dir.path()),
+            Err(DatasetError::Read { .. })
+        ));
+    }
+}
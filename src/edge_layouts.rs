@@ -55,6 +55,38 @@ impl EdgeSoA {
             .zip(self.rate.iter().copied())
             .map(|((u, v), r)| (u, v, r))
     }
+
+    /// Parallel `From<&[EdgeAoS]>`: the three columns are filled concurrently, in input order.
+    #[cfg(feature = "rayon")]
+    pub fn from_par(edges: &[EdgeAoS]) -> Self {
+        use rayon::prelude::*;
+
+        let (from, (to, rate)) = rayon::join(
+            || edges.par_iter().map(|edge| edge.from).collect(),
+            || {
+                rayon::join(
+                    || edges.par_iter().map(|edge| edge.to).collect(),
+                    || edges.par_iter().map(|edge| edge.rate).collect(),
+                )
+            },
+        );
+        Self { from, to, rate }
+    }
+
+    /// Parallel `From<EdgeSoA> for Vec<EdgeAoS>`, preserving element order.
+    #[cfg(feature = "rayon")]
+    pub fn into_par_vec(self) -> Vec<EdgeAoS> {
+        use rayon::prelude::*;
+
+        let EdgeSoA { from, to, rate } = self;
+        debug_assert!(from.len() == to.len() && to.len() == rate.len());
+
+        from.into_par_iter()
+            .zip(to)
+            .zip(rate)
+            .map(|((u, v), r)| EdgeAoS::new(u, v, r))
+            .collect()
+    }
 }
 
 impl From<Vec<EdgeAoS>> for EdgeSoA {
@@ -162,4 +194,17 @@ mod tests {
         let collected: Vec<_> = soa.iter().collect();
         assert_eq!(collected, vec![(10, 11, 1.11), (11, 12, 0.91)]);
     }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_conversions_match_serial() {
+        let edges: Vec<EdgeAoS> = (0..5_000)
+            .map(|i| EdgeAoS::new(i % 97, (i * 7) % 101, 1.0 + i as f64 * 1e-4))
+            .collect();
+
+        let par = EdgeSoA::from_par(&edges);
+        assert_eq!(par, EdgeSoA::from(edges.as_slice()));
+        assert_eq!(par.clone().into_par_vec(), Vec::<EdgeAoS>::from(par));
+        assert_eq!(EdgeSoA::from_par(&edges).into_par_vec(), edges);
+    }
 }
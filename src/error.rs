@@ -1,4 +1,4 @@
-use std::{num::TryFromIntError, path::PathBuf};
+use std::{num::TryFromIntError, path::PathBuf, time::Duration};
 
 use thiserror::Error;
 
@@ -16,6 +16,40 @@ pub enum DatasetError {
         #[source]
         source: serde_json::Error,
     },
+    #[error("dataset file {path} is not a valid gzip stream")]
+    Decompress {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("dataset file {path} could not be read")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("dataset file {path} could not be serialized")]
+    Serialize {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("dataset file {path} could not be written")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("dataset file {path} line {line}: {reason}")]
+    CsvParse {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("cannot merge datasets: symbol {symbol} names more than one token")]
+    AmbiguousSymbol { symbol: String },
+    #[error("cannot merge datasets: id {id} shifted by {offset} overflows u64")]
+    IdOverflow { id: u64, offset: u64 },
 }
 
 #[derive(Debug, Error)]
@@ -36,10 +70,35 @@ pub enum PipelineError {
     },
     #[error("edge {edge_id} has invalid rate {rate}")]
     InvalidRate { edge_id: u64, rate: f64 },
-    #[error("producer task failed")]
-    ProducerJoin(#[source] tokio::task::JoinError),
+    #[error("writer aborted after {fraction:.3} of updates failed validation")]
+    TooManyInvalidUpdates { fraction: f64 },
+    #[error("producer source reader was already consumed by an earlier run")]
+    ProducerSourceConsumed,
     #[error("writer task failed")]
     WriterJoin(#[source] tokio::task::JoinError),
     #[error("searcher task failed")]
     SearcherJoin(#[source] tokio::task::JoinError),
+    #[error("pipeline driver task failed")]
+    DriverJoin(#[source] tokio::task::JoinError),
+    #[error("pipeline did not shut down within {grace:?} of its deadline and was aborted")]
+    ShutdownTimedOut { grace: Duration },
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ConfigError {
+    #[error("channel_capacity must be greater than zero")]
+    ZeroChannelCapacity,
+    #[error("rate_jitter must be finite, got {0}")]
+    NonFiniteRateJitter(f64),
+    #[error("search_interval must be greater than zero")]
+    ZeroSearchInterval,
+    #[error("search_interval {interval:?} is below the {floor:?} floor")]
+    SearchIntervalBelowFloor { interval: Duration, floor: Duration },
+    #[error("min_profit_ratio must be finite and positive, got {0}")]
+    InvalidMinProfitRatio(f64),
+    #[error("min_rate_bound {min} exceeds max_rate_bound {max}")]
+    InvertedRateBounds { min: f64, max: f64 },
+    /// `scale` is the model's spread (`std` for a normal model); `df` is set for Student's t.
+    #[error("jitter model needs a finite spread >= 0 and df > 0, got scale {scale}, df {df:?}")]
+    InvalidJitterModel { df: Option<f64>, scale: f64 },
 }
@@ -1,6 +1,19 @@
+/// Half-way band of the default ties-to-even rounding, in ULPs of the value being rounded.
+pub const DEFAULT_TIE_SLACK_ULPS: u32 = 1;
+
+/// Linear-domain quantisation applied between the multiply and the log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantMode {
+    /// Skip quantisation entirely: clamp → multiply → log → gate. Maximum precision, but none of
+    /// the ties-to-even bias-avoidance or bin-stability guarantees of [`QuantMode::Linear`].
+    None,
+    /// Quantise to multiples of the given step (floored by [`double::sanitize_quantum`]).
+    Linear(f64),
+}
+
 /// Fused clamp→multiply→quantise (linear)→log→gate kernel.
-/// Inputs `old_value_log`/outputs remain in log domain, while [`normalize_bounds`],
-/// [`sanitize_quantum`] and [`sanitize_eps`] keep the linear step numerically safe
+/// Inputs `old_value_log`/outputs remain in log domain, while [`double::normalize_bounds`],
+/// [`double::sanitize_quantum`] and [`double::sanitize_eps`] keep the linear step numerically safe
 /// (no denormals, NaNs, or long-run bias thanks to ties-to-even rounding).
 pub fn log_mul_eps(
     old_value: f64,
@@ -11,159 +24,349 @@ pub fn log_mul_eps(
     max_r: f64,
     quantum: f64,
 ) -> f64 {
-    let (lo, hi) = normalize_bounds(min_r, max_r);
-    let eps = sanitize_eps(eps);
-    let quantum = sanitize_quantum(quantum, lo);
-    let inv_quantum = quantum.recip();
-
-    let ac = clamp_operand(a, lo, hi);
-    let bc = clamp_operand(b, lo, hi);
-
-    // Multiply while keeping the result within the sanitised range.
-    let product = (ac * bc).clamp(lo, hi);
+    log_mul_eps_with_mode(
+        old_value,
+        a,
+        b,
+        eps,
+        min_r,
+        max_r,
+        QuantMode::Linear(quantum),
+    )
+}
 
-    // Quantise in linear space using ties-to-even to avoid long-run bias.
-    let quantised_linear = quantize_ties_even_linear(product, inv_quantum, quantum).clamp(lo, hi);
+/// [`log_mul_eps`] with an explicit [`QuantMode`].
+pub fn log_mul_eps_with_mode(
+    old_value: f64,
+    a: f64,
+    b: f64,
+    eps: f64,
+    min_r: f64,
+    max_r: f64,
+    mode: QuantMode,
+) -> f64 {
+    let quantum = match mode {
+        QuantMode::None => None,
+        QuantMode::Linear(quantum) => Some(quantum),
+    };
+    let params = SanitizedParams::new(eps, min_r, max_r, quantum, DEFAULT_TIE_SLACK_ULPS);
+    log_mul_eps_sanitized(old_value, a, b, &params)
+}
 
-    // Convert back to log space with a path that preserves precision near one.
-    let new_log = ln_near_one(quantised_linear);
+/// Scalar parameters of [`log_mul_eps`], grouped so batch callers sanitise them once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogKernelParams {
+    pub eps: f64,
+    pub min_r: f64,
+    pub max_r: f64,
+    pub quantum: f64,
+    /// Half-way band of the quantiser's ties-to-even rounding, in ULPs (see
+    /// [`round_ties_even_with_slack`]). [`DEFAULT_TIE_SLACK_ULPS`] matches [`log_mul_eps`];
+    /// widen it to absorb FP error accumulated upstream.
+    pub tie_slack_ulps: u32,
+}
 
-    if !old_value.is_finite() {
-        return new_log;
-    }
+/// [`log_mul_eps`] with parameters taken from `params`, including the tie slack.
+pub fn log_mul_eps_with_params(old_value: f64, a: f64, b: f64, params: &LogKernelParams) -> f64 {
+    log_mul_eps_sanitized(old_value, a, b, &SanitizedParams::from(params))
+}
 
-    if eps > 0.0 && (new_log - old_value).abs() < eps {
-        old_value
-    } else {
-        new_log
+/// [`log_mul_eps_with_params`] over whole slices:
+/// `out[i] = log_mul_eps_with_params(old_values[i], a[i], b[i], params)`, bit-for-bit.
+/// Parameters are sanitised once up front and the per-element body is a plain indexed loop the
+/// compiler can unroll and vectorise.
+///
+/// # Panics
+/// If the four slices differ in length.
+pub fn log_mul_eps_batch(
+    old_values: &[f64],
+    a: &[f64],
+    b: &[f64],
+    out: &mut [f64],
+    params: &LogKernelParams,
+) {
+    let len = out.len();
+    assert!(
+        old_values.len() == len && a.len() == len && b.len() == len,
+        "log_mul_eps_batch slices must have equal lengths"
+    );
+
+    let params = SanitizedParams::from(params);
+    for i in 0..len {
+        out[i] = log_mul_eps_sanitized(old_values[i], a[i], b[i], &params);
     }
 }
 
-/// Sanitise the caller-provided clamp range before entering the hot loop.
-#[inline(always)]
-fn normalize_bounds(min_r: f64, max_r: f64) -> (f64, f64) {
-    let mut lo = min_r.min(max_r);
-    let mut hi = min_r.max(max_r);
+/// Kernel body and sanitising helpers for one float width, so the `f64` kernel and its `f32`
+/// reference share a single implementation. `abs_min_quantum` is the absolute floor of the
+/// quantisation step and `ln_near_one_band` the `|x - 1|` up to which `ln_1p` replaces `ln`.
+macro_rules! log_kernel_impl {
+    ($float:ident, abs_min_quantum: $abs_min_q:expr, ln_near_one_band: $band:expr) => {
+        /// Kernel parameters after sanitisation: positive finite `eps`, ordered bounds and the
+        /// quantisation step as `(quantum, 1 / quantum)` (`None` when disabled).
+        #[derive(Debug, Clone, Copy)]
+        pub(super) struct SanitizedParams {
+            eps: $float,
+            lo: $float,
+            hi: $float,
+            step: Option<($float, $float)>,
+            tie_slack_ulps: u32,
+        }
 
-    // Keep bounds ≥ MIN_POSITIVE so subsequent math avoids denormals.
-    if !lo.is_finite() || lo <= 0.0 {
-        lo = f64::MIN_POSITIVE;
-    }
-    if !hi.is_finite() || hi < lo {
-        hi = lo;
-    }
-    (lo, hi)
-}
+        impl SanitizedParams {
+            #[inline(always)]
+            pub(super) fn new(
+                eps: $float,
+                min_r: $float,
+                max_r: $float,
+                quantum: Option<$float>,
+                tie_slack_ulps: u32,
+            ) -> Self {
+                let (lo, hi) = normalize_bounds(min_r, max_r);
+                let step = quantum.map(|quantum| {
+                    let quantum = sanitize_quantum(quantum, lo);
+                    (quantum, quantum.recip())
+                });
+                Self {
+                    eps: sanitize_eps(eps),
+                    lo,
+                    hi,
+                    step,
+                    tie_slack_ulps,
+                }
+            }
+        }
 
-/// Keep the epsilon gate positive and finite.
-#[inline(always)]
-fn sanitize_eps(eps: f64) -> f64 {
-    if eps.is_finite() {
-        eps.abs()
-    } else {
-        0.0
-    }
-}
+        /// Kernel body shared by every entry point of this width.
+        #[inline(always)]
+        pub(super) fn log_mul_eps_sanitized(
+            old_value: $float,
+            a: $float,
+            b: $float,
+            params: &SanitizedParams,
+        ) -> $float {
+            let SanitizedParams {
+                eps,
+                lo,
+                hi,
+                step,
+                tie_slack_ulps,
+            } = *params;
+            let ac = clamp_operand(a, lo, hi);
+            let bc = clamp_operand(b, lo, hi);
 
-/// Ensure the linear quantisation step never drops below a meaningful minimum.
-#[inline(always)]
-fn sanitize_quantum(quantum_lin: f64, lo: f64) -> f64 {
-    const ABS_MIN_Q: f64 = 1e-12;
-    // Floor quantisation to ≥ max(1e-12, ~1 ULP at the lower bound) so steps stay meaningful.
-    let min_step = (f64::EPSILON * lo).max(ABS_MIN_Q);
-    if quantum_lin.is_finite() && quantum_lin > 0.0 {
-        quantum_lin.max(min_step)
-    } else {
-        min_step
-    }
-}
+            // Multiply while keeping the result within the sanitised range.
+            let product = (ac * bc).clamp(lo, hi);
 
-/// Defensive clamp used for both operands; treats NaN/Inf as bound hits.
-#[inline(always)]
-fn clamp_operand(value: f64, lo: f64, hi: f64) -> f64 {
-    let sanitized = if value.is_nan() {
-        lo
-    } else if !value.is_finite() {
-        if value.is_sign_negative() {
-            lo
-        } else {
-            hi
+            let linear = match step {
+                None => product,
+                // Quantise in linear space using ties-to-even to avoid long-run bias.
+                Some((quantum, inv_quantum)) => {
+                    quantize_ties_even_linear(product, inv_quantum, quantum, tie_slack_ulps)
+                        .clamp(lo, hi)
+                }
+            };
+
+            // Convert back to log space with a path that preserves precision near one.
+            let new_log = ln_near_one(linear);
+
+            if !old_value.is_finite() {
+                return new_log;
+            }
+
+            if eps > 0.0 && (new_log - old_value).abs() < eps {
+                old_value
+            } else {
+                new_log
+            }
         }
-    } else {
-        value
-    };
-    sanitized.max(lo).min(hi)
-}
 
-/// Scale→round→rescale using ties-to-even to avoid long-run bias.
-#[inline(always)]
-fn quantize_ties_even_linear(value: f64, inv_quantum: f64, quantum: f64) -> f64 {
-    let scaled = value * inv_quantum;
-    round_ties_even(scaled) * quantum
-}
+        /// Sanitise the caller-provided clamp range before entering the hot loop.
+        #[inline(always)]
+        pub(super) fn normalize_bounds(min_r: $float, max_r: $float) -> ($float, $float) {
+            let mut lo = min_r.min(max_r);
+            let mut hi = min_r.max(max_r);
+
+            // Keep bounds ≥ MIN_POSITIVE so subsequent math avoids denormals.
+            if !lo.is_finite() || lo <= 0.0 {
+                lo = $float::MIN_POSITIVE;
+            }
+            if !hi.is_finite() || hi < lo {
+                hi = lo;
+            }
+            (lo, hi)
+        }
 
-/// IEEE-754 round-to-nearest, ties-to-even with an ULP-scaled tie slack.
-#[inline(always)]
-fn round_ties_even(x: f64) -> f64 {
-    if !x.is_finite() {
-        return x;
-    }
+        /// Keep the epsilon gate positive and finite.
+        #[inline(always)]
+        pub(super) fn sanitize_eps(eps: $float) -> $float {
+            if eps.is_finite() {
+                eps.abs()
+            } else {
+                0.0
+            }
+        }
 
-    // IEEE-754 round-to-nearest, ties-to-even with ULP-scaled slack for half-way detection.
-    let t = x.trunc();
-    let frac = (x - t).abs();
-    let slack = ulp(x);
+        /// Ensure the linear quantisation step never drops below a meaningful minimum.
+        #[inline(always)]
+        pub(super) fn sanitize_quantum(quantum_lin: $float, lo: $float) -> $float {
+            // Floor quantisation to ≥ max(abs_min_quantum, ~1 ULP at the lower bound) so steps
+            // stay meaningful.
+            let min_step = ($float::EPSILON * lo).max($abs_min_q);
+            if quantum_lin.is_finite() && quantum_lin > 0.0 {
+                quantum_lin.max(min_step)
+            } else {
+                min_step
+            }
+        }
 
-    if frac < 0.5 - slack {
-        return t;
-    }
-    if frac > 0.5 + slack {
-        return t + x.signum();
-    }
+        /// Defensive clamp used for both operands; treats NaN/Inf as bound hits.
+        #[inline(always)]
+        pub(super) fn clamp_operand(value: $float, lo: $float, hi: $float) -> $float {
+            let sanitized = if value.is_nan() {
+                lo
+            } else if !value.is_finite() {
+                if value.is_sign_negative() {
+                    lo
+                } else {
+                    hi
+                }
+            } else {
+                value
+            };
+            sanitized.max(lo).min(hi)
+        }
 
-    // `t` is integral up to 2^53; beyond that every representable value is already even.
-    if t.rem_euclid(2.0) == 0.0 {
-        t
-    } else {
-        t + x.signum()
-    }
-}
+        /// Scale→round→rescale using ties-to-even (half-way band of `tie_slack_ulps`, see
+        /// [`round_ties_even_with_slack`]) to avoid long-run bias.
+        #[inline(always)]
+        pub(crate) fn quantize_ties_even_linear(
+            value: $float,
+            inv_quantum: $float,
+            quantum: $float,
+            tie_slack_ulps: u32,
+        ) -> $float {
+            let scaled = value * inv_quantum;
+            round_ties_even_with_slack(scaled, tie_slack_ulps) * quantum
+        }
 
-/// Compute the unit in the last place around `x` (handles zero and infinities).
-#[inline(always)]
-fn ulp(x: f64) -> f64 {
-    if !x.is_finite() {
-        return 0.0;
-    }
-    if x == 0.0 {
-        return f64::MIN_POSITIVE;
-    }
-    let bits = x.to_bits();
-    if x > 0.0 {
-        (f64::from_bits(bits + 1) - x).abs()
-    } else {
-        (x - f64::from_bits(bits - 1)).abs()
-    }
+        /// Round to nearest, ties to even, where any `x` whose fractional part lies within
+        /// `slack_ulps * ulp(x)` of one half counts as a tie. `0` demands an exact half.
+        #[inline(always)]
+        pub fn round_ties_even_with_slack(x: $float, slack_ulps: u32) -> $float {
+            if !x.is_finite() {
+                return x;
+            }
+
+            // IEEE-754 round-to-nearest, ties-to-even with ULP-scaled slack for half-way
+            // detection.
+            let t = x.trunc();
+            let frac = (x - t).abs();
+            let slack = slack_ulps as $float * ulp(x);
+
+            if frac < 0.5 - slack {
+                return t;
+            }
+            if frac > 0.5 + slack {
+                return t + x.signum();
+            }
+
+            // `t` is integral up to the mantissa width; beyond that every representable value
+            // is already even.
+            if t.rem_euclid(2.0) == 0.0 {
+                t
+            } else {
+                t + x.signum()
+            }
+        }
+
+        /// Compute the unit in the last place around `x` (handles zero and infinities).
+        #[inline(always)]
+        pub(super) fn ulp(x: $float) -> $float {
+            if !x.is_finite() {
+                return 0.0;
+            }
+            if x == 0.0 {
+                return $float::MIN_POSITIVE;
+            }
+            let bits = x.to_bits();
+            if x > 0.0 {
+                ($float::from_bits(bits + 1) - x).abs()
+            } else {
+                (x - $float::from_bits(bits - 1)).abs()
+            }
+        }
+
+        /// Accurate natural log for values close to one; falls back to `ln` otherwise.
+        #[inline(always)]
+        pub(super) fn ln_near_one(x: $float) -> $float {
+            debug_assert!(x > 0.0 && x.is_finite());
+            let delta = x - 1.0;
+            if delta.abs() <= $band {
+                delta.ln_1p()
+            } else {
+                x.ln()
+            }
+        }
+    };
 }
 
-/// Accurate natural log for values close to one; falls back to `ln` otherwise.
-#[inline(always)]
-fn ln_near_one(x: f64) -> f64 {
-    debug_assert!(x > 0.0 && x.is_finite());
-    let delta = x - 1.0;
+/// The `f64` instantiation behind every kernel entry point except [`log_mul_eps_f32`].
+mod double {
     // Switch to log1p when |x-1| ≤ 1e-6; empirically this threshold balances accuracy and cost.
-    if delta.abs() <= 1e-6 {
-        delta.ln_1p()
-    } else {
-        x.ln()
+    log_kernel_impl!(f64, abs_min_quantum: 1e-12, ln_near_one_band: 1e-6);
+}
+
+pub(crate) use double::quantize_ties_even_linear;
+pub use double::round_ties_even_with_slack;
+use double::{log_mul_eps_sanitized, SanitizedParams};
+
+impl From<&LogKernelParams> for SanitizedParams {
+    #[inline(always)]
+    fn from(params: &LogKernelParams) -> Self {
+        Self::new(
+            params.eps,
+            params.min_r,
+            params.max_r,
+            Some(params.quantum),
+            params.tie_slack_ulps,
+        )
     }
 }
 
+/// The `f32` instantiation behind [`log_mul_eps_f32`].
+mod single {
+    // f32 carries ~7 significant digits, so the step floor and the log1p band sit far above the
+    // f64 ones.
+    log_kernel_impl!(f32, abs_min_quantum: 1e-6, ln_near_one_band: 1e-3);
+}
+
+/// Single-precision reference of [`log_mul_eps`] for validating f32 pipelines: same clamp →
+/// multiply → quantise (ties-to-even) → log-near-one → gate sequence, with bounds and floors
+/// expressed in `f32` terms (`f32::MIN_POSITIVE`, `f32::EPSILON`).
+pub fn log_mul_eps_f32(
+    old_value: f32,
+    a: f32,
+    b: f32,
+    eps: f32,
+    min_r: f32,
+    max_r: f32,
+    quantum: f32,
+) -> f32 {
+    let params =
+        single::SanitizedParams::new(eps, min_r, max_r, Some(quantum), DEFAULT_TIE_SLACK_ULPS);
+    single::log_mul_eps_sanitized(old_value, a, b, &params)
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        clamp_operand, ln_near_one, log_mul_eps, normalize_bounds, quantize_ties_even_linear,
-        round_ties_even, sanitize_eps, sanitize_quantum,
+        double::{
+            clamp_operand, ln_near_one, normalize_bounds, sanitize_eps, sanitize_quantum, ulp,
+        },
+        log_mul_eps, log_mul_eps_batch, log_mul_eps_f32, log_mul_eps_with_mode,
+        log_mul_eps_with_params, quantize_ties_even_linear, round_ties_even_with_slack, single,
+        LogKernelParams, QuantMode, DEFAULT_TIE_SLACK_ULPS,
     };
     use rand::{rngs::StdRng, Rng, SeedableRng};
 
@@ -184,7 +387,9 @@ mod tests {
         let ac = clamp_operand(a, lo, hi);
         let bc = clamp_operand(b, lo, hi);
         let product = ac * bc;
-        let quantised_linear = quantize_ties_even_linear(product, inv_q, quantum).clamp(lo, hi);
+        let quantised_linear =
+            quantize_ties_even_linear(product, inv_q, quantum, DEFAULT_TIE_SLACK_ULPS)
+                .clamp(lo, hi);
         let expected_log = ln_near_one(quantised_linear);
         let actual_linear = result.exp();
         let tol = quantum * 0.5 + f64::EPSILON;
@@ -270,7 +475,8 @@ mod tests {
             let ac = clamp_operand(a, lo, hi);
             let bc = clamp_operand(b, lo, hi);
             let product = ac * bc;
-            let quantised = quantize_ties_even_linear(product, inv_q, q).clamp(lo, hi);
+            let quantised =
+                quantize_ties_even_linear(product, inv_q, q, DEFAULT_TIE_SLACK_ULPS).clamp(lo, hi);
             let expected_log = ln_near_one(quantised);
             let gated_expected = if eps > 0.0 && (expected_log - state).abs() < eps {
                 state
@@ -287,6 +493,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn batch_matches_scalar_bit_for_bit() {
+        let mut rng = StdRng::seed_from_u64(0xBA7C_4E11);
+        let params = LogKernelParams {
+            eps: 5e-6,
+            min_r: 0.5,
+            max_r: 2.0,
+            quantum: 1e-5,
+            tie_slack_ulps: DEFAULT_TIE_SLACK_ULPS,
+        };
+        let len = 10_000;
+        let old_values: Vec<f64> = (0..len).map(|_| rng.random_range(-0.01..0.01)).collect();
+        let a: Vec<f64> = (0..len).map(|_| rng.random_range(0.3..2.5)).collect();
+        let b: Vec<f64> = (0..len).map(|_| rng.random_range(0.3..2.5)).collect();
+
+        let mut out = vec![0.0; len];
+        log_mul_eps_batch(&old_values, &a, &b, &mut out, &params);
+
+        for i in 0..len {
+            let scalar = log_mul_eps(
+                old_values[i],
+                a[i],
+                b[i],
+                params.eps,
+                params.min_r,
+                params.max_r,
+                params.quantum,
+            );
+            assert_eq!(out[i].to_bits(), scalar.to_bits(), "mismatch at {i}");
+        }
+    }
+
+    #[test]
+    fn f32_kernel_clamps_inputs_to_bounds() {
+        let result = log_mul_eps_f32(0.0, 10.0, 0.0001, 1e-6, 0.1, 2.0, 1e-3);
+        let linear = result.exp();
+        assert!(linear <= 2.0 + 1e-5);
+        assert!(linear >= 0.1 - 1e-5);
+    }
+
+    #[test]
+    fn f32_kernel_maps_nan_to_bounds() {
+        let result = log_mul_eps_f32(0.0, f32::NAN, f32::INFINITY, 1e-6, 0.1, 10.0, 1e-3);
+        let linear = result.exp();
+        assert!(linear.is_finite());
+        assert!((0.1 - 1e-5..=10.0 + 1e-4).contains(&linear));
+    }
+
+    #[test]
+    fn f32_kernel_is_idempotent_and_gated() {
+        let first = log_mul_eps_f32(0.0, 1.01, 0.99, 1e-6, 0.5, 2.0, 1e-4);
+        let second = log_mul_eps_f32(first, 1.01, 0.99, 1e-6, 0.5, 2.0, 1e-4);
+        assert_eq!(first, second);
+
+        let raw = log_mul_eps_f32(0.0, 1.0 + 2e-4, 1.0, 0.0, 0.5, 2.0, 1e-4);
+        assert!(raw > 0.0 && raw < 5e-4);
+        assert_eq!(
+            log_mul_eps_f32(0.0, 1.0 + 2e-4, 1.0, 5e-4, 0.5, 2.0, 1e-4),
+            0.0
+        );
+
+        assert_eq!(
+            single::round_ties_even_with_slack(2.5, DEFAULT_TIE_SLACK_ULPS),
+            2.0
+        );
+        assert_eq!(
+            single::round_ties_even_with_slack(-1.5, DEFAULT_TIE_SLACK_ULPS),
+            -2.0
+        );
+    }
+
+    #[test]
+    fn f32_kernel_tracks_f64_kernel() {
+        let mut rng = StdRng::seed_from_u64(0xF32);
+        for _ in 0..1024 {
+            let a = 1.0 + rng.random_range(-0.05..0.05);
+            let b = 1.0 + rng.random_range(-0.05..0.05);
+            let wide = log_mul_eps(0.0, a, b, 0.0, 0.5, 2.0, 1e-4);
+            let narrow = log_mul_eps_f32(0.0, a as f32, b as f32, 0.0, 0.5, 2.0, 1e-4);
+            assert!(
+                (f64::from(narrow) - wide).abs() <= 2e-4,
+                "f32 drifted: {narrow} vs {wide}"
+            );
+        }
+    }
+
+    #[test]
+    fn tie_slack_widens_the_half_way_band() {
+        // 2.5 + 2 ulps: outside the default 1-ulp band, inside a 2-ulp one.
+        let x = 2.5 + 2.0 * ulp(2.5);
+        assert_eq!(round_ties_even_with_slack(x, 1), 3.0);
+        assert_eq!(round_ties_even(x), 3.0);
+        assert_eq!(round_ties_even_with_slack(x, 2), 2.0);
+        assert_eq!(round_ties_even_with_slack(-x, 2), -2.0);
+        assert_eq!(round_ties_even_with_slack(2.5, 0), 2.0);
+
+        // Through the kernel: scaling by a power-of-two quantum is exact, so `a / quantum` sits
+        // 2 ulps above 1026.5 and only the wider band rounds it down to the even 1026.
+        let quantum = 1.0 / 1024.0;
+        let a = f64::from_bits((1026.5f64 * quantum).to_bits() + 2);
+        let params = |tie_slack_ulps| LogKernelParams {
+            eps: 0.0,
+            min_r: 0.5,
+            max_r: 2.0,
+            quantum,
+            tie_slack_ulps,
+        };
+        let default = log_mul_eps_with_params(0.0, a, 1.0, &params(DEFAULT_TIE_SLACK_ULPS));
+        assert_eq!(default, log_mul_eps(0.0, a, 1.0, 0.0, 0.5, 2.0, quantum));
+        assert_eq!(default, (1027.0 * quantum).ln());
+        let wide = log_mul_eps_with_params(0.0, a, 1.0, &params(2));
+        assert_eq!(wide, (1026.0 * quantum).ln());
+    }
+
     #[test]
     fn ties_even_rounding_is_unbiased() {
         // Check positive and negative halfway cases.
@@ -353,7 +673,7 @@ mod tests {
 
         for i in 0..samples {
             let value = if i % 2 == 0 { tie_hi } else { tie_lo };
-            let even = quantize_ties_even_linear(value, inv_q, quantum);
+            let even = quantize_ties_even_linear(value, inv_q, quantum, DEFAULT_TIE_SLACK_ULPS);
             let away = quantize_ties_away_linear(value, inv_q, quantum);
             bias_even += even - value;
             bias_away += away - value;
@@ -372,6 +692,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn quant_mode_none_skips_linear_quantisation() {
+        let (a, b) = (1.2345, 1.1111);
+        let (min_r, max_r) = (0.5, 2.0);
+
+        let raw = log_mul_eps_with_mode(f64::NAN, a, b, 0.0, min_r, max_r, QuantMode::None);
+        assert_eq!(raw, (a * b).clamp(min_r, max_r).ln());
+
+        let quantised =
+            log_mul_eps_with_mode(f64::NAN, a, b, 0.0, min_r, max_r, QuantMode::Linear(1e-3));
+        assert_ne!(quantised, raw);
+        assert!((quantised.exp() - 1.372).abs() < 1e-12);
+        assert_eq!(
+            quantised,
+            log_mul_eps(f64::NAN, a, b, 0.0, min_r, max_r, 1e-3)
+        );
+    }
+
+    /// The kernel's default rounding: ties-to-even with a [`DEFAULT_TIE_SLACK_ULPS`] band.
+    fn round_ties_even(x: f64) -> f64 {
+        round_ties_even_with_slack(x, DEFAULT_TIE_SLACK_ULPS)
+    }
+
     fn round_ties_away(x: f64) -> f64 {
         if !x.is_finite() {
             return x;
@@ -381,9 +724,7 @@ mod tests {
         let frac = x - floor;
         if frac < 0.5 {
             floor
-        } else if frac > 0.5 {
-            ceil
-        } else if x >= 0.0 {
+        } else if frac > 0.5 || x >= 0.0 {
             ceil
         } else {
             floor
@@ -1,16 +1,69 @@
+use clap::Parser;
 use optimizer::{
     dataset::{self, Dataset},
-    pipeline::{self, PipelineConfig},
+    error::DatasetError,
+    pipeline::{self, ConfigError, PipelineConfig},
 };
+use std::{path::PathBuf, time::Duration};
 use tracing::info;
 use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 
+/// Run the arbitrage pipeline over a dataset. Every flag is optional; omitted ones keep the
+/// built-in defaults.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Dataset JSON to load instead of the bundled one.
+    #[arg(long)]
+    dataset: Option<PathBuf>,
+    /// Longest cycle, in edges, the searcher looks for.
+    #[arg(long)]
+    hop_cap: Option<usize>,
+    /// Synthetic updates the producer sends before the run ends.
+    #[arg(long)]
+    max_updates: Option<usize>,
+    /// Milliseconds between search passes.
+    #[arg(long)]
+    search_interval_ms: Option<u64>,
+    /// Only report cycles whose rate product reaches this ratio (e.g. 1.005).
+    #[arg(long)]
+    min_profit: Option<f64>,
+}
+
+impl Cli {
+    fn load_dataset(&self) -> Result<Dataset, DatasetError> {
+        match self.dataset {
+            Some(ref path) => Dataset::load_from_path(path),
+            None => dataset::load_default_dataset(),
+        }
+    }
+
+    /// Overlay the given flags on [`PipelineConfig::default`] and validate the result.
+    fn pipeline_config(&self) -> Result<PipelineConfig, ConfigError> {
+        let mut builder = PipelineConfig::builder();
+        if let Some(hop_cap) = self.hop_cap {
+            builder = builder.hop_cap(hop_cap);
+        }
+        if let Some(max_updates) = self.max_updates {
+            builder = builder.max_updates(max_updates);
+        }
+        if let Some(ms) = self.search_interval_ms {
+            builder = builder.search_interval(Duration::from_millis(ms));
+        }
+        if let Some(ratio) = self.min_profit {
+            builder = builder.min_profit_ratio(ratio);
+        }
+        builder.build()
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
     init_tracing()?;
 
-    let dataset: Dataset = dataset::load_default_dataset()?;
-    let stats = pipeline::run(dataset, PipelineConfig::default()).await?;
+    let dataset = cli.load_dataset()?;
+    let stats = pipeline::run(dataset, cli.pipeline_config()?).await?;
     if let Some(ref cycle) = stats.last_cycle {
         info!(
             updates_processed = stats.updates_processed,
@@ -18,6 +71,7 @@ async fn main() -> anyhow::Result<()> {
             cycle_profit = cycle.profit,
             cycle_neg_log = cycle.neg_log_sum,
             vertices = ?cycle.vertices,
+            symbols = ?stats.last_cycle_symbols,
             edge_indexes = ?cycle.edge_indexes,
             "pipeline finished with profitable cycle"
         );
@@ -46,3 +100,44 @@ fn init_tracing() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_map_onto_pipeline_config() {
+        let defaults = Cli::parse_from(["optimizer"]).pipeline_config().unwrap();
+        let baseline = PipelineConfig::default();
+        assert_eq!(defaults.hop_cap, baseline.hop_cap);
+        assert_eq!(defaults.max_updates, baseline.max_updates);
+        assert_eq!(defaults.search_interval, baseline.search_interval);
+        assert_eq!(defaults.min_profit_ratio, baseline.min_profit_ratio);
+
+        let cli = Cli::parse_from([
+            "optimizer",
+            "--dataset",
+            "data/other.json",
+            "--hop-cap",
+            "3",
+            "--max-updates",
+            "10",
+            "--search-interval-ms",
+            "40",
+            "--min-profit",
+            "1.005",
+        ]);
+        assert_eq!(cli.dataset, Some(PathBuf::from("data/other.json")));
+        let config = cli.pipeline_config().unwrap();
+        assert_eq!(config.hop_cap, 3);
+        assert_eq!(config.max_updates, 10);
+        assert_eq!(config.search_interval, Duration::from_millis(40));
+        assert_eq!(config.min_profit_ratio, Some(1.005));
+
+        let invalid = Cli::parse_from(["optimizer", "--search-interval-ms", "0"]);
+        assert!(matches!(
+            invalid.pipeline_config(),
+            Err(ConfigError::ZeroSearchInterval)
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+//! The producer → writer pipe, in either [`ChannelMode`].
+//!
+//! `Bounded` is a plain `mpsc` channel. `DropOldest` puts a small keyed ring in front of it:
+//! the producer writes into the ring without ever waiting, a newer update for the same edge
+//! replaces the pending one in place, and once the ring is full the oldest pending update is
+//! evicted. A pump task feeds the ring into the bounded channel the writer reads, so the writer
+//! code is the same in both modes.
+
+use super::{config::ChannelMode, types::GraphUpdate};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tokio::{
+    sync::{
+        mpsc::{
+            self,
+            error::{SendError, TrySendError},
+        },
+        Notify,
+    },
+    task::{AbortHandle, JoinHandle},
+};
+use tracing::debug;
+
+/// Producer end of the update pipe.
+#[derive(Debug, Clone)]
+pub(super) enum UpdateSender {
+    Bounded(mpsc::Sender<GraphUpdate>),
+    DropOldest(LatestSender),
+}
+
+impl From<mpsc::Sender<GraphUpdate>> for UpdateSender {
+    fn from(sender: mpsc::Sender<GraphUpdate>) -> Self {
+        Self::Bounded(sender)
+    }
+}
+
+impl UpdateSender {
+    /// Never fails with `Full` in `DropOldest` mode.
+    pub(super) fn try_send(&self, update: GraphUpdate) -> Result<(), TrySendError<GraphUpdate>> {
+        match self {
+            Self::Bounded(sender) => sender.try_send(update),
+            Self::DropOldest(sender) => sender
+                .push(update)
+                .map_err(|SendError(update)| TrySendError::Closed(update)),
+        }
+    }
+
+    pub(super) async fn send(&self, update: GraphUpdate) -> Result<(), SendError<GraphUpdate>> {
+        match self {
+            Self::Bounded(sender) => sender.send(update).await,
+            Self::DropOldest(sender) => sender.push(update),
+        }
+    }
+}
+
+/// Build the pipe for `mode`: the producer's sender, the writer's receiver, and in
+/// `DropOldest` mode the pump task plus the ring for reading its drop counter.
+pub(super) fn update_pipe(
+    mode: ChannelMode,
+    capacity: usize,
+) -> (UpdateSender, mpsc::Receiver<GraphUpdate>, Option<Pump>) {
+    let (sender, receiver) = mpsc::channel(capacity);
+    match mode {
+        ChannelMode::Bounded => (sender.into(), receiver, None),
+        ChannelMode::DropOldest => {
+            let ring = Arc::new(LatestUpdates::new(capacity));
+            let pump = Pump {
+                ring: Arc::clone(&ring),
+                task: tokio::spawn(pump(Arc::clone(&ring), sender)),
+            };
+            (
+                UpdateSender::DropOldest(LatestSender(Arc::new(SenderGuard(ring)))),
+                receiver,
+                Some(pump),
+            )
+        }
+    }
+}
+
+/// The `DropOldest` forwarding task and the ring it drains.
+pub(super) struct Pump {
+    ring: Arc<LatestUpdates>,
+    task: JoinHandle<()>,
+}
+
+impl Pump {
+    pub(super) fn abort_handle(&self) -> AbortHandle {
+        self.task.abort_handle()
+    }
+
+    /// Wait for the ring to drain into the writer channel and report how many updates were
+    /// superseded or evicted along the way.
+    pub(super) async fn finish(self) -> usize {
+        if let Err(err) = self.task.await {
+            debug!(error = %err, "update pump task failed");
+        }
+        self.ring.state.lock().dropped
+    }
+}
+
+async fn pump(ring: Arc<LatestUpdates>, writer: mpsc::Sender<GraphUpdate>) {
+    while let Some(update) = ring.recv().await {
+        if writer.send(update).await.is_err() {
+            break;
+        }
+    }
+    ring.state.lock().receiver_gone = true;
+}
+
+/// Cloneable producer handle on the ring; the ring closes once the last clone is dropped.
+#[derive(Debug, Clone)]
+pub(super) struct LatestSender(Arc<SenderGuard>);
+
+impl LatestSender {
+    fn push(&self, update: GraphUpdate) -> Result<(), SendError<GraphUpdate>> {
+        self.0 .0.push(update)
+    }
+}
+
+#[derive(Debug)]
+struct SenderGuard(Arc<LatestUpdates>);
+
+impl Drop for SenderGuard {
+    fn drop(&mut self) {
+        self.0.state.lock().closed = true;
+        self.0.ready.notify_one();
+    }
+}
+
+/// Pending updates by key, oldest first. Rate and fee writes for the same edge coalesce like
+/// the writer's own last-write-wins; rate deltas compound, so each one gets its own slot. A rate
+/// that replaces a pending one also drops that edge's pending deltas: they were queued after the
+/// old rate, so keeping them would compound them onto the new absolute rate.
+#[derive(Debug)]
+struct LatestUpdates {
+    capacity: usize,
+    state: Mutex<RingState>,
+    ready: Notify,
+}
+
+#[derive(Debug, Default)]
+struct RingState {
+    order: VecDeque<UpdateKey>,
+    pending: HashMap<UpdateKey, GraphUpdate>,
+    next_delta: u64,
+    dropped: usize,
+    closed: bool,
+    receiver_gone: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum UpdateKey {
+    Rate(usize),
+    Fee(usize),
+    Delta(u64),
+}
+
+impl RingState {
+    fn drop_pending_deltas(&mut self, edge_index: usize) {
+        let RingState {
+            order,
+            pending,
+            dropped,
+            ..
+        } = self;
+        order.retain(|key| {
+            let superseded = matches!(key, UpdateKey::Delta(_))
+                && pending.get(key).map(GraphUpdate::edge_index) == Some(edge_index);
+            if superseded {
+                pending.remove(key);
+                *dropped += 1;
+            }
+            !superseded
+        });
+    }
+}
+
+impl LatestUpdates {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(RingState::default()),
+            ready: Notify::new(),
+        }
+    }
+
+    fn push(&self, update: GraphUpdate) -> Result<(), SendError<GraphUpdate>> {
+        let mut state = self.state.lock();
+        if state.receiver_gone {
+            return Err(SendError(update));
+        }
+        let key = match update {
+            GraphUpdate::Rate { edge_index, .. } => UpdateKey::Rate(edge_index),
+            GraphUpdate::Fee { edge_index, .. } => UpdateKey::Fee(edge_index),
+            GraphUpdate::RateDelta { .. } => {
+                state.next_delta += 1;
+                UpdateKey::Delta(state.next_delta)
+            }
+        };
+        if let Some(pending) = state.pending.get_mut(&key) {
+            *pending = update;
+            state.dropped += 1;
+            if let UpdateKey::Rate(edge_index) = key {
+                state.drop_pending_deltas(edge_index);
+            }
+            return Ok(());
+        }
+        if state.order.len() >= self.capacity {
+            if let Some(oldest) = state.order.pop_front() {
+                state.pending.remove(&oldest);
+                state.dropped += 1;
+            }
+        }
+        state.order.push_back(key);
+        state.pending.insert(key, update);
+        drop(state);
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Next pending update, oldest first; `None` once the ring is closed and empty.
+    async fn recv(&self) -> Option<GraphUpdate> {
+        loop {
+            {
+                let mut state = self.state.lock();
+                if let Some(key) = state.order.pop_front() {
+                    return state.pending.remove(&key);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            // A push between the check and here leaves a permit, so this cannot miss it.
+            self.ready.notified().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(edge_index: usize, new_rate: f64) -> GraphUpdate {
+        GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            seq: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn ring_keeps_freshest_rate_and_evicts_oldest() {
+        let ring = LatestUpdates::new(2);
+        for update in [rate(0, 1.0), rate(1, 2.0), rate(0, 1.5), rate(2, 3.0)] {
+            ring.push(update).expect("ring open");
+        }
+        ring.state.lock().closed = true;
+
+        let mut drained = Vec::new();
+        while let Some(GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            ..
+        }) = ring.recv().await
+        {
+            drained.push((edge_index, new_rate));
+        }
+        // Edge 0 was refreshed in place, then evicted as the oldest entry to make room for 2.
+        assert_eq!(drained, vec![(1, 2.0), (2, 3.0)]);
+        assert_eq!(ring.state.lock().dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn replacing_a_rate_drops_deltas_queued_behind_it() {
+        let ring = LatestUpdates::new(8);
+        let delta = |edge_index, pct_change| GraphUpdate::RateDelta {
+            edge_index,
+            pct_change,
+        };
+        for update in [rate(0, 1.2), delta(0, 0.1), delta(1, 0.2), rate(0, 1.5)] {
+            ring.push(update).expect("ring open");
+        }
+        ring.state.lock().closed = true;
+
+        let mut drained = Vec::new();
+        while let Some(update) = ring.recv().await {
+            drained.push(match update {
+                GraphUpdate::Rate {
+                    edge_index,
+                    new_rate,
+                    ..
+                } => ("rate", edge_index, new_rate),
+                GraphUpdate::RateDelta {
+                    edge_index,
+                    pct_change,
+                } => ("delta", edge_index, pct_change),
+                GraphUpdate::Fee { .. } => unreachable!("no fee updates were pushed"),
+            });
+        }
+        // Applying the delta after the refreshed rate would land on 1.5 * 1.1, not 1.5.
+        assert_eq!(drained, vec![("rate", 0, 1.5), ("delta", 1, 0.2)]);
+        assert_eq!(ring.state.lock().dropped, 2);
+    }
+}
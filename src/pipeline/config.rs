@@ -1,16 +1,201 @@
-use std::time::Duration;
+use super::types::{BoxedUpdateSource, CycleCallback, CycleSink, UpdateSource};
+use crate::{csr_graph::DedupPolicy, error::ConfigError};
+use parking_lot::Mutex;
+use std::{fmt, path::PathBuf, sync::Arc, time::Duration};
+use tokio::io::AsyncBufRead;
+use tracing::warn;
+
+/// Shortest search cadence the pipeline runs with. [`PipelineConfig::validate`] rejects anything
+/// below it; configs that skip validation are clamped up to it at startup.
+pub const MIN_SEARCH_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How the producer derives the random stream used for rate jitter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JitterSeeding {
+    /// Jitter comes from the same RNG that picks edges, so every edge's series depends on the
+    /// global update order.
+    #[default]
+    Shared,
+    /// Every edge draws jitter from its own RNG seeded from `(rng_seed, edge_index)`, so an
+    /// edge's series is reproducible regardless of how updates interleave.
+    PerEdge,
+}
+
+/// Distribution the producer draws each relative rate shock from; the jittered rate is
+/// `base_rate * (1 + shock)`, clamped to `[min_rate_bound, max_rate_bound]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum JitterModel {
+    /// Uniform in `[-rate_jitter, rate_jitter)`.
+    #[default]
+    Uniform,
+    /// Gaussian with mean 0 and standard deviation `std`.
+    Normal { std: f64 },
+    /// Student's t with `df` degrees of freedom, scaled by `scale`. Low `df` gives the
+    /// occasional large shock that `Normal` almost never produces.
+    StudentT { df: f64, scale: f64 },
+}
+
+impl JitterModel {
+    fn validate(self) -> Result<(), ConfigError> {
+        let (df, scale) = match self {
+            Self::Uniform => return Ok(()),
+            Self::Normal { std } => (None, std),
+            Self::StudentT { df, scale } => (Some(df), scale),
+        };
+        let df_ok = df.is_none_or(|df| df.is_finite() && df > 0.0);
+        if df_ok && scale.is_finite() && scale >= 0.0 {
+            Ok(())
+        } else {
+            Err(ConfigError::InvalidJitterModel { df, scale })
+        }
+    }
+}
+
+/// What makes the searcher run a pass. Every mode also runs one final pass at shutdown and one
+/// per [`super::PipelineHandle::trigger_search`] call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchTrigger {
+    /// Search every `search_interval`.
+    #[default]
+    Interval,
+    /// No timer; search only when [`super::PipelineHandle::trigger_search`] is called.
+    OnDemand,
+    /// No timer; the writer requests one search after every batch it applies.
+    OnUpdate,
+}
+
+/// How the producer hands updates to the writer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// Bounded queue of `channel_capacity`; the producer waits while it is full.
+    #[default]
+    Bounded,
+    /// Lossy but fresh: pending updates are keyed by edge, so a newer rate or fee replaces the
+    /// queued one, and at `channel_capacity` pending edges the oldest is evicted. The producer
+    /// never waits; superseded and evicted updates are counted in
+    /// [`super::PipelineStats::dropped_updates`].
+    DropOldest,
+}
+
+/// Searcher filter that drops cycles whose [`crate::cycle_finder::Cycle::net_profit`] is not
+/// positive for a trade of `reference_size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasFilter {
+    pub cost_per_hop: f64,
+    pub reference_size: f64,
+}
+
+/// Line-oriented input consumed by [`ProducerSource::JsonLines`].
+pub type UpdateReader = Box<dyn AsyncBufRead + Send + Unpin>;
+
+/// Where the producer's updates come from.
+#[derive(Clone, Default)]
+pub enum ProducerSource {
+    /// Random jitter around the dataset's baseline rates, bounded by `max_updates`.
+    #[default]
+    Synthetic,
+    /// Newline-delimited `{"edge_index":N,"new_rate":R}` objects, read until EOF. The reader is
+    /// handed to the first pipeline started with this config; `max_updates` and `rate_jitter`
+    /// are ignored.
+    JsonLines(Arc<Mutex<Option<UpdateReader>>>),
+    /// Caller-supplied [`UpdateSource`], drained until it returns `None`. Like `JsonLines`, it is
+    /// handed to the first pipeline started with this config; `max_updates` and `rate_jitter`
+    /// are ignored.
+    Custom(Arc<Mutex<Option<BoxedUpdateSource>>>),
+}
+
+impl ProducerSource {
+    pub fn json_lines(reader: impl AsyncBufRead + Send + Unpin + 'static) -> Self {
+        Self::JsonLines(Arc::new(Mutex::new(Some(Box::new(reader)))))
+    }
+
+    pub fn custom(source: impl UpdateSource + 'static) -> Self {
+        Self::Custom(Arc::new(Mutex::new(Some(BoxedUpdateSource::new(source)))))
+    }
+}
+
+impl fmt::Debug for ProducerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Synthetic => f.write_str("Synthetic"),
+            Self::JsonLines(_) => f.write_str("JsonLines(..)"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct PipelineConfig {
     pub hop_cap: usize,
     pub max_updates: usize,
     pub channel_capacity: usize,
+    /// Whether a full update pipe blocks the producer or drops stale updates; see
+    /// [`ChannelMode`].
+    pub channel_mode: ChannelMode,
     pub search_interval: Duration,
+    /// What schedules search passes; see [`SearchTrigger`].
+    pub search_trigger: SearchTrigger,
     pub coalesce_window: Duration,
     pub max_coalesce: usize,
+    /// Half-width of the [`JitterModel::Uniform`] shock; unused by the other models.
     pub rate_jitter: f64,
+    /// Shape of the producer's rate shocks; see [`JitterModel`].
+    pub jitter_model: JitterModel,
     pub min_rate_bound: f64,
     pub max_rate_bound: f64,
+    /// Where updates come from; see [`ProducerSource`].
+    pub producer_source: ProducerSource,
+    /// Receives every profitable cycle the searcher finds, tagged with its discovery time.
+    pub cycle_sink: Option<CycleSink>,
+    /// Called with every profitable cycle before the searcher records it.
+    pub on_cycle: Option<CycleCallback>,
+    /// How many recent cycles [`super::PipelineStats::recent_cycles`] retains. Repeat
+    /// detections of the same cycle in a row are recorded once; `0` disables the history.
+    pub cycle_history_len: usize,
+    /// Only report cycles whose rate product reaches this ratio (e.g. `1.005` for 0.5%).
+    /// `None` reports any profitable cycle.
+    pub min_profit_ratio: Option<f64>,
+    /// Expand dataset edges of kind [`crate::dataset::BIDIRECTIONAL_EDGE_KIND`] into a forward
+    /// edge and a reverse edge at `1 / rate`, placed right after it and tagged
+    /// [`crate::dataset::REVERSED_EDGE_KIND`].
+    pub expand_bidirectional: bool,
+    /// Collapse duplicate `(from, to)` pairs in the dataset before building the graph.
+    /// `None` keeps every edge.
+    pub dedup_edges: Option<DedupPolicy>,
+    /// Only report cycles that still profit after gas at a reference trade size. `None`
+    /// ignores gas.
+    pub gas_filter: Option<GasFilter>,
+    /// Start each search sweep from the nodes whose edges changed most recently, so fresh
+    /// opportunities surface first. Only the scan order changes.
+    pub recent_first_search: bool,
+    /// Warm-start each search from the previous pass: every start node's result is kept, and
+    /// only start nodes within `hop_cap` hops upstream of an edge updated since the last pass
+    /// are searched again. Reports exactly the cycle a full search would.
+    pub incremental_search: bool,
+    /// Stop producing as soon as the searcher records its first profitable cycle.
+    pub stop_on_first_cycle: bool,
+    /// Validate, coalesce and clamp updates and count them as usual, but never write them to
+    /// the graph, so every search sees the baseline rates. For vetting an update feed.
+    pub dry_run: bool,
+    /// Abort the run once this fraction of updates has failed validation (after a minimum
+    /// sample). `None` tolerates any amount of bad input.
+    pub max_invalid_fraction: Option<f64>,
+    /// Cadence of full-graph snapshots, each saved as a dataset that
+    /// [`crate::dataset::Dataset::load_from_path`] reads back; requires `snapshot_dir` as well.
+    pub snapshot_interval: Option<Duration>,
+    /// Directory timestamped snapshots are written to (created if missing).
+    pub snapshot_dir: Option<PathBuf>,
+    /// Seed for the producer's RNG so identical configs replay identical update streams.
+    /// `None` draws a fresh seed per run.
+    pub rng_seed: Option<u64>,
+    /// Where jitter randomness comes from; see [`JitterSeeding`].
+    pub jitter_seeding: JitterSeeding,
+    /// How long [`super::run_with_timeout`] waits for the graceful shutdown after its deadline
+    /// before aborting the run.
+    pub shutdown_grace: Duration,
+    /// Sleep injected after every search pass to reproduce slow-searcher scenarios.
+    #[cfg(feature = "chaos")]
+    pub artificial_search_delay: Duration,
 }
 
 impl Default for PipelineConfig {
@@ -19,13 +204,254 @@ impl Default for PipelineConfig {
             hop_cap: 6,
             max_updates: 256,
             channel_capacity: 64,
+            channel_mode: ChannelMode::Bounded,
             search_interval: Duration::from_millis(250),
+            search_trigger: SearchTrigger::Interval,
             coalesce_window: Duration::from_millis(5),
             max_coalesce: 16,
             rate_jitter: 0.02,
+            jitter_model: JitterModel::Uniform,
             min_rate_bound: 1e-9,
             max_rate_bound: 1e9,
+            producer_source: ProducerSource::Synthetic,
+            cycle_sink: None,
+            on_cycle: None,
+            cycle_history_len: 16,
+            min_profit_ratio: None,
+            gas_filter: None,
+            expand_bidirectional: false,
+            dedup_edges: None,
+            recent_first_search: false,
+            incremental_search: false,
+            stop_on_first_cycle: false,
+            dry_run: false,
+            max_invalid_fraction: None,
+            snapshot_interval: None,
+            snapshot_dir: None,
+            rng_seed: None,
+            jitter_seeding: JitterSeeding::Shared,
+            shutdown_grace: Duration::from_secs(5),
+            #[cfg(feature = "chaos")]
+            artificial_search_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn builder() -> PipelineConfigBuilder {
+        PipelineConfigBuilder::default()
+    }
+
+    /// Reject settings that would otherwise be silently corrected or panic at runtime.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.channel_capacity == 0 {
+            return Err(ConfigError::ZeroChannelCapacity);
+        }
+        if !self.rate_jitter.is_finite() {
+            return Err(ConfigError::NonFiniteRateJitter(self.rate_jitter));
+        }
+        self.jitter_model.validate()?;
+        if self.search_interval.is_zero() {
+            return Err(ConfigError::ZeroSearchInterval);
+        }
+        if self.search_interval < MIN_SEARCH_INTERVAL {
+            return Err(ConfigError::SearchIntervalBelowFloor {
+                interval: self.search_interval,
+                floor: MIN_SEARCH_INTERVAL,
+            });
+        }
+        if let Some(ratio) = self.min_profit_ratio {
+            if !ratio.is_finite() || ratio <= 0.0 {
+                return Err(ConfigError::InvalidMinProfitRatio(ratio));
+            }
         }
+        if self.min_rate_bound > self.max_rate_bound {
+            return Err(ConfigError::InvertedRateBounds {
+                min: self.min_rate_bound,
+                max: self.max_rate_bound,
+            });
+        }
+        Ok(())
+    }
+
+    /// Raise `search_interval` to [`MIN_SEARCH_INTERVAL`] so the searcher can never busy-spin.
+    pub(super) fn clamp_search_interval(&mut self) {
+        if self.search_interval < MIN_SEARCH_INTERVAL {
+            warn!(
+                requested = ?self.search_interval,
+                floor = ?MIN_SEARCH_INTERVAL,
+                "search_interval below floor; clamping"
+            );
+            self.search_interval = MIN_SEARCH_INTERVAL;
+        }
+    }
+}
+
+/// Fluent alternative to struct-update syntax; [`PipelineConfigBuilder::build`] validates.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineConfigBuilder {
+    config: PipelineConfig,
+}
+
+impl PipelineConfigBuilder {
+    pub fn hop_cap(mut self, hop_cap: usize) -> Self {
+        self.config.hop_cap = hop_cap;
+        self
+    }
+
+    pub fn max_updates(mut self, max_updates: usize) -> Self {
+        self.config.max_updates = max_updates;
+        self
+    }
+
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.config.channel_capacity = channel_capacity;
+        self
+    }
+
+    pub fn channel_mode(mut self, channel_mode: ChannelMode) -> Self {
+        self.config.channel_mode = channel_mode;
+        self
+    }
+
+    pub fn search_interval(mut self, search_interval: Duration) -> Self {
+        self.config.search_interval = search_interval;
+        self
+    }
+
+    pub fn search_trigger(mut self, search_trigger: SearchTrigger) -> Self {
+        self.config.search_trigger = search_trigger;
+        self
+    }
+
+    pub fn coalesce_window(mut self, coalesce_window: Duration) -> Self {
+        self.config.coalesce_window = coalesce_window;
+        self
+    }
+
+    pub fn max_coalesce(mut self, max_coalesce: usize) -> Self {
+        self.config.max_coalesce = max_coalesce;
+        self
+    }
+
+    pub fn rate_jitter(mut self, rate_jitter: f64) -> Self {
+        self.config.rate_jitter = rate_jitter;
+        self
+    }
+
+    pub fn jitter_model(mut self, jitter_model: JitterModel) -> Self {
+        self.config.jitter_model = jitter_model;
+        self
+    }
+
+    pub fn min_rate_bound(mut self, min_rate_bound: f64) -> Self {
+        self.config.min_rate_bound = min_rate_bound;
+        self
+    }
+
+    pub fn max_rate_bound(mut self, max_rate_bound: f64) -> Self {
+        self.config.max_rate_bound = max_rate_bound;
+        self
+    }
+
+    pub fn producer_source(mut self, producer_source: ProducerSource) -> Self {
+        self.config.producer_source = producer_source;
+        self
+    }
+
+    pub fn cycle_sink(mut self, cycle_sink: CycleSink) -> Self {
+        self.config.cycle_sink = Some(cycle_sink);
+        self
+    }
+
+    pub fn on_cycle(mut self, on_cycle: CycleCallback) -> Self {
+        self.config.on_cycle = Some(on_cycle);
+        self
+    }
+
+    pub fn cycle_history_len(mut self, cycle_history_len: usize) -> Self {
+        self.config.cycle_history_len = cycle_history_len;
+        self
+    }
+
+    pub fn min_profit_ratio(mut self, min_profit_ratio: f64) -> Self {
+        self.config.min_profit_ratio = Some(min_profit_ratio);
+        self
+    }
+
+    pub fn expand_bidirectional(mut self, expand_bidirectional: bool) -> Self {
+        self.config.expand_bidirectional = expand_bidirectional;
+        self
+    }
+
+    pub fn dedup_edges(mut self, policy: DedupPolicy) -> Self {
+        self.config.dedup_edges = Some(policy);
+        self
+    }
+
+    pub fn gas_filter(mut self, cost_per_hop: f64, reference_size: f64) -> Self {
+        self.config.gas_filter = Some(GasFilter {
+            cost_per_hop,
+            reference_size,
+        });
+        self
+    }
+
+    pub fn recent_first_search(mut self, recent_first_search: bool) -> Self {
+        self.config.recent_first_search = recent_first_search;
+        self
+    }
+
+    pub fn incremental_search(mut self, incremental_search: bool) -> Self {
+        self.config.incremental_search = incremental_search;
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.config.dry_run = dry_run;
+        self
+    }
+
+    pub fn stop_on_first_cycle(mut self, stop_on_first_cycle: bool) -> Self {
+        self.config.stop_on_first_cycle = stop_on_first_cycle;
+        self
+    }
+
+    pub fn max_invalid_fraction(mut self, max_invalid_fraction: f64) -> Self {
+        self.config.max_invalid_fraction = Some(max_invalid_fraction);
+        self
+    }
+
+    pub fn snapshots(mut self, interval: Duration, dir: impl Into<PathBuf>) -> Self {
+        self.config.snapshot_interval = Some(interval);
+        self.config.snapshot_dir = Some(dir.into());
+        self
+    }
+
+    pub fn rng_seed(mut self, rng_seed: u64) -> Self {
+        self.config.rng_seed = Some(rng_seed);
+        self
+    }
+
+    pub fn shutdown_grace(mut self, shutdown_grace: Duration) -> Self {
+        self.config.shutdown_grace = shutdown_grace;
+        self
+    }
+
+    pub fn jitter_seeding(mut self, jitter_seeding: JitterSeeding) -> Self {
+        self.config.jitter_seeding = jitter_seeding;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn artificial_search_delay(mut self, delay: Duration) -> Self {
+        self.config.artificial_search_delay = delay;
+        self
+    }
+
+    pub fn build(self) -> Result<PipelineConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
     }
 }
 
@@ -47,3 +473,97 @@ impl RateBounds {
         rate.clamp(self.min, self.max)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_applies_setters_over_defaults() {
+        let config = PipelineConfig::builder()
+            .hop_cap(3)
+            .max_updates(10)
+            .search_interval(Duration::from_millis(7))
+            .build()
+            .expect("valid config");
+
+        assert_eq!(config.hop_cap, 3);
+        assert_eq!(config.max_updates, 10);
+        assert_eq!(config.search_interval, Duration::from_millis(7));
+        assert_eq!(
+            config.channel_capacity,
+            PipelineConfig::default().channel_capacity
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_fields() {
+        assert_eq!(
+            PipelineConfig::builder().channel_capacity(0).build().err(),
+            Some(ConfigError::ZeroChannelCapacity)
+        );
+        assert!(matches!(
+            PipelineConfig::builder().rate_jitter(f64::NAN).build(),
+            Err(ConfigError::NonFiniteRateJitter(_))
+        ));
+        let fat_tails = JitterModel::StudentT {
+            df: 0.0,
+            scale: 0.01,
+        };
+        assert_eq!(
+            PipelineConfig::builder()
+                .jitter_model(fat_tails)
+                .build()
+                .err(),
+            Some(ConfigError::InvalidJitterModel {
+                df: Some(0.0),
+                scale: 0.01
+            })
+        );
+        assert_eq!(
+            PipelineConfig::builder()
+                .search_interval(Duration::ZERO)
+                .build()
+                .err(),
+            Some(ConfigError::ZeroSearchInterval)
+        );
+        assert!(matches!(
+            PipelineConfig::builder()
+                .search_interval(Duration::from_micros(500))
+                .build(),
+            Err(ConfigError::SearchIntervalBelowFloor { .. })
+        ));
+        assert_eq!(
+            PipelineConfig::builder()
+                .min_rate_bound(2.0)
+                .max_rate_bound(1.0)
+                .build()
+                .err(),
+            Some(ConfigError::InvertedRateBounds { min: 2.0, max: 1.0 })
+        );
+        assert_eq!(
+            PipelineConfig::builder()
+                .min_profit_ratio(0.0)
+                .build()
+                .err(),
+            Some(ConfigError::InvalidMinProfitRatio(0.0))
+        );
+    }
+
+    #[test]
+    fn clamp_raises_search_interval_to_floor() {
+        let mut config = PipelineConfig {
+            search_interval: Duration::ZERO,
+            ..PipelineConfig::default()
+        };
+        config.clamp_search_interval();
+        assert_eq!(config.search_interval, MIN_SEARCH_INTERVAL);
+
+        let mut config = PipelineConfig::default();
+        config.clamp_search_interval();
+        assert_eq!(
+            config.search_interval,
+            PipelineConfig::default().search_interval
+        );
+    }
+}
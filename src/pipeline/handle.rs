@@ -0,0 +1,146 @@
+use super::{
+    stats::PipelineStats,
+    types::{ChildTasks, DirtyEdges, SearchTriggerSender, SearcherControl, SharedGraph},
+};
+use crate::{csr_graph::CSRGraph, error::PipelineError};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+/// Control surface for a pipeline started with [`super::spawn`].
+#[derive(Debug)]
+pub struct PipelineHandle {
+    pub(super) shared_graph: SharedGraph,
+    baseline_rates: Arc<[f64]>,
+    stop: watch::Sender<bool>,
+    search_trigger: SearchTriggerSender,
+    searcher_paused: watch::Sender<bool>,
+    dirty_edges: Option<DirtyEdges>,
+    task: JoinHandle<Result<PipelineStats, PipelineError>>,
+    child_tasks: ChildTasks,
+}
+
+/// The driver task of a run and the tasks it spawns.
+pub(super) struct RunTasks {
+    pub(super) driver: JoinHandle<Result<PipelineStats, PipelineError>>,
+    pub(super) children: ChildTasks,
+}
+
+impl PipelineHandle {
+    pub(super) fn new(
+        shared_graph: SharedGraph,
+        baseline_rates: Arc<[f64]>,
+        stop: watch::Sender<bool>,
+        search_trigger: SearchTriggerSender,
+        searcher_paused: watch::Sender<bool>,
+        dirty_edges: Option<DirtyEdges>,
+        tasks: RunTasks,
+    ) -> Self {
+        Self {
+            shared_graph,
+            baseline_rates,
+            stop,
+            search_trigger,
+            searcher_paused,
+            dirty_edges,
+            task: tasks.driver,
+            child_tasks: tasks.children,
+        }
+    }
+
+    /// Re-apply the dataset's original rates to every live edge under a single write lock.
+    /// The searcher sees the reset graph on its next tick.
+    pub fn reset_to_baseline(&self) {
+        let mut graph = self.shared_graph.write();
+        let mut restored = Vec::new();
+        for (edge_index, &rate) in self.baseline_rates.iter().enumerate() {
+            if graph.update_rate(edge_index, rate).is_ok() {
+                restored.push(edge_index);
+            }
+        }
+        drop(graph);
+        let restored_count = restored.len();
+        // Report the reset like a writer batch so searches that track changes see it.
+        if let Some(dirty_edges) = self.dirty_edges.as_ref() {
+            dirty_edges.lock().extend(restored);
+        }
+        info!(restored = restored_count, "graph reset to baseline rates");
+    }
+
+    /// Ask the pipeline to wind down early: the producer stops sending, the writer drains what
+    /// is already queued and the searcher runs its final pass. [`PipelineHandle::join`] then
+    /// returns stats covering everything processed up to that point.
+    pub fn cancel(&self) {
+        if !self.stop.send_replace(true) {
+            info!("pipeline cancellation requested");
+        }
+    }
+
+    /// Rates the graph was built with, indexed by edge (dataset order, after any
+    /// [`super::PipelineConfig::dedup_edges`]). Compare against [`PipelineHandle::snapshot`] to
+    /// measure drift.
+    pub fn baseline_rates(&self) -> &[f64] {
+        &self.baseline_rates
+    }
+
+    /// Ask the searcher for one extra pass as soon as it is free. This is the only way passes run
+    /// under [`super::SearchTrigger::OnDemand`]; in the other modes it adds to the schedule.
+    pub fn trigger_search(&self) {
+        if self.search_trigger.send(()).is_err() {
+            debug!("searcher already finished; ignoring search trigger");
+        }
+    }
+
+    /// Pause or resume search passes without stopping the pipeline; updates keep flowing into
+    /// the graph while paused.
+    pub fn control_searcher(&self, control: SearcherControl) {
+        let paused = control == SearcherControl::Pause;
+        if self.searcher_paused.send_replace(paused) != paused {
+            info!(paused, "searcher control changed");
+        }
+    }
+
+    /// Copy of the graph as the pipeline currently sees it.
+    pub fn snapshot(&self) -> CSRGraph {
+        self.shared_graph.read().clone()
+    }
+
+    /// Wait for the run to finish and collect its stats.
+    pub async fn join(self) -> Result<PipelineStats, PipelineError> {
+        self.task.await.map_err(PipelineError::DriverJoin)?
+    }
+
+    /// Like [`PipelineHandle::join`], but once `deadline` passes the run is cancelled through
+    /// [`PipelineHandle::cancel`] and the partial stats come back with `timed_out` set. A run
+    /// still going `grace` after that is aborted, along with every task it spawned, with
+    /// [`PipelineError::ShutdownTimedOut`].
+    pub(super) async fn join_within(
+        mut self,
+        deadline: Duration,
+        grace: Duration,
+    ) -> Result<PipelineStats, PipelineError> {
+        if let Ok(joined) = tokio::time::timeout(deadline, &mut self.task).await {
+            return joined.map_err(PipelineError::DriverJoin)?;
+        }
+        warn!(?deadline, "pipeline deadline reached; shutting down");
+        self.cancel();
+        match tokio::time::timeout(grace, &mut self.task).await {
+            Ok(joined) => {
+                let mut stats = joined.map_err(PipelineError::DriverJoin)??;
+                stats.timed_out = true;
+                Ok(stats)
+            }
+            Err(_) => {
+                warn!(
+                    ?grace,
+                    "pipeline shutdown overran its grace period; aborting"
+                );
+                self.task.abort();
+                for child in self.child_tasks.lock().drain(..) {
+                    child.abort();
+                }
+                Err(PipelineError::ShutdownTimedOut { grace })
+            }
+        }
+    }
+}
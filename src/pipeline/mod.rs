@@ -1,91 +1,300 @@
+mod channel;
 mod config;
+mod handle;
 mod producer;
 mod searcher;
+mod snapshot;
 mod stats;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
 mod types;
 mod writer;
 
-pub use crate::error::PipelineError;
-pub use config::PipelineConfig;
-pub use stats::PipelineStats;
+pub use crate::csr_graph::DedupPolicy;
+pub use crate::error::{ConfigError, PipelineError};
+pub use config::{
+    ChannelMode, GasFilter, JitterModel, JitterSeeding, PipelineConfig, PipelineConfigBuilder,
+    ProducerSource, SearchTrigger, UpdateReader, MIN_SEARCH_INTERVAL,
+};
+pub use handle::PipelineHandle;
+pub use producer::RandomUpdateSource;
+pub use searcher::search_once;
+pub use stats::{CounterDelta, CyclePresence, DurationDelta, FlagDelta, PipelineStats, StatsDelta};
+pub use types::{
+    BoxedUpdateSource, CycleCallback, CycleSink, GraphUpdate, SearcherControl, SharedGraph,
+    TimedCycle, UpdateSource, WriterOutcome,
+};
 
 use crate::{
-    csr_graph::{CSRGraph, InputEdge},
-    dataset::Dataset,
+    csr_graph::dataset_input_edges,
+    dataset::{Dataset, Token, TokenIndex},
+};
+use parking_lot::{Mutex, RwLock};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    task::AbortHandle,
+    time::Instant,
 };
-use parking_lot::RwLock;
-use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot};
-use tracing::{info, instrument};
+use tracing::{error, info, instrument, warn};
 
-use types::{GraphUpdate, SharedGraph};
+use handle::RunTasks;
+use types::{
+    ChildTasks, DirtyEdges, ProducerOutcome, SearchTriggerSender, SearcherControls, TouchedEdges,
+};
 
-#[instrument(name = "pipeline_run", level = "debug", skip_all)]
 pub async fn run(dataset: Dataset, config: PipelineConfig) -> Result<PipelineStats, PipelineError> {
-    if dataset.edges.is_empty() {
-        return Err(PipelineError::EmptyDataset);
-    }
-
-    let mut graph_edges: Vec<InputEdge> = Vec::with_capacity(dataset.edges.len());
-    let mut baseline_rates = Vec::with_capacity(dataset.edges.len());
-    let mut highest_node_index = 0usize;
-
-    for edge in &dataset.edges {
-        let from = usize::try_from(edge.from).map_err(|source| PipelineError::FromIndex {
-            edge_id: edge.id,
-            source,
-        })?;
-        let to = usize::try_from(edge.to).map_err(|source| PipelineError::ToIndex {
-            edge_id: edge.id,
-            source,
-        })?;
-        if !edge.rate.is_finite() || edge.rate <= 0.0 {
-            return Err(PipelineError::InvalidRate {
-                edge_id: edge.id,
-                rate: edge.rate,
-            });
+    spawn(dataset, config)?.join().await
+}
+
+/// [`run`] with a hard deadline. On expiry the pipeline shuts down gracefully (producer stops,
+/// writer drains, searcher runs its final pass) and the partial stats are returned with
+/// [`PipelineStats::timed_out`] set. A shutdown that overruns
+/// [`PipelineConfig::shutdown_grace`] is aborted with [`PipelineError::ShutdownTimedOut`].
+pub async fn run_with_timeout(
+    dataset: Dataset,
+    config: PipelineConfig,
+    deadline: Duration,
+) -> Result<PipelineStats, PipelineError> {
+    let grace = config.shutdown_grace;
+    spawn(dataset, config)?.join_within(deadline, grace).await
+}
+
+/// Start the pipeline in the background and return a handle for controlling it mid-run.
+/// Must be called from within a Tokio runtime.
+pub fn spawn(
+    dataset: Dataset,
+    mut config: PipelineConfig,
+) -> Result<PipelineHandle, PipelineError> {
+    let started_at = Instant::now();
+    config.clamp_search_interval();
+    let mut graph_edges = dataset_input_edges(&dataset, config.expand_bidirectional)?;
+
+    let duplicate_edges_removed = match config.dedup_edges {
+        Some(policy) => {
+            let removed = graph_edges.dedup(policy);
+            info!(removed, ?policy, "removed duplicate dataset edges");
+            removed
         }
-        graph_edges.push((from, to, edge.rate));
-        baseline_rates.push(edge.rate);
-        highest_node_index = highest_node_index.max(from.max(to));
-    }
+        None => 0,
+    };
+    let producer_feed = match &config.producer_source {
+        ProducerSource::Synthetic => ProducerFeed::Synthetic,
+        ProducerSource::JsonLines(reader) => ProducerFeed::Reader(
+            reader
+                .lock()
+                .take()
+                .ok_or(PipelineError::ProducerSourceConsumed)?,
+        ),
+        ProducerSource::Custom(source) => ProducerFeed::Custom(
+            source
+                .lock()
+                .take()
+                .ok_or(PipelineError::ProducerSourceConsumed)?,
+        ),
+    };
+    let baseline_rates: Vec<f64> = graph_edges.edges.iter().map(|&(_, _, rate)| rate).collect();
 
     info!(
-        edge_count = graph_edges.len(),
-        node_count = highest_node_index + 1,
+        edge_count = graph_edges.edges.len(),
+        node_count = graph_edges.node_count,
         "initialised pipeline state"
     );
 
-    let node_count = highest_node_index + 1;
-    let shared_graph: SharedGraph =
-        Arc::new(RwLock::new(CSRGraph::from_edges(node_count, graph_edges)));
+    let shared_graph: SharedGraph = Arc::new(RwLock::new(graph_edges.into_graph()));
+    let baseline: Arc<[f64]> = baseline_rates.into();
+    let tokens = dataset.tokens;
+    let (stop_tx, _) = watch::channel(false);
+    let (trigger_tx, trigger_rx) = mpsc::unbounded_channel();
+    let (paused_tx, paused_rx) = watch::channel(false);
+    let edge_index_bound = shared_graph.read().edge_index_bound();
+    let dirty_edges = (config.recent_first_search || config.incremental_search)
+        .then(|| DirtyEdges::new(Mutex::new(TouchedEdges::with_bound(edge_index_bound))));
+    let child_tasks = ChildTasks::default();
+
+    let inputs = RunInputs {
+        baseline_rates: baseline.to_vec(),
+        producer_feed,
+        tokens,
+        started_at,
+        duplicate_edges_removed,
+        search_triggers: (trigger_tx.clone(), trigger_rx),
+        searcher_paused: paused_rx,
+        dirty_edges: dirty_edges.clone(),
+        child_tasks: Arc::clone(&child_tasks),
+    };
+    let task = tokio::spawn(drive(
+        Arc::clone(&shared_graph),
+        inputs,
+        config,
+        stop_tx.clone(),
+    ));
+    Ok(PipelineHandle::new(
+        shared_graph,
+        baseline,
+        stop_tx,
+        trigger_tx,
+        paused_tx,
+        dirty_edges,
+        RunTasks {
+            driver: task,
+            children: child_tasks,
+        },
+    ))
+}
+
+/// Everything [`spawn`] prepares for [`drive`] besides the shared graph and config.
+struct RunInputs {
+    baseline_rates: Vec<f64>,
+    producer_feed: ProducerFeed,
+    /// The dataset's tokens, used to name the last cycle's vertices.
+    tokens: Vec<Token>,
+    started_at: Instant,
+    duplicate_edges_removed: usize,
+    search_triggers: (SearchTriggerSender, mpsc::UnboundedReceiver<()>),
+    searcher_paused: watch::Receiver<bool>,
+    /// Edges changed since the searcher's last pass, when a search mode needs them.
+    dirty_edges: Option<DirtyEdges>,
+    /// Where [`drive`] registers every task it spawns.
+    child_tasks: ChildTasks,
+}
 
-    let (update_sender, update_receiver) = mpsc::channel::<GraphUpdate>(config.channel_capacity);
+/// The producer input taken out of [`ProducerSource`] at startup.
+enum ProducerFeed {
+    Synthetic,
+    Reader(UpdateReader),
+    Custom(BoxedUpdateSource),
+}
+
+#[instrument(name = "pipeline_run", level = "debug", skip_all)]
+async fn drive(
+    shared_graph: SharedGraph,
+    inputs: RunInputs,
+    config: PipelineConfig,
+    stop_tx: watch::Sender<bool>,
+) -> Result<PipelineStats, PipelineError> {
+    let RunInputs {
+        baseline_rates,
+        producer_feed,
+        tokens,
+        started_at,
+        duplicate_edges_removed,
+        search_triggers: (trigger_tx, trigger_rx),
+        searcher_paused,
+        dirty_edges,
+        child_tasks,
+    } = inputs;
+    let (update_sender, update_receiver, pump) =
+        channel::update_pipe(config.channel_mode, config.channel_capacity);
+    let register = |task: AbortHandle| child_tasks.lock().push(task);
+    if let Some(pump) = &pump {
+        register(pump.abort_handle());
+    }
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let stop_rx = stop_tx.subscribe();
 
     info!("spawning writer task");
-    let writer_handle = writer::start(Arc::clone(&shared_graph), update_receiver, config.clone());
+    let writer_handle = writer::start(
+        Arc::clone(&shared_graph),
+        update_receiver,
+        dirty_edges.clone(),
+        (config.search_trigger == SearchTrigger::OnUpdate).then_some(trigger_tx),
+        config.clone(),
+    );
+    register(writer_handle.abort_handle());
 
     info!("spawning searcher task");
-    let search_handle = searcher::start(Arc::clone(&shared_graph), shutdown_rx, config.clone());
+    let search_handle = searcher::start(
+        Arc::clone(&shared_graph),
+        shutdown_rx,
+        dirty_edges,
+        SearcherControls {
+            triggers: trigger_rx,
+            paused: searcher_paused,
+        },
+        config.clone(),
+        started_at,
+        stop_tx.clone(),
+    );
+    register(search_handle.abort_handle());
+
+    let snapshot_handle = match (config.snapshot_interval, config.snapshot_dir.clone()) {
+        (Some(interval), Some(dir)) if !interval.is_zero() => {
+            info!("spawning snapshot task");
+            let (snapshot_tx, snapshot_rx) = oneshot::channel::<()>();
+            let handle = snapshot::start(
+                Arc::clone(&shared_graph),
+                tokens.clone(),
+                snapshot_rx,
+                interval,
+                dir,
+            );
+            register(handle.abort_handle());
+            Some((snapshot_tx, handle))
+        }
+        _ => None,
+    };
 
     info!("spawning producer task");
-    let producer_handle = producer::start(update_sender, baseline_rates, config.clone());
+    let producer_handle = match producer_feed {
+        ProducerFeed::Synthetic => {
+            producer::start(update_sender, baseline_rates, config.clone(), stop_rx)
+        }
+        ProducerFeed::Reader(reader) => producer::start_from_reader(update_sender, reader, stop_rx),
+        ProducerFeed::Custom(source) => producer::start_from_source(update_sender, source, stop_rx),
+    };
+    register(producer_handle.abort_handle());
 
     info!("awaiting producer task completion");
-    producer_handle.await.map_err(PipelineError::ProducerJoin)?;
-    info!("producer task completed");
+    // A failed producer only cuts the feed short: its sender is gone, so the writer drains what
+    // was sent and the rest of the run winds down as usual.
+    let (producer_outcome, producer_failed) = match producer_handle.await {
+        Ok(outcome) => (outcome, false),
+        Err(err) => {
+            error!(error = %err, "producer task failed; draining pipeline");
+            stop_tx.send_replace(true);
+            (ProducerOutcome::default(), true)
+        }
+    };
+    info!(
+        producer_failed,
+        sanitized_rates = producer_outcome.sanitized_rates,
+        malformed_lines = producer_outcome.malformed_lines,
+        backpressure_events = producer_outcome.backpressure_events,
+        "producer task completed"
+    );
+    let dropped_updates = match pump {
+        Some(pump) => pump.finish().await,
+        None => 0,
+    };
 
-    let writer_outcome = writer_handle.await.map_err(PipelineError::WriterJoin)?;
+    let writer_outcome = match writer_handle.await.map_err(PipelineError::WriterJoin)? {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            let _ = shutdown_tx.send(());
+            return Err(err);
+        }
+    };
     info!(
         processed_updates = writer_outcome.processed_updates,
         unique_updates_applied = writer_outcome.unique_updates_applied,
         invalid_index_updates = writer_outcome.invalid_index_updates,
         invalid_rate_updates = writer_outcome.invalid_rate_updates,
+        invalid_fee_updates = writer_outcome.invalid_fee_updates,
+        stale_updates = writer_outcome.stale_updates,
+        batches_formed = writer_outcome.batches_formed,
+        max_batch_size = writer_outcome.max_batch_size,
         "writer task completed"
     );
 
     let _ = shutdown_tx.send(());
+    if let Some((snapshot_tx, snapshot_handle)) = snapshot_handle {
+        let _ = snapshot_tx.send(());
+        match snapshot_handle.await {
+            Ok(written) => info!(snapshots_written = written, "snapshot task completed"),
+            Err(err) => warn!(error = %err, "snapshot task failed"),
+        }
+    }
     let search_outcome = search_handle.await.map_err(PipelineError::SearcherJoin)?;
     if let Some(ref cycle) = search_outcome.last_cycle {
         info!(
@@ -108,16 +317,40 @@ pub async fn run(dataset: Dataset, config: PipelineConfig) -> Result<PipelineSta
         updates_processed: writer_outcome.processed_updates,
         unique_updates_applied: writer_outcome.unique_updates_applied,
         searches_run: search_outcome.searches_run,
+        last_cycle_symbols: search_outcome
+            .last_cycle
+            .as_ref()
+            .and_then(|cycle| TokenIndex::new(&tokens).symbol_path(&cycle.vertices)),
         last_cycle: search_outcome.last_cycle,
+        recent_cycles: search_outcome.recent_cycles.into(),
+        hop_length_histogram: search_outcome.hop_length_histogram,
+        total_search_time: search_outcome.total_search_time,
+        max_search_time: search_outcome.max_search_time,
         invalid_index_updates: writer_outcome.invalid_index_updates,
         invalid_rate_updates: writer_outcome.invalid_rate_updates,
+        invalid_fee_updates: writer_outcome.invalid_fee_updates,
+        stale_updates: writer_outcome.stale_updates,
+        producer_sanitized: producer_outcome.sanitized_rates,
+        malformed_update_lines: producer_outcome.malformed_lines,
+        batches_formed: writer_outcome.batches_formed,
+        max_batch_size: writer_outcome.max_batch_size,
+        total_batched: writer_outcome.total_batched,
+        duplicate_edges_removed,
+        backpressure_events: producer_outcome.backpressure_events,
+        dropped_updates,
+        producer_failed,
+        timed_out: false,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dataset::{Dataset, Edge, Token};
+    use crate::{
+        csr_graph::CSRGraph,
+        cycle_finder::Cycle,
+        dataset::{Dataset, Edge, Token, BIDIRECTIONAL_EDGE_KIND, REVERSED_EDGE_KIND},
+    };
     use std::time::Duration;
 
     fn triangular_arbitrage_dataset() -> Dataset {
@@ -228,6 +461,88 @@ mod tests {
         }
     }
 
+    /// A running pipeline that searches only when asked and takes rate updates from a JSON-lines
+    /// feed the test writes to.
+    struct OnDemandRun {
+        handle: PipelineHandle,
+        feed: tokio::io::DuplexStream,
+        cycles: tokio::sync::mpsc::UnboundedReceiver<TimedCycle>,
+    }
+
+    impl OnDemandRun {
+        /// Spawn over tokens `0..node_count` and `(from, to, rate)` edges numbered in order.
+        fn spawn(node_count: u64, edges: &[(u64, u64, f64)]) -> Self {
+            let dataset = Dataset {
+                tokens: (0..node_count)
+                    .map(|id| Token {
+                        id,
+                        symbol: format!("T{id}"),
+                    })
+                    .collect(),
+                edges: (0..)
+                    .zip(edges)
+                    .map(|(id, &(from, to, rate))| Edge {
+                        id,
+                        from,
+                        to,
+                        rate,
+                        pool_id: id,
+                        kind: 0,
+                    })
+                    .collect(),
+            };
+            let (feed, reader) = tokio::io::duplex(256);
+            let (cycle_tx, cycles) = tokio::sync::mpsc::unbounded_channel();
+            let config = PipelineConfig {
+                search_trigger: SearchTrigger::OnDemand,
+                producer_source: ProducerSource::json_lines(tokio::io::BufReader::new(reader)),
+                cycle_sink: Some(CycleSink::PerCycle(cycle_tx)),
+                ..quick_config(0)
+            };
+            let handle = spawn(dataset, config).expect("pipeline spawns");
+            Self {
+                handle,
+                feed,
+                cycles,
+            }
+        }
+
+        /// Feed `(edge_index, new_rate)` updates and wait until the writer has applied them.
+        async fn set_rates(&mut self, updates: &[(usize, f64)]) {
+            use tokio::io::AsyncWriteExt;
+
+            let lines: String = updates
+                .iter()
+                .map(|(edge_index, new_rate)| {
+                    format!("{{\"edge_index\":{edge_index},\"new_rate\":{new_rate}}}\n")
+                })
+                .collect();
+            self.feed.write_all(lines.as_bytes()).await.unwrap();
+            while updates
+                .iter()
+                .any(|&(edge_index, rate)| self.handle.snapshot().edge_rate(edge_index) != rate)
+            {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        }
+
+        /// Trigger one search and wait for the cycle it reports.
+        async fn search(&mut self) -> Cycle {
+            self.handle.trigger_search();
+            self.cycles
+                .recv()
+                .await
+                .expect("search reports a cycle")
+                .cycle
+        }
+
+        /// Close the feed so the producer ends, then collect the run's stats.
+        async fn finish(self) -> PipelineStats {
+            drop(self.feed);
+            self.handle.join().await.expect("pipeline runs")
+        }
+    }
+
     #[tokio::test]
     async fn pipeline_consumes_expected_number_of_updates() {
         let dataset = triangular_arbitrage_dataset();
@@ -275,6 +590,10 @@ mod tests {
         .expect("pipeline has a profitable cycle to report");
 
         assert!(stats.last_cycle.is_some(), "expected a profitable cycle");
+        assert_eq!(
+            stats.last_cycle_symbols,
+            Some(vec!["A".into(), "B".into(), "C".into(), "A".into()])
+        );
         assert!(
             stats.unique_updates_applied <= stats.updates_processed,
             "unique applied updates should never exceed processed"
@@ -289,6 +608,30 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn last_cycle_symbols_names_vertices_and_is_none_for_unmapped_ones() {
+        let symbols_with = |tokens: Vec<Token>| async move {
+            let dataset = Dataset {
+                tokens,
+                ..triangular_arbitrage_dataset()
+            };
+            let stats = run(dataset, quick_config(16))
+                .await
+                .expect("pipeline runs without error");
+            assert!(stats.last_cycle.is_some(), "expected a profitable cycle");
+            stats.last_cycle_symbols
+        };
+
+        let all_tokens = triangular_arbitrage_dataset().tokens;
+        assert_eq!(
+            symbols_with(all_tokens.clone()).await,
+            Some(vec!["A".into(), "B".into(), "C".into(), "A".into()])
+        );
+
+        let without_c = all_tokens.into_iter().filter(|t| t.id != 2).collect();
+        assert_eq!(symbols_with(without_c).await, None);
+    }
+
     #[tokio::test]
     async fn pipeline_runs_search_even_without_cycle() {
         let dataset = acyclic_dataset();
@@ -364,6 +707,756 @@ mod tests {
         assert!(matches!(err, PipelineError::InvalidRate { .. }));
     }
 
+    #[tokio::test]
+    async fn cycle_sink_receives_cycles_in_discovery_order() {
+        let (sink, mut found) = mpsc::unbounded_channel();
+        let stats = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                cycle_sink: Some(CycleSink::PerCycle(sink)),
+                ..quick_config(32)
+            },
+        )
+        .await
+        .expect("pipeline runs with a cycle sink");
+
+        let mut timed = Vec::new();
+        while let Ok(cycle) = found.try_recv() {
+            timed.push(cycle);
+        }
+
+        assert!(stats.last_cycle.is_some());
+        assert!(timed.len() >= 2, "expected several searches to report");
+        assert!(timed
+            .windows(2)
+            .all(|pair| pair[0].found_at <= pair[1].found_at));
+        assert!(timed.last().unwrap().found_at > timed[0].found_at);
+    }
+
+    #[tokio::test]
+    async fn pipeline_stops_on_first_cycle() {
+        let config = PipelineConfig {
+            max_updates: 10_000,
+            stop_on_first_cycle: true,
+            ..quick_config(0)
+        };
+        let stats = run(triangular_arbitrage_dataset(), config)
+            .await
+            .expect("pipeline stops early without error");
+
+        assert!(stats.last_cycle.is_some(), "expected a profitable cycle");
+        assert!(
+            stats.updates_processed < 10_000,
+            "producer should stop before exhausting max_updates; processed {}",
+            stats.updates_processed
+        );
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test]
+    async fn pipeline_survives_slow_searcher() {
+        let stats = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                artificial_search_delay: Duration::from_millis(20),
+                ..quick_config(32)
+            },
+        )
+        .await
+        .expect("slow searcher should not break the pipeline");
+
+        assert_eq!(stats.updates_processed, 32);
+        assert!(stats.searches_run >= 1);
+        assert!(stats.last_cycle.is_some(), "cycle should still be found");
+    }
+
+    #[tokio::test]
+    async fn pipeline_writes_periodic_snapshots() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let stats = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                snapshot_interval: Some(Duration::from_millis(2)),
+                snapshot_dir: Some(dir.path().join("snapshots")),
+                ..quick_config(16)
+            },
+        )
+        .await
+        .expect("pipeline runs with snapshots enabled");
+        assert_eq!(stats.updates_processed, 16);
+
+        let snapshots: Vec<_> = std::fs::read_dir(dir.path().join("snapshots"))
+            .expect("snapshot dir created")
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert!(!snapshots.is_empty(), "expected at least one snapshot");
+
+        let snapshot = Dataset::load_from_path(&snapshots[0]).expect("snapshot is a dataset");
+        assert_eq!(snapshot.tokens.len(), 3);
+        assert_eq!(snapshot.edges.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn batched_cycle_sink_groups_cycles() {
+        let (sender, mut batches) = mpsc::unbounded_channel();
+        run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                cycle_sink: Some(CycleSink::Batched {
+                    sender,
+                    max_cycles: 4,
+                    max_delay: Duration::from_secs(60),
+                }),
+                ..quick_config(32)
+            },
+        )
+        .await
+        .expect("pipeline runs with a batched cycle sink");
+
+        let mut received = Vec::new();
+        while let Ok(batch) = batches.try_recv() {
+            received.push(batch);
+        }
+        let total_cycles: usize = received.iter().map(Vec::len).sum();
+
+        assert!(!received.is_empty(), "shutdown should flush pending cycles");
+        assert!(received.iter().all(|batch| batch.len() <= 4));
+        assert!(
+            received.len() < total_cycles,
+            "expected fewer batches ({}) than cycles ({total_cycles})",
+            received.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_to_baseline_restores_dataset_rates() {
+        let dataset = triangular_arbitrage_dataset();
+        let baseline: Vec<f64> = dataset.edges.iter().map(|edge| edge.rate).collect();
+        let handle = spawn(dataset, quick_config(16)).expect("pipeline spawns");
+
+        {
+            let mut graph = handle.shared_graph.write();
+            for edge_index in 0..baseline.len() {
+                graph
+                    .update_rate(edge_index, 2.5)
+                    .expect("valid manual update");
+            }
+        }
+        handle.reset_to_baseline();
+
+        let snapshot = handle.snapshot();
+        let rates: Vec<f64> = (0..baseline.len())
+            .map(|edge_index| snapshot.edge_rate(edge_index))
+            .collect();
+        assert_eq!(rates, baseline);
+
+        handle.join().await.expect("pipeline completes");
+    }
+
+    #[tokio::test]
+    async fn handle_exposes_baseline_rates_in_edge_order() {
+        let dataset = triangular_arbitrage_dataset();
+        let expected: Vec<f64> = dataset.edges.iter().map(|edge| edge.rate).collect();
+        let config = PipelineConfig {
+            rate_jitter: 0.05,
+            ..quick_config(16)
+        };
+        let handle = spawn(dataset, config).expect("pipeline spawns");
+
+        assert_eq!(handle.baseline_rates(), expected.as_slice());
+        let baseline = handle.baseline_rates().to_vec();
+        let graph = handle.snapshot();
+        handle.join().await.expect("pipeline completes");
+        assert_eq!(baseline.len(), graph.edge_index_bound());
+    }
+
+    #[tokio::test]
+    async fn cancelled_pipeline_returns_partial_stats() {
+        let config = PipelineConfig {
+            max_updates: 1_000_000,
+            ..quick_config(0)
+        };
+        let handle = spawn(triangular_arbitrage_dataset(), config).expect("pipeline spawns");
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle.cancel();
+        let stats = tokio::time::timeout(Duration::from_secs(1), handle.join())
+            .await
+            .expect("cancelled pipeline returns promptly")
+            .expect("pipeline completes");
+
+        assert!(stats.updates_processed < 1_000_000);
+        assert!(stats.searches_run >= 1, "shutdown search pass still runs");
+    }
+
+    #[tokio::test]
+    async fn run_with_timeout_stops_long_producer_at_deadline() {
+        let config = PipelineConfig {
+            max_updates: 1_000_000,
+            ..quick_config(0)
+        };
+        let started = std::time::Instant::now();
+        let stats = run_with_timeout(
+            triangular_arbitrage_dataset(),
+            config,
+            Duration::from_millis(30),
+        )
+        .await
+        .expect("pipeline completes");
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(stats.timed_out);
+        assert!(stats.updates_processed < 1_000_000);
+        assert!(stats.searches_run >= 1, "shutdown search pass still runs");
+
+        let stats = run_with_timeout(
+            triangular_arbitrage_dataset(),
+            quick_config(4),
+            Duration::from_secs(10),
+        )
+        .await
+        .expect("pipeline completes");
+        assert!(!stats.timed_out);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn run_with_timeout_aborts_a_shutdown_that_overruns_its_grace() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // Blocks its worker thread on every call, so the producer cannot notice the stop signal
+        // in time; `alive` is cleared once the producer task lets go of it.
+        struct StuckSource {
+            alive: Arc<AtomicBool>,
+        }
+
+        impl UpdateSource for StuckSource {
+            async fn next(&mut self) -> Option<GraphUpdate> {
+                std::thread::sleep(Duration::from_millis(300));
+                Some(GraphUpdate::Rate {
+                    edge_index: 0,
+                    new_rate: 1.1,
+                    seq: 0,
+                })
+            }
+        }
+
+        impl Drop for StuckSource {
+            fn drop(&mut self) {
+                self.alive.store(false, Ordering::SeqCst);
+            }
+        }
+
+        let alive = Arc::new(AtomicBool::new(true));
+        let config = PipelineConfig {
+            producer_source: ProducerSource::custom(StuckSource {
+                alive: Arc::clone(&alive),
+            }),
+            shutdown_grace: Duration::from_millis(20),
+            ..quick_config(0)
+        };
+        let started = std::time::Instant::now();
+        let result = run_with_timeout(
+            triangular_arbitrage_dataset(),
+            config,
+            Duration::from_millis(20),
+        )
+        .await;
+
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert!(matches!(
+            result,
+            Err(PipelineError::ShutdownTimedOut { grace }) if grace == Duration::from_millis(20)
+        ));
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while alive.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("producer task stops after the abort");
+    }
+
+    #[tokio::test]
+    async fn paused_searcher_skips_passes_but_still_shuts_down() {
+        async fn searches_over_window(pause: bool) -> usize {
+            let config = PipelineConfig {
+                max_updates: 1_000_000,
+                ..quick_config(0)
+            };
+            let handle = spawn(triangular_arbitrage_dataset(), config).expect("pipeline spawns");
+            if pause {
+                handle.control_searcher(SearcherControl::Pause);
+            }
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            handle.cancel();
+            handle
+                .join()
+                .await
+                .expect("pipeline completes")
+                .searches_run
+        }
+
+        let unpaused = searches_over_window(false).await;
+        let paused = searches_over_window(true).await;
+        assert_eq!(paused, 1, "only the shutdown pass runs while paused");
+        assert!(paused < unpaused, "paused {paused} vs unpaused {unpaused}");
+    }
+
+    #[tokio::test]
+    async fn recent_first_search_still_finds_cycles() {
+        let stats = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                recent_first_search: true,
+                ..quick_config(32)
+            },
+        )
+        .await
+        .expect("pipeline runs with recent-first search");
+
+        assert!(stats.last_cycle.is_some());
+    }
+
+    #[tokio::test]
+    async fn bidirectional_edge_expands_into_both_directions() {
+        let mut dataset = acyclic_dataset();
+        dataset.edges.truncate(1);
+        dataset.edges[0].kind = BIDIRECTIONAL_EDGE_KIND;
+        let (from, to, rate) = (
+            dataset.edges[0].from as usize,
+            dataset.edges[0].to as usize,
+            dataset.edges[0].rate,
+        );
+
+        let config = PipelineConfig {
+            expand_bidirectional: true,
+            ..quick_config(8)
+        };
+        let handle = spawn(dataset.clone(), config).expect("pipeline spawns");
+        let graph = handle.snapshot();
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.edge(0), (from, to, rate));
+        assert_eq!(graph.edge(1), (to, from, 1.0 / rate));
+        assert_eq!(graph.edge_kind(1), REVERSED_EDGE_KIND);
+        assert_eq!(handle.baseline_rates(), &[rate, 1.0 / rate]);
+        handle.join().await.expect("pipeline completes");
+
+        let handle = spawn(dataset, quick_config(0)).expect("pipeline spawns");
+        assert_eq!(handle.snapshot().edge_count(), 1);
+        handle.join().await.expect("pipeline completes");
+    }
+
+    #[tokio::test]
+    async fn on_update_trigger_searches_once_per_applied_batch() {
+        let config = PipelineConfig {
+            search_trigger: SearchTrigger::OnUpdate,
+            ..quick_config(40)
+        };
+        let stats = run(triangular_arbitrage_dataset(), config)
+            .await
+            .expect("pipeline runs");
+
+        assert!(stats.batches_formed > 1);
+        // One pass per batch plus the final shutdown pass.
+        assert_eq!(stats.searches_run, stats.batches_formed + 1);
+        assert!(stats.last_cycle.is_some());
+    }
+
+    #[tokio::test]
+    async fn on_demand_trigger_searches_only_when_asked() {
+        let config = PipelineConfig {
+            search_trigger: SearchTrigger::OnDemand,
+            ..quick_config(0)
+        };
+        let handle = spawn(triangular_arbitrage_dataset(), config).expect("pipeline spawns");
+        handle.trigger_search();
+        handle.trigger_search();
+        let stats = handle.join().await.expect("pipeline runs");
+
+        assert_eq!(stats.searches_run, 3);
+    }
+
+    #[tokio::test]
+    async fn gas_filter_suppresses_cycles_that_do_not_cover_gas() {
+        // The triangle returns ~13% per round trip: 1.1319 on 100 notional is ~13.2 in profit.
+        let with_gas = |cost_per_hop| PipelineConfig {
+            gas_filter: Some(GasFilter {
+                cost_per_hop,
+                reference_size: 100.0,
+            }),
+            ..quick_config(8)
+        };
+
+        let stats = run(triangular_arbitrage_dataset(), with_gas(4.0))
+            .await
+            .expect("pipeline runs");
+        assert!(stats.last_cycle.is_some());
+
+        let stats = run(triangular_arbitrage_dataset(), with_gas(5.0))
+            .await
+            .expect("pipeline runs");
+        assert!(stats.last_cycle.is_none());
+    }
+
+    #[tokio::test]
+    async fn dedup_edges_collapses_duplicate_pairs_at_startup() {
+        let mut dataset = triangular_arbitrage_dataset();
+        let duplicate = |id, from, to, rate| Edge {
+            id,
+            from,
+            to,
+            rate,
+            pool_id: 1,
+            kind: 0,
+        };
+        dataset.edges.push(duplicate(3, 0, 1, 1.20));
+        dataset.edges.push(duplicate(4, 1, 2, 0.90));
+        let edges_before = dataset.edges.len();
+
+        let config = PipelineConfig {
+            dedup_edges: Some(DedupPolicy::KeepBest),
+            ..quick_config(0)
+        };
+        let handle = spawn(dataset.clone(), config).expect("pipeline spawns");
+        let graph = handle.snapshot();
+        let stats = handle.join().await.expect("pipeline runs");
+
+        assert_eq!(stats.duplicate_edges_removed, 2);
+        assert_eq!(graph.edge_count(), edges_before - 2);
+        assert_eq!(graph.edge(0), (0, 1, 1.20));
+        assert_eq!(graph.edge(1), (1, 2, 1.05));
+
+        let stats = run(dataset, quick_config(0)).await.expect("pipeline runs");
+        assert_eq!(stats.duplicate_edges_removed, 0);
+    }
+
+    #[tokio::test]
+    async fn json_lines_source_drives_the_writer_once() {
+        let feed = "{\"edge_index\":0,\"new_rate\":1.2}\n{\"edge_index\":1,\"new_rate\":0.9}\n";
+        let config = PipelineConfig {
+            producer_source: ProducerSource::json_lines(std::io::Cursor::new(feed.as_bytes())),
+            ..quick_config(1_000)
+        };
+
+        let stats = run(triangular_arbitrage_dataset(), config.clone())
+            .await
+            .expect("pipeline runs");
+        assert_eq!(stats.updates_processed, 2);
+        assert_eq!(stats.malformed_update_lines, 0);
+
+        assert!(matches!(
+            run(triangular_arbitrage_dataset(), config).await,
+            Err(PipelineError::ProducerSourceConsumed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn recent_cycles_keep_each_distinct_opportunity() {
+        // Round trip 0 <-> 1 starts profitable; 1 <-> 2 starts unprofitable.
+        let mut run = OnDemandRun::spawn(3, &[(0, 1, 1.0), (1, 0, 1.1), (1, 2, 1.0), (2, 1, 0.9)]);
+        let first = run.search().await;
+        // Flip which round trip is profitable, then search again.
+        run.set_rates(&[(1, 0.9), (3, 1.1)]).await;
+        let second = run.search().await;
+        let stats = run.finish().await;
+
+        let mut routes = vec![first.edge_indexes, second.edge_indexes];
+        for route in &mut routes {
+            route.sort_unstable();
+        }
+        assert_eq!(routes, vec![vec![0, 1], vec![2, 3]]);
+        // The shutdown pass finds the second cycle again, which is not recorded twice.
+        let recent: Vec<Vec<usize>> = stats
+            .recent_cycles
+            .iter()
+            .map(|cycle| {
+                let mut route = cycle.edge_indexes.clone();
+                route.sort_unstable();
+                route
+            })
+            .collect();
+        assert_eq!(recent, routes);
+    }
+
+    #[tokio::test]
+    async fn custom_update_source_feeds_every_update() {
+        struct VecSource(std::vec::IntoIter<GraphUpdate>);
+
+        impl UpdateSource for VecSource {
+            async fn next(&mut self) -> Option<GraphUpdate> {
+                self.0.next()
+            }
+        }
+
+        let updates = vec![
+            GraphUpdate::Rate {
+                edge_index: 0,
+                new_rate: 1.2,
+                seq: 0,
+            },
+            GraphUpdate::Fee {
+                edge_index: 1,
+                fee_bps: 30.0,
+            },
+            GraphUpdate::RateDelta {
+                edge_index: 2,
+                pct_change: 0.5,
+            },
+        ];
+        let config = PipelineConfig {
+            producer_source: ProducerSource::custom(VecSource(updates.into_iter())),
+            ..quick_config(1_000)
+        };
+        let handle =
+            spawn(triangular_arbitrage_dataset(), config.clone()).expect("pipeline spawns");
+        let graph = Arc::clone(&handle.shared_graph);
+        let stats = handle.join().await.expect("pipeline runs");
+
+        assert_eq!(stats.updates_processed, 3);
+        assert_eq!(stats.unique_updates_applied, 3);
+        let graph = graph.read();
+        assert_eq!(graph.edge_rate(0), 1.2);
+        assert_eq!(graph.edge_fee_bps(1), 30.0);
+        assert!((graph.edge_rate(2) - 0.98 * 1.5).abs() < 1e-12);
+        assert!(matches!(
+            spawn(triangular_arbitrage_dataset(), config),
+            Err(PipelineError::ProducerSourceConsumed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn tiny_channel_reports_backpressure() {
+        let config = PipelineConfig {
+            channel_capacity: 1,
+            max_coalesce: 64,
+            ..quick_config(256)
+        };
+        let stats = run(triangular_arbitrage_dataset(), config)
+            .await
+            .expect("pipeline runs");
+
+        assert_eq!(stats.updates_processed, 256);
+        assert!(stats.backpressure_events > 0);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_channel_never_blocks_producer() {
+        let config = PipelineConfig {
+            channel_capacity: 1,
+            channel_mode: ChannelMode::DropOldest,
+            max_coalesce: 64,
+            ..quick_config(256)
+        };
+        let stats = run(triangular_arbitrage_dataset(), config)
+            .await
+            .expect("pipeline runs");
+
+        assert_eq!(stats.backpressure_events, 0);
+        assert!(stats.dropped_updates > 0);
+        assert_eq!(stats.updates_processed + stats.dropped_updates, 256);
+    }
+
+    #[tokio::test]
+    async fn panicking_producer_still_returns_stats() {
+        struct PanicAfterOne(bool);
+
+        impl UpdateSource for PanicAfterOne {
+            async fn next(&mut self) -> Option<GraphUpdate> {
+                if std::mem::replace(&mut self.0, true) {
+                    panic!("update source blew up");
+                }
+                Some(GraphUpdate::Rate {
+                    edge_index: 0,
+                    new_rate: 1.2,
+                    seq: 0,
+                })
+            }
+        }
+
+        let config = PipelineConfig {
+            producer_source: ProducerSource::custom(PanicAfterOne(false)),
+            ..quick_config(1_000)
+        };
+        let stats = run(triangular_arbitrage_dataset(), config)
+            .await
+            .expect("pipeline drains instead of failing");
+
+        assert!(stats.producer_failed);
+        assert_eq!(stats.updates_processed, 1);
+        assert!(stats.searches_run >= 1);
+        assert!(stats.last_cycle.is_some());
+    }
+
+    #[test]
+    fn search_once_finds_cycle_in_caller_owned_graph() {
+        let shared: SharedGraph = Arc::new(parking_lot::RwLock::new(CSRGraph::from_edges(
+            3,
+            vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98), (1, 0, 0.80)],
+        )));
+
+        let config = PipelineConfig {
+            hop_cap: 3,
+            ..PipelineConfig::default()
+        };
+        let cycle = search_once(&shared, &config).expect("profitable triangle");
+        let mut route = cycle.edge_indexes.clone();
+        route.sort_unstable();
+        assert_eq!(route, vec![0, 1, 2]);
+        assert!((cycle.profit - 1.10 * 1.05 * 0.98).abs() < 1e-12);
+
+        // Thresholds from the config apply just as they do in the searcher task.
+        let demanding = PipelineConfig {
+            min_profit_ratio: Some(1.2),
+            ..config.clone()
+        };
+        assert_eq!(search_once(&shared, &demanding), None);
+        let gas_heavy = PipelineConfig {
+            gas_filter: Some(GasFilter {
+                cost_per_hop: 1.0,
+                reference_size: 1.0,
+            }),
+            ..config.clone()
+        };
+        assert_eq!(search_once(&shared, &gas_heavy), None);
+
+        // The lock is free again, and a graph with no live edges is not searched.
+        let mut graph = shared.write();
+        for edge_index in 0..4 {
+            graph.remove_edge(edge_index).unwrap();
+        }
+        drop(graph);
+        assert_eq!(search_once(&shared, &config), None);
+    }
+
+    #[tokio::test]
+    async fn search_latency_is_accumulated() {
+        let stats = run(triangular_arbitrage_dataset(), quick_config(32))
+            .await
+            .expect("pipeline runs without error");
+
+        assert!(stats.searches_run >= 1);
+        assert!(stats.total_search_time > Duration::ZERO);
+        assert!(stats.max_search_time > Duration::ZERO);
+        assert!(stats.max_search_time <= stats.total_search_time);
+    }
+
+    #[tokio::test]
+    async fn hop_length_histogram_counts_cycles_by_length() {
+        // Round trip 0 <-> 1 starts profitable; triangle 1 -> 2 -> 3 -> 1 starts unprofitable.
+        let mut run = OnDemandRun::spawn(
+            4,
+            &[
+                (0, 1, 1.0),
+                (1, 0, 1.1),
+                (1, 2, 1.0),
+                (2, 3, 1.0),
+                (3, 1, 0.9),
+            ],
+        );
+        assert_eq!(run.search().await.edge_indexes.len(), 2);
+        run.set_rates(&[(1, 0.9), (4, 1.1)]).await;
+        assert_eq!(run.search().await.edge_indexes.len(), 3);
+        let stats = run.finish().await;
+
+        // One 2-hop find, then the triangle on the triggered and the shutdown pass.
+        assert_eq!(stats.hop_length_histogram, vec![0, 0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_pass_counts_even_without_live_edges() {
+        let (feed, reader) = tokio::io::duplex(64);
+        let config = PipelineConfig {
+            search_trigger: SearchTrigger::OnDemand,
+            producer_source: ProducerSource::json_lines(tokio::io::BufReader::new(reader)),
+            ..quick_config(0)
+        };
+        let handle = spawn(triangular_arbitrage_dataset(), config).expect("pipeline spawns");
+        {
+            let mut graph = handle.shared_graph.write();
+            for edge_index in 0..graph.edge_index_bound() {
+                graph.remove_edge(edge_index).unwrap();
+            }
+        }
+        drop(feed);
+        let stats = handle.join().await.expect("pipeline runs");
+
+        assert_eq!(stats.searches_run, 1);
+        assert!(stats.last_cycle.is_none());
+    }
+
+    #[tokio::test]
+    async fn dry_run_counts_updates_without_touching_the_graph() {
+        let config = PipelineConfig {
+            dry_run: true,
+            rate_jitter: 0.5,
+            ..quick_config(64)
+        };
+        let handle = spawn(triangular_arbitrage_dataset(), config).expect("pipeline spawns");
+        let graph = Arc::clone(&handle.shared_graph);
+        let baseline = handle.baseline_rates().to_vec();
+        let stats = handle.join().await.expect("pipeline runs");
+
+        assert_eq!(stats.updates_processed, 64);
+        assert!(stats.unique_updates_applied > 0);
+        let graph = graph.read();
+        for (edge_index, &rate) in baseline.iter().enumerate() {
+            assert_eq!(graph.edge_rate(edge_index), rate);
+        }
+        assert_eq!(graph.current_tick(), 0);
+    }
+
+    #[tokio::test]
+    async fn incremental_search_finds_same_cycle_as_full_search() {
+        let last_route = |stats: PipelineStats| {
+            let mut route = stats.last_cycle.expect("profitable cycle").edge_indexes;
+            route.sort_unstable();
+            route
+        };
+        let config = PipelineConfig {
+            rng_seed: Some(5),
+            rate_jitter: 0.01,
+            ..quick_config(64)
+        };
+        let full = run(triangular_arbitrage_dataset(), config.clone())
+            .await
+            .expect("pipeline runs");
+        let incremental = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                incremental_search: true,
+                ..config
+            },
+        )
+        .await
+        .expect("pipeline runs");
+
+        assert!(incremental.searches_run > 1);
+        assert_eq!(last_route(incremental), last_route(full));
+    }
+
+    #[tokio::test]
+    async fn on_cycle_callback_fires_for_found_cycles() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let stats = run(
+            triangular_arbitrage_dataset(),
+            PipelineConfig {
+                on_cycle: Some(CycleCallback::new(move |cycle| {
+                    assert!(cycle.profit > 1.0);
+                    counter.fetch_add(1, Ordering::Relaxed);
+                })),
+                ..quick_config(16)
+            },
+        )
+        .await
+        .expect("pipeline runs with a cycle callback");
+
+        assert!(stats.last_cycle.is_some());
+        assert!(calls.load(Ordering::Relaxed) >= 1);
+    }
+
     #[tokio::test]
     async fn pipeline_handles_bursty_producer() {
         let dataset = triangular_arbitrage_dataset();
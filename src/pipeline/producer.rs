@@ -1,18 +1,28 @@
 use super::{
-    config::{PipelineConfig, RateBounds},
-    types::GraphUpdate,
+    channel::UpdateSender,
+    config::{JitterModel, JitterSeeding, PipelineConfig, RateBounds, UpdateReader},
+    types::{
+        stop_requested, BoxedUpdateSource, GraphUpdate, ProducerOutcome, StopSignal, UpdateSource,
+    },
 };
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal, StudentT};
+use serde::Deserialize;
 use std::time::Duration;
-use tokio::{sync::mpsc, task::JoinHandle};
-use tracing::{instrument, warn};
+use tokio::{
+    io::{AsyncBufReadExt, Lines},
+    sync::mpsc::error::TrySendError,
+    task::JoinHandle,
+};
+use tracing::{info, info_span, instrument, warn, Instrument};
 
 pub(super) fn start(
-    update_sender: mpsc::Sender<GraphUpdate>,
+    update_sender: UpdateSender,
     baseline_rates: Vec<f64>,
     config: PipelineConfig,
-) -> JoinHandle<()> {
-    tokio::spawn(producer_task(update_sender, baseline_rates, config))
+    stop: StopSignal,
+) -> JoinHandle<ProducerOutcome> {
+    tokio::spawn(producer_task(update_sender, baseline_rates, config, stop))
 }
 
 #[instrument(
@@ -26,55 +36,531 @@ pub(super) fn start(
     )
 )]
 async fn producer_task(
-    update_sender: mpsc::Sender<GraphUpdate>,
+    update_sender: UpdateSender,
     baseline_rates: Vec<f64>,
     config: PipelineConfig,
-) {
-    let edge_count = baseline_rates.len();
-    if edge_count == 0 {
-        return;
+    stop: StopSignal,
+) -> ProducerOutcome {
+    let mut source = RandomUpdateSource::new(baseline_rates, &config);
+    let backpressure_events = forward_updates(&update_sender, &mut source, stop).await;
+    ProducerOutcome {
+        sanitized_rates: source.sanitized_rates(),
+        backpressure_events,
+        ..ProducerOutcome::default()
     }
+}
 
-    let mut rng = StdRng::from_seed(rand::random::<[u8; 32]>());
-    let mut remaining = config.max_updates;
-    let max_burst = config.max_coalesce.max(1);
-    let bounds = RateBounds::from_config(&config);
+/// Drain a caller-supplied [`super::ProducerSource::Custom`] source into the writer.
+pub(super) fn start_from_source(
+    update_sender: UpdateSender,
+    mut source: BoxedUpdateSource,
+    stop: StopSignal,
+) -> JoinHandle<ProducerOutcome> {
+    tokio::spawn(
+        async move {
+            let backpressure_events = forward_updates(&update_sender, &mut source, stop).await;
+            ProducerOutcome {
+                backpressure_events,
+                ..ProducerOutcome::default()
+            }
+        }
+        .instrument(info_span!("pipeline_source_producer")),
+    )
+}
 
-    while remaining > 0 {
-        let burst = rng.random_range(1..=max_burst.min(remaining));
+/// Pull updates from `source` into the writer channel until it runs dry, the writer goes away
+/// or a stop is requested. Returns how many sends hit a full channel.
+async fn forward_updates<S: UpdateSource>(
+    update_sender: &UpdateSender,
+    source: &mut S,
+    mut stop: StopSignal,
+) -> usize {
+    let mut sent = 0usize;
+    let mut backpressure_events = 0usize;
+    loop {
+        let update = tokio::select! {
+            biased;
+            _ = stop_requested(&mut stop) => {
+                info!(sent, "producer stopped early");
+                return backpressure_events;
+            }
+            update = source.next() => update,
+        };
+        let Some(update) = update else {
+            return backpressure_events;
+        };
+        let update = match try_send_or_count(update_sender, update, &mut backpressure_events) {
+            Ok(()) => {
+                sent += 1;
+                continue;
+            }
+            Err(update) => update,
+        };
+        tokio::select! {
+            biased;
+            _ = stop_requested(&mut stop) => {
+                info!(sent, "producer stopped early");
+                return backpressure_events;
+            }
+            result = update_sender.send(update) => {
+                if result.is_err() {
+                    warn!("writer dropped before producer finished sending updates");
+                    return backpressure_events;
+                }
+            }
+        }
+        sent += 1;
+    }
+}
 
-        for _ in 0..burst {
-            let edge_index = rng.random_range(0..edge_count);
-            let base_rate = baseline_rates[edge_index];
-            let jitter = if config.rate_jitter > 0.0 {
-                rng.random_range(-config.rate_jitter..config.rate_jitter)
+/// Send without waiting when the channel has room. A full channel counts as one backpressure
+/// event and hands the update back for an awaited send; a closed one hands it back too, so the
+/// awaited send reports the closure.
+fn try_send_or_count(
+    update_sender: &UpdateSender,
+    update: GraphUpdate,
+    backpressure_events: &mut usize,
+) -> Result<(), GraphUpdate> {
+    match update_sender.try_send(update) {
+        Ok(()) => Ok(()),
+        Err(TrySendError::Full(update)) => {
+            *backpressure_events += 1;
+            Err(update)
+        }
+        Err(TrySendError::Closed(update)) => Err(update),
+    }
+}
+
+/// The built-in synthetic feed: `max_updates` rate updates in random bursts of up to
+/// `max_coalesce`, each shocking a random edge's baseline rate by a draw from `jitter_model`
+/// and clamped to the configured bounds. Bursts are separated by random pauses of up to twice
+/// `search_interval`.
+pub struct RandomUpdateSource {
+    baseline_rates: Vec<f64>,
+    rng: StdRng,
+    edge_streams: Option<EdgeJitterStreams>,
+    bounds: RateBounds,
+    shock: Shock,
+    max_burst: usize,
+    max_delay_ms: u64,
+    remaining: usize,
+    left_in_burst: usize,
+    started: bool,
+    sanitized_rates: usize,
+}
+
+impl RandomUpdateSource {
+    pub fn new(baseline_rates: Vec<f64>, config: &PipelineConfig) -> Self {
+        let rng = match config.rng_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_seed(rand::random::<[u8; 32]>()),
+        };
+        let edge_streams = match config.jitter_seeding {
+            JitterSeeding::Shared => None,
+            JitterSeeding::PerEdge => Some(EdgeJitterStreams::new(
+                config.rng_seed.unwrap_or_else(rand::random),
+                baseline_rates.len(),
+            )),
+        };
+        Self {
+            rng,
+            edge_streams,
+            bounds: RateBounds::from_config(config),
+            shock: Shock::from_config(config),
+            max_burst: config.max_coalesce.max(1),
+            max_delay_ms: (config.search_interval.as_millis().max(1) as u64).saturating_mul(2),
+            remaining: if baseline_rates.is_empty() {
+                0
             } else {
+                config.max_updates
+            },
+            left_in_burst: 0,
+            started: false,
+            sanitized_rates: 0,
+            baseline_rates,
+        }
+    }
+
+    /// Jittered rates skipped so far because they came out non-finite. Each still counts
+    /// against `max_updates`.
+    pub fn sanitized_rates(&self) -> usize {
+        self.sanitized_rates
+    }
+}
+
+impl UpdateSource for RandomUpdateSource {
+    async fn next(&mut self) -> Option<GraphUpdate> {
+        loop {
+            if self.left_in_burst == 0 {
+                if self.remaining == 0 {
+                    return None;
+                }
+                if std::mem::replace(&mut self.started, true) {
+                    let sleep_ms = self.rng.random_range(0..=self.max_delay_ms);
+                    if sleep_ms > 0 {
+                        tokio::time::sleep(Duration::from_millis(sleep_ms)).await;
+                    }
+                }
+                let burst = self
+                    .rng
+                    .random_range(1..=self.max_burst.min(self.remaining));
+                self.remaining -= burst;
+                self.left_in_burst = burst;
+            }
+            self.left_in_burst -= 1;
+
+            let edge_index = self.rng.random_range(0..self.baseline_rates.len());
+            let base_rate = self.baseline_rates[edge_index];
+            let jitter = if self.shock.is_none() {
                 0.0
+            } else {
+                let jitter_rng = match self.edge_streams.as_mut() {
+                    Some(streams) => streams.rng_for(edge_index),
+                    None => &mut self.rng,
+                };
+                self.shock.draw(jitter_rng)
+            };
+            let jittered = base_rate * (1.0 + jitter);
+            // `clamp` propagates NaN, so never let a non-finite rate reach the channel.
+            if !jittered.is_finite() {
+                self.sanitized_rates += 1;
+                warn!(edge_index, base_rate, "skipped non-finite jittered rate");
+                continue;
+            }
+
+            return Some(GraphUpdate::Rate {
+                edge_index,
+                new_rate: self.bounds.clamp(jittered),
+                seq: 0,
+            });
+        }
+    }
+}
+
+/// [`JitterModel`] resolved into a ready-to-sample distribution.
+enum Shock {
+    None,
+    Uniform(f64),
+    Normal(Normal<f64>),
+    StudentT(StudentT<f64>, f64),
+}
+
+impl Shock {
+    /// Zero-width models draw nothing, so they leave the RNG stream untouched. Configs that
+    /// skipped validation fall back to no jitter rather than panicking.
+    fn from_config(config: &PipelineConfig) -> Self {
+        let shock = match config.jitter_model {
+            JitterModel::Uniform => Some(if config.rate_jitter > 0.0 {
+                Self::Uniform(config.rate_jitter)
+            } else {
+                Self::None
+            }),
+            JitterModel::Normal { std: 0.0 } => Some(Self::None),
+            JitterModel::Normal { std } => Normal::new(0.0, std).ok().map(Self::Normal),
+            JitterModel::StudentT { scale: 0.0, .. } => Some(Self::None),
+            JitterModel::StudentT { df, scale } => StudentT::new(df)
+                .ok()
+                .filter(|_| scale.is_finite() && scale > 0.0)
+                .map(|distribution| Self::StudentT(distribution, scale)),
+        };
+        shock.unwrap_or_else(|| {
+            warn!(model = ?config.jitter_model, "invalid jitter model; producing baseline rates");
+            Self::None
+        })
+    }
+
+    fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    fn draw(&self, rng: &mut StdRng) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Uniform(rate_jitter) => rng.random_range(-rate_jitter..*rate_jitter),
+            Self::Normal(distribution) => distribution.sample(rng),
+            Self::StudentT(distribution, scale) => scale * distribution.sample(rng),
+        }
+    }
+}
+
+/// Replay updates from a [`super::ProducerSource::JsonLines`] feed instead of generating them.
+pub(super) fn start_from_reader(
+    update_sender: UpdateSender,
+    reader: UpdateReader,
+    stop: StopSignal,
+) -> JoinHandle<ProducerOutcome> {
+    tokio::spawn(reader_producer_task(update_sender, reader, stop))
+}
+
+/// One line of a JSON-lines update feed.
+#[derive(Debug, Deserialize)]
+struct JsonLineUpdate {
+    edge_index: usize,
+    new_rate: f64,
+}
+
+#[instrument(name = "pipeline_reader_producer", level = "debug", skip_all)]
+async fn reader_producer_task(
+    update_sender: UpdateSender,
+    reader: UpdateReader,
+    stop: StopSignal,
+) -> ProducerOutcome {
+    let mut source = LineUpdateSource::new(reader);
+    let backpressure_events = forward_updates(&update_sender, &mut source, stop).await;
+    ProducerOutcome {
+        malformed_lines: source.malformed_lines,
+        backpressure_events,
+        ..ProducerOutcome::default()
+    }
+}
+
+/// Rate updates parsed from a JSON-lines feed. Blank lines are ignored and malformed ones
+/// counted and skipped; the feed ends at EOF or on the first read error.
+struct LineUpdateSource {
+    lines: Lines<UpdateReader>,
+    line_number: usize,
+    malformed_lines: usize,
+}
+
+impl LineUpdateSource {
+    fn new(reader: UpdateReader) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_number: 0,
+            malformed_lines: 0,
+        }
+    }
+}
+
+impl UpdateSource for LineUpdateSource {
+    async fn next(&mut self) -> Option<GraphUpdate> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(line) => line?,
+                Err(err) => {
+                    let line_number = self.line_number;
+                    warn!(line_number, error = %err, "update feed read failed; stopping");
+                    return None;
+                }
             };
-            let new_rate = bounds.clamp(base_rate * (1.0 + jitter));
+            self.line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
 
-            if update_sender
-                .send(GraphUpdate::Rate {
+            match serde_json::from_str(&line) {
+                Ok(JsonLineUpdate {
                     edge_index,
                     new_rate,
-                })
-                .await
-                .is_err()
-            {
-                warn!("writer dropped before producer finished sending updates");
-                return;
+                }) => {
+                    return Some(GraphUpdate::Rate {
+                        edge_index,
+                        new_rate,
+                        seq: 0,
+                    })
+                }
+                Err(err) => {
+                    self.malformed_lines += 1;
+                    let line_number = self.line_number;
+                    warn!(line_number, error = %err, "skipped malformed update line");
+                }
             }
         }
+    }
+}
+
+/// Lazily created per-edge RNGs for [`JitterSeeding::PerEdge`].
+struct EdgeJitterStreams {
+    global_seed: u64,
+    streams: Vec<Option<StdRng>>,
+}
+
+impl EdgeJitterStreams {
+    fn new(global_seed: u64, edge_count: usize) -> Self {
+        Self {
+            global_seed,
+            streams: (0..edge_count).map(|_| None).collect(),
+        }
+    }
+
+    fn rng_for(&mut self, edge_index: usize) -> &mut StdRng {
+        let global_seed = self.global_seed;
+        self.streams[edge_index].get_or_insert_with(|| {
+            // Spread consecutive edge indices across the seed space before mixing.
+            let offset = (edge_index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            StdRng::seed_from_u64(global_seed ^ offset)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::{mpsc, watch};
+
+    #[tokio::test]
+    async fn producer_skips_non_finite_rates() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let config = PipelineConfig {
+            max_updates: 32,
+            search_interval: Duration::from_millis(1),
+            ..PipelineConfig::default()
+        };
+
+        let (_stop_tx, stop) = watch::channel(false);
+        let outcome = producer_task(tx.into(), vec![f64::NAN, 1.0], config, stop).await;
+
+        let mut sent = 0;
+        while let Ok(GraphUpdate::Rate { new_rate, .. }) = rx.try_recv() {
+            assert!(new_rate.is_finite() && new_rate > 0.0);
+            sent += 1;
+        }
+        assert!(outcome.sanitized_rates > 0, "NaN baseline should be hit");
+        assert_eq!(sent + outcome.sanitized_rates, 32);
+    }
 
-        remaining -= burst;
-        if remaining == 0 {
-            break;
+    #[tokio::test]
+    async fn reader_producer_emits_parsed_lines_and_skips_malformed() {
+        let feed = concat!(
+            "{\"edge_index\":0,\"new_rate\":1.5}\n",
+            "not json\n",
+            "\n",
+            "{\"edge_index\":2,\"new_rate\":0.25}\n",
+            "{\"edge_index\":1}\n",
+            "{\"edge_index\":1,\"new_rate\":3.0}",
+        );
+        let (tx, mut rx) = mpsc::channel(16);
+        let (_stop_tx, stop) = watch::channel(false);
+        let reader: UpdateReader = Box::new(std::io::Cursor::new(feed.as_bytes().to_vec()));
+        let outcome = reader_producer_task(tx.into(), reader, stop).await;
+
+        let mut received = Vec::new();
+        while let Ok(GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            ..
+        }) = rx.try_recv()
+        {
+            received.push((edge_index, new_rate));
         }
+        assert_eq!(received, vec![(0, 1.5), (2, 0.25), (1, 3.0)]);
+        assert_eq!(outcome.malformed_lines, 2);
+    }
+
+    #[tokio::test]
+    async fn reader_producer_stops_while_blocked_on_a_full_channel() {
+        let feed = "{\"edge_index\":0,\"new_rate\":1.5}\n".repeat(4);
+        let (tx, _rx) = mpsc::channel(1);
+        let (stop_tx, stop) = watch::channel(false);
+        let reader: UpdateReader = Box::new(std::io::Cursor::new(feed.into_bytes()));
+        let producer = tokio::spawn(reader_producer_task(tx.into(), reader, stop));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!producer.is_finished(), "second send waits for room");
+        stop_tx.send_replace(true);
+        let outcome = tokio::time::timeout(Duration::from_secs(1), producer)
+            .await
+            .expect("stop unblocks the pending send")
+            .expect("producer task");
+        assert_eq!(outcome.backpressure_events, 1);
+    }
 
-        let max_delay_ms = (config.search_interval.as_millis().max(1) as u64).saturating_mul(2);
-        let sleep_ms = rng.random_range(0..=max_delay_ms);
-        if sleep_ms > 0 {
-            tokio::time::sleep(Duration::from_millis(sleep_ms)).await;
+    async fn replay_seeded(seed: u64, baseline_rates: &[f64]) -> (usize, Vec<f64>) {
+        let (tx, mut rx) = mpsc::channel(256);
+        let config = PipelineConfig {
+            max_updates: 128,
+            search_interval: Duration::from_millis(1),
+            rng_seed: Some(seed),
+            ..PipelineConfig::default()
+        };
+        let (_stop_tx, stop) = watch::channel(false);
+        producer_task(tx.into(), baseline_rates.to_vec(), config, stop).await;
+
+        let mut rates = baseline_rates.to_vec();
+        let mut received = 0;
+        while let Ok(GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            ..
+        }) = rx.try_recv()
+        {
+            rates[edge_index] = new_rate;
+            received += 1;
         }
+        (received, rates)
+    }
+
+    #[tokio::test]
+    async fn seeded_producer_replays_identical_updates() {
+        let baseline = [1.0, 2.0, 0.5, 1.25];
+
+        let (first_count, first_rates) = replay_seeded(7, &baseline).await;
+        let (second_count, second_rates) = replay_seeded(7, &baseline).await;
+        let (_, other_rates) = replay_seeded(8, &baseline).await;
+
+        assert_eq!(first_count, 128);
+        assert_eq!(first_count, second_count);
+        assert_eq!(first_rates, second_rates);
+        assert_ne!(first_rates, other_rates);
+    }
+
+    async fn edge_rates_with(config: PipelineConfig, edge: usize) -> Vec<f64> {
+        let (tx, mut rx) = mpsc::channel(1024);
+        let (_stop_tx, stop) = watch::channel(false);
+        producer_task(tx.into(), vec![1.0, 2.0, 0.5], config, stop).await;
+
+        let mut rates = Vec::new();
+        while let Ok(GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            ..
+        }) = rx.try_recv()
+        {
+            if edge_index == edge {
+                rates.push(new_rate);
+            }
+        }
+        rates
+    }
+
+    #[tokio::test]
+    async fn per_edge_seeding_keeps_edge_series_stable_across_max_updates() {
+        let config = |max_updates| PipelineConfig {
+            max_updates,
+            search_interval: Duration::from_millis(1),
+            rng_seed: Some(11),
+            jitter_seeding: JitterSeeding::PerEdge,
+            ..PipelineConfig::default()
+        };
+
+        let short = edge_rates_with(config(40), 1).await;
+        let long = edge_rates_with(config(200), 1).await;
+
+        assert!(!short.is_empty());
+        assert!(long.len() > short.len());
+        assert_eq!(short[..], long[..short.len()]);
+    }
+
+    #[tokio::test]
+    async fn normal_jitter_is_seeded_and_clamped() {
+        let config = PipelineConfig {
+            max_updates: 200,
+            search_interval: Duration::from_millis(1),
+            rng_seed: Some(3),
+            jitter_model: JitterModel::Normal { std: 0.4 },
+            min_rate_bound: 0.8,
+            max_rate_bound: 1.5,
+            ..PipelineConfig::default()
+        };
+        config.validate().expect("valid normal jitter");
+
+        let first = edge_rates_with(config.clone(), 0).await;
+        let second = edge_rates_with(config, 0).await;
+
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+        assert!(first.iter().all(|rate| (0.8..=1.5).contains(rate)));
+        // A 0.4 std on a 1.0 baseline pushes some draws past each bound.
+        assert!(first.contains(&0.8) && first.contains(&1.5));
     }
 }
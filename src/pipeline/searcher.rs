@@ -1,21 +1,49 @@
+#[cfg(feature = "metrics")]
+use super::telemetry;
 use super::{
-    config::PipelineConfig,
-    types::{SearchOutcome, SharedGraph},
+    config::{PipelineConfig, SearchTrigger},
+    types::{CycleSink, DirtyEdges, SearchOutcome, SearcherControls, SharedGraph, TimedCycle},
 };
-use crate::cycle_finder::{find_profitable_cycle_with_graph, Cycle};
+use crate::{
+    csr_graph::{CSRGraph, WeightedGraph},
+    cycle_finder::{
+        canonical_edge_ring, find_cycle_through, find_profitable_cycle_starting_with, sweep_order,
+        Cycle,
+    },
+};
+use std::{collections::VecDeque, time::Instant as StdInstant};
 use tokio::{
-    sync::oneshot,
+    sync::{oneshot, watch},
     task::JoinHandle,
-    time::{self, MissedTickBehavior},
+    time::{self, Instant, MissedTickBehavior},
 };
-use tracing::{info, instrument};
+use tracing::{debug, info, instrument};
 
 pub(super) fn start(
     shared_graph: SharedGraph,
     shutdown: oneshot::Receiver<()>,
+    dirty_edges: Option<DirtyEdges>,
+    controls: SearcherControls,
     config: PipelineConfig,
+    started_at: Instant,
+    stop: watch::Sender<bool>,
 ) -> JoinHandle<SearchOutcome> {
-    tokio::spawn(searcher_task(shared_graph, shutdown, config))
+    tokio::spawn(searcher_task(
+        shared_graph,
+        shutdown,
+        dirty_edges,
+        controls,
+        config,
+        started_at,
+        stop,
+    ))
+}
+
+/// Why the searcher is about to run a pass.
+enum Pass {
+    Tick,
+    Triggered,
+    Shutdown,
 }
 
 #[instrument(
@@ -27,81 +55,443 @@ pub(super) fn start(
 async fn searcher_task(
     shared_graph: SharedGraph,
     mut shutdown: oneshot::Receiver<()>,
+    dirty_edges: Option<DirtyEdges>,
+    controls: SearcherControls,
     config: PipelineConfig,
+    started_at: Instant,
+    stop: watch::Sender<bool>,
 ) -> SearchOutcome {
+    let SearcherControls {
+        mut triggers,
+        paused,
+    } = controls;
+    let periodic = config.search_trigger == SearchTrigger::Interval;
     let mut interval = time::interval(config.search_interval);
     interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
     let mut outcome = SearchOutcome::default();
+    let mut emitter = CycleEmitter::new(config.cycle_sink.clone());
+    let mut incremental = config.incremental_search.then(IncrementalSearch::default);
 
     loop {
-        tokio::select! {
-            _ = interval.tick() => {
-                let shared_graph = {
-                    let shared_graph = shared_graph.read();
-                    if shared_graph.edge_count() == 0 {
-                        None
-                    } else {
-                        // clone to release read lock
-                        Some(shared_graph.clone())
-                    }
-                };
-
-
-                if let Some(shared_graph) = shared_graph {
-                    if let Some(cycle) = find_profitable_cycle_with_graph(&shared_graph, config.hop_cap) {
-                        let Cycle {
-                            ref vertices,
-                            ref edge_indexes,
-                            profit,
-                            neg_log_sum,
-                        } = cycle;
-                        info!(
-                            vertices = ?vertices,
-                            edge_indexes = ?edge_indexes,
-                            profit,
-                            neg_log_sum,
-                            "profitable cycle detected"
-                        );
-                        outcome.last_cycle = Some(cycle);
-                    }
-                    outcome.searches_run += 1;
+        // Triggers queued before shutdown are all served first so every one gets its pass.
+        let pass = tokio::select! {
+            biased;
+            Some(()) = triggers.recv() => Pass::Triggered,
+            _shutdown_request = &mut shutdown => Pass::Shutdown,
+            _ = interval.tick(), if periodic => Pass::Tick,
+        };
+        let shutting_down = matches!(pass, Pass::Shutdown);
+        if !shutting_down && *paused.borrow() {
+            debug!("searcher paused; skipping pass");
+            emitter.flush_if_due();
+            continue;
+        }
+        if matches!(pass, Pass::Triggered) {
+            debug!("search triggered");
+        }
+
+        if let Some(shared_graph) = clone_searchable(&shared_graph) {
+            let search_started = StdInstant::now();
+            let found = search(
+                &shared_graph,
+                &config,
+                dirty_edges.as_ref(),
+                incremental.as_mut(),
+            );
+            let search_time = search_started.elapsed();
+            outcome.total_search_time += search_time;
+            outcome.max_search_time = outcome.max_search_time.max(search_time);
+            #[cfg(feature = "metrics")]
+            telemetry::search_completed(search_time, found.as_ref());
+            if let Some(cycle) = found {
+                let Cycle {
+                    ref vertices,
+                    ref edge_indexes,
+                    profit,
+                    neg_log_sum,
+                    ..
+                } = cycle;
+                if shutting_down {
+                    info!(
+                        vertices = ?vertices,
+                        edge_indexes = ?edge_indexes,
+                        profit,
+                        neg_log_sum,
+                        "profitable cycle detected during shutdown check"
+                    );
+                } else {
+                    info!(
+                        vertices = ?vertices,
+                        edge_indexes = ?edge_indexes,
+                        profit,
+                        neg_log_sum,
+                        "profitable cycle detected"
+                    );
+                }
+                notify_cycle(&config, &mut emitter, &cycle, started_at);
+                remember_cycle(&mut outcome.recent_cycles, &cycle, config.cycle_history_len);
+                count_hop_length(&mut outcome.hop_length_histogram, cycle.edge_indexes.len());
+                outcome.last_cycle = Some(cycle);
+                if !shutting_down && config.stop_on_first_cycle && !stop.send_replace(true) {
+                    info!("stopping pipeline after first profitable cycle");
                 }
             }
-            _shutdown_request = &mut shutdown => {
-                let shared_graph = {
-                    let shared_graph = shared_graph.read();
-                    if shared_graph.edge_count() == 0 {
-                        None
-                    } else {
-                        // clone to release read lock
-                        Some(shared_graph.clone())
-                    }
-                };
-
-                if let Some(shared_graph) = shared_graph {
-                    if let Some(cycle) = find_profitable_cycle_with_graph(&shared_graph, config.hop_cap) {
-                        let Cycle {
-                            ref vertices,
-                            ref edge_indexes,
-                            profit,
-                            neg_log_sum,
-                        } = cycle;
-                        info!(
-                            vertices = ?vertices,
-                            edge_indexes = ?edge_indexes,
-                            profit,
-                            neg_log_sum,
-                            "profitable cycle detected during shutdown check"
-                        );
-                        outcome.last_cycle = Some(cycle);
+            outcome.searches_run += 1;
+        } else if shutting_down {
+            // Every run records its shutdown pass, even when there was nothing left to search.
+            debug!("no live edges; recording an empty shutdown pass");
+            outcome.searches_run += 1;
+        }
+
+        if shutting_down {
+            emitter.flush();
+            break;
+        }
+        emitter.flush_if_due();
+
+        #[cfg(feature = "chaos")]
+        if !config.artificial_search_delay.is_zero() {
+            time::sleep(config.artificial_search_delay).await;
+        }
+    }
+
+    outcome
+}
+
+/// One-off search of a graph you maintain yourself, without the producer/writer/searcher
+/// pipeline: the read lock is held only to clone the graph, then one pass of the searcher task
+/// runs under `config` (its `hop_cap`, `min_profit_ratio` and `gas_filter`). A graph with no
+/// live edges yields `None`. The search itself runs on the calling thread; move large graphs to
+/// `spawn_blocking`.
+pub fn search_once(shared_graph: &SharedGraph, config: &PipelineConfig) -> Option<Cycle> {
+    let graph = clone_searchable(shared_graph)?;
+    search(&graph, config, None, None)
+}
+
+/// Copy of the shared graph, so searching does not hold the read lock; `None` when it has no
+/// live edges and there is nothing to search.
+fn clone_searchable(shared_graph: &SharedGraph) -> Option<CSRGraph> {
+    let shared_graph = shared_graph.read();
+    (shared_graph.edge_count() > 0).then(|| shared_graph.clone())
+}
+
+/// One search pass; with `recent_first_search`, nodes behind the most recently updated edges
+/// are swept first, and with `incremental_search` the pass builds on the previous one.
+fn search(
+    graph: &CSRGraph,
+    config: &PipelineConfig,
+    dirty_edges: Option<&DirtyEdges>,
+    incremental: Option<&mut IncrementalSearch>,
+) -> Option<Cycle> {
+    let touched = dirty_edges
+        .map(|dirty_edges| dirty_edges.lock().drain())
+        .unwrap_or_default();
+    let cycle = match incremental {
+        Some(incremental) => incremental.search(graph, config, &touched),
+        None => full_search(graph, config, &touched),
+    }?;
+    if let Some(gas) = config.gas_filter {
+        let net_profit = cycle.net_profit(gas.reference_size, gas.cost_per_hop, graph);
+        if net_profit <= 0.0 {
+            debug!(
+                net_profit,
+                vertices = ?cycle.vertices,
+                "cycle unprofitable after gas; not reporting"
+            );
+            return None;
+        }
+    }
+    Some(cycle)
+}
+
+fn full_search(graph: &CSRGraph, config: &PipelineConfig, touched: &[usize]) -> Option<Cycle> {
+    find_profitable_cycle_starting_with(
+        graph,
+        config.hop_cap,
+        config.min_profit_ratio.unwrap_or(1.0),
+        &priority_nodes(graph, config, touched),
+    )
+}
+
+/// With `recent_first_search`, the source nodes of the touched edges, newest first.
+fn priority_nodes(graph: &CSRGraph, config: &PipelineConfig, touched: &[usize]) -> Vec<usize> {
+    if config.recent_first_search {
+        touched
+            .iter()
+            .rev()
+            .map(|&edge_index| graph.edge_src(edge_index))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Searcher state for [`PipelineConfig::incremental_search`]. A sweep runs the exact-hop DP
+/// from each start node in turn and reports the first cycle found; the DP from `start` only
+/// ever reads edges leaving nodes it reaches within `hop_cap - 1` hops. So each start node's
+/// outcome is kept between passes and recomputed only when a touched edge leaves a node that
+/// close to it (found by walking incoming edges back from the touched edges' sources). The
+/// reported cycle is therefore exactly the one [`full_search`] would report.
+#[derive(Default)]
+struct IncrementalSearch {
+    /// Per start node, the DP outcome from the last pass that ran it; `None` when stale.
+    by_start: Vec<Option<Option<Cycle>>>,
+    /// Source nodes of each node's incoming edges.
+    incoming: Vec<Vec<usize>>,
+    /// Shape the cache was built for; a graph with other edges starts over cold.
+    live_edges: usize,
+    edge_index_bound: usize,
+}
+
+impl IncrementalSearch {
+    fn search(
+        &mut self,
+        graph: &CSRGraph,
+        config: &PipelineConfig,
+        touched: &[usize],
+    ) -> Option<Cycle> {
+        let n = graph.node_count();
+        if self.by_start.len() != n
+            || self.live_edges != graph.edge_count()
+            || self.edge_index_bound != graph.edge_index_bound()
+        {
+            self.reset(graph);
+        } else {
+            self.invalidate_reaching(touched.iter().map(|&ei| graph.edge_src(ei)), config);
+        }
+
+        let min_profit_ratio = config.min_profit_ratio.unwrap_or(1.0);
+        let mut recomputed = 0usize;
+        let found = sweep_order(n, &priority_nodes(graph, config, touched))
+            .into_iter()
+            .find_map(|start| {
+                self.by_start[start]
+                    .get_or_insert_with(|| {
+                        recomputed += 1;
+                        find_cycle_through(graph, start, config.hop_cap, min_profit_ratio)
+                    })
+                    .clone()
+            });
+        debug!(recomputed, "incremental search pass");
+        found
+    }
+
+    fn reset(&mut self, graph: &CSRGraph) {
+        let n = graph.node_count();
+        self.by_start = vec![None; n];
+        self.incoming = vec![Vec::new(); n];
+        for from_node in 0..n {
+            graph.for_each_neighbor(from_node, |_, to_node, _| {
+                self.incoming[to_node].push(from_node);
+            });
+        }
+        self.live_edges = graph.edge_count();
+        self.edge_index_bound = graph.edge_index_bound();
+    }
+
+    /// Forget the outcome of every start node within `hop_cap - 1` hops of a changed edge's
+    /// source, i.e. every start whose DP could read that edge.
+    fn invalidate_reaching(
+        &mut self,
+        changed_sources: impl Iterator<Item = usize>,
+        config: &PipelineConfig,
+    ) {
+        let mut frontier: Vec<usize> = changed_sources.collect();
+        let mut reached = vec![false; self.by_start.len()];
+        for &node in &frontier {
+            reached[node] = true;
+        }
+        for _ in 1..config.hop_cap {
+            let mut next = Vec::new();
+            for node in frontier {
+                for &from_node in &self.incoming[node] {
+                    if !std::mem::replace(&mut reached[from_node], true) {
+                        next.push(from_node);
                     }
-                    outcome.searches_run += 1;
                 }
-                break;
+            }
+            frontier = next;
+        }
+        for (cached, reached) in self.by_start.iter_mut().zip(reached) {
+            if reached {
+                *cached = None;
             }
         }
     }
+}
 
-    outcome
+/// Hand a found cycle to the configured callback and sink.
+fn notify_cycle(
+    config: &PipelineConfig,
+    emitter: &mut CycleEmitter,
+    cycle: &Cycle,
+    started_at: Instant,
+) {
+    if let Some(on_cycle) = config.on_cycle.as_ref() {
+        on_cycle.call(cycle);
+    }
+    emitter.emit(TimedCycle {
+        cycle: cycle.clone(),
+        found_at: started_at.elapsed(),
+    });
+}
+
+/// Append `cycle` to the bounded history unless it is the newest entry's route again (the
+/// same edge ring, whatever the rotation), evicting the oldest entry once `capacity` is
+/// reached.
+fn remember_cycle(history: &mut VecDeque<Cycle>, cycle: &Cycle, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+    if history
+        .back()
+        .is_some_and(|newest| canonical_edge_ring(newest) == canonical_edge_ring(cycle))
+    {
+        return;
+    }
+    if history.len() >= capacity {
+        history.pop_front();
+    }
+    history.push_back(cycle.clone());
+}
+
+/// Bump the histogram bucket for a cycle of `hops` edges, growing it as needed.
+fn count_hop_length(histogram: &mut Vec<usize>, hops: usize) {
+    if histogram.len() <= hops {
+        histogram.resize(hops + 1, 0);
+    }
+    histogram[hops] += 1;
+}
+
+/// Delivers cycles to the [`CycleSink`], buffering them in batched mode. A dropped receiver is
+/// not an error.
+struct CycleEmitter {
+    sink: Option<CycleSink>,
+    pending: Vec<TimedCycle>,
+    oldest_pending_at: Option<Instant>,
+}
+
+impl CycleEmitter {
+    fn new(sink: Option<CycleSink>) -> Self {
+        Self {
+            sink,
+            pending: Vec::new(),
+            oldest_pending_at: None,
+        }
+    }
+
+    fn emit(&mut self, timed: TimedCycle) {
+        let max_cycles = match self.sink {
+            None => return,
+            Some(CycleSink::PerCycle(ref sender)) => {
+                if sender.send(timed).is_err() {
+                    debug!("cycle sink receiver dropped; discarding cycle");
+                }
+                return;
+            }
+            Some(CycleSink::Batched { max_cycles, .. }) => max_cycles,
+        };
+
+        self.oldest_pending_at.get_or_insert_with(Instant::now);
+        self.pending.push(timed);
+        if self.pending.len() >= max_cycles.max(1) {
+            self.flush();
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if let (Some(CycleSink::Batched { max_delay, .. }), Some(oldest)) =
+            (&self.sink, self.oldest_pending_at)
+        {
+            if oldest.elapsed() >= *max_delay {
+                self.flush();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        self.oldest_pending_at = None;
+        if self.pending.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut self.pending);
+        if let Some(CycleSink::Batched { ref sender, .. }) = self.sink {
+            if sender.send(batch).is_err() {
+                debug!("cycle sink receiver dropped; discarding cycle batch");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::generate_random;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn incremental_search_matches_full_search_on_competing_cycles() {
+        let dataset = generate_random(10, 40, 11, 0.9..1.04);
+        let edges = dataset
+            .edges
+            .iter()
+            .map(|edge| (edge.from as usize, edge.to as usize, edge.rate))
+            .collect();
+        let mut graph = CSRGraph::from_edges(10, edges);
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for recent_first_search in [false, true] {
+            let config = PipelineConfig {
+                hop_cap: 4,
+                recent_first_search,
+                ..PipelineConfig::default()
+            };
+            let mut incremental = IncrementalSearch::default();
+            let mut distinct_routes = std::collections::HashSet::new();
+            for pass in 0..200 {
+                let touched: Vec<usize> = (0..rng.random_range(0..3))
+                    .map(|_| rng.random_range(0..graph.edge_index_bound()))
+                    .collect();
+                for &edge_index in &touched {
+                    let rate = rng.random_range(0.9..1.04);
+                    graph.update_rate(edge_index, rate).unwrap();
+                }
+
+                let expected = full_search(&graph, &config, &touched);
+                assert_eq!(
+                    incremental.search(&graph, &config, &touched),
+                    expected,
+                    "pass {pass} diverged (recent_first_search = {recent_first_search})"
+                );
+                if let Some(cycle) = expected {
+                    distinct_routes.insert(cycle.edge_indexes);
+                }
+            }
+            assert!(distinct_routes.len() > 1, "several cycles should compete");
+        }
+    }
+
+    #[test]
+    fn history_skips_a_rotation_of_the_newest_route() {
+        let graph = CSRGraph::from_edges(
+            3,
+            vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98), (1, 0, 0.80)],
+        );
+        let from_zero = find_cycle_through(&graph, 0, 3, 1.0).expect("cycle through 0");
+        let from_two = find_cycle_through(&graph, 2, 3, 1.0).expect("cycle through 2");
+        assert_ne!(from_zero.edge_indexes, from_two.edge_indexes);
+
+        let mut history = VecDeque::new();
+        remember_cycle(&mut history, &from_zero, 4);
+        remember_cycle(&mut history, &from_two, 4);
+        assert_eq!(history.len(), 1);
+
+        let reversed_order = Cycle {
+            edge_indexes: vec![0, 2, 1],
+            ..from_zero.clone()
+        };
+        remember_cycle(&mut history, &reversed_order, 4);
+        assert_eq!(history.len(), 2);
+    }
 }
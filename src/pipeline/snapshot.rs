@@ -0,0 +1,126 @@
+use super::types::SharedGraph;
+use crate::{
+    dataset::{Dataset, Token},
+    error::DatasetError,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::oneshot,
+    task::JoinHandle,
+    time::{self, MissedTickBehavior},
+};
+use tracing::{debug, instrument, warn};
+
+/// Snapshots are written as datasets (live edges plus the run's tokens), so each file loads
+/// back with [`Dataset::load_from_path`].
+pub(super) fn start(
+    shared_graph: SharedGraph,
+    tokens: Vec<Token>,
+    shutdown: oneshot::Receiver<()>,
+    interval: Duration,
+    dir: PathBuf,
+) -> JoinHandle<usize> {
+    tokio::spawn(snapshot_task(shared_graph, tokens, shutdown, interval, dir))
+}
+
+#[instrument(
+    name = "pipeline_snapshot",
+    level = "debug",
+    skip_all,
+    fields(snapshot_interval_ms = interval.as_millis(), dir = %dir.display())
+)]
+async fn snapshot_task(
+    shared_graph: SharedGraph,
+    tokens: Vec<Token>,
+    mut shutdown: oneshot::Receiver<()>,
+    interval: Duration,
+    dir: PathBuf,
+) -> usize {
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut written = 0usize;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let tokens = tokens.clone();
+                let snapshot = Dataset::from_graph(&shared_graph.read(), tokens);
+
+                let dir = dir.clone();
+                match tokio::task::spawn_blocking(move || write_snapshot(&dir, &snapshot)).await {
+                    Ok(Ok(path)) => {
+                        written += 1;
+                        debug!(path = %path.display(), "graph snapshot written");
+                    }
+                    Ok(Err(err)) => warn!(error = %err, "failed to write graph snapshot"),
+                    Err(err) => warn!(error = %err, "graph snapshot task failed"),
+                }
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    written
+}
+
+fn write_snapshot(dir: &Path, snapshot: &Dataset) -> Result<PathBuf, DatasetError> {
+    std::fs::create_dir_all(dir).map_err(|source| DatasetError::Write {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros();
+    let path = dir.join(format!("graph-{stamp}.json"));
+    snapshot.save_to_path(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{csr_graph::CSRGraph, dataset::Edge};
+
+    #[test]
+    fn snapshot_loads_back_into_the_same_graph() {
+        let dataset = Dataset {
+            tokens: (0..3)
+                .map(|id| Token {
+                    id,
+                    symbol: format!("T{id}"),
+                })
+                .collect(),
+            edges: [(0, 1, 1.1), (1, 2, 0.95), (2, 0, 1.02)]
+                .into_iter()
+                .enumerate()
+                .map(|(id, (from, to, rate))| Edge {
+                    id: id as u64,
+                    from,
+                    to,
+                    rate,
+                    pool_id: 7,
+                    kind: 0,
+                })
+                .collect(),
+        };
+        let (mut graph, _) = CSRGraph::from_dataset(&dataset).unwrap();
+        graph.update_rate(1, 1.25).unwrap();
+
+        let dir = tempfile::tempdir().expect("temp dir");
+        let snapshot = Dataset::from_graph(&graph, dataset.tokens.clone());
+        let path = write_snapshot(&dir.path().join("snapshots"), &snapshot).unwrap();
+
+        let loaded = Dataset::load_from_path(&path).expect("snapshot loads as a dataset");
+        assert_eq!(loaded.tokens, dataset.tokens);
+        let (reloaded, _) = CSRGraph::from_dataset(&loaded).unwrap();
+        assert_eq!(reloaded.node_count(), graph.node_count());
+        for edge_index in 0..graph.edge_count() {
+            assert_eq!(reloaded.edge(edge_index), graph.edge(edge_index));
+            assert_eq!(reloaded.edge_pool(edge_index), 7);
+        }
+    }
+}
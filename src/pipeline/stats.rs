@@ -1,11 +1,383 @@
 use crate::cycle_finder::Cycle;
+use std::time::Duration;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PipelineStats {
     pub updates_processed: usize,
     pub unique_updates_applied: usize,
+    /// Search passes run. The final shutdown pass always counts, even when the graph had no
+    /// live edges left to search.
     pub searches_run: usize,
     pub last_cycle: Option<Cycle>,
+    /// Token symbols along `last_cycle.vertices`, when every vertex maps to a dataset token.
+    pub last_cycle_symbols: Option<Vec<String>>,
+    /// Distinct profitable cycles found during the run, oldest to newest, capped at
+    /// [`super::PipelineConfig::cycle_history_len`]. Consecutive repeats are recorded once.
+    pub recent_cycles: Vec<Cycle>,
+    /// `hop_length_histogram[h]` counts the profitable cycles of `h` hops found over the run,
+    /// one per search pass that found one (repeats included, unlike `recent_cycles`). Sized to
+    /// the longest cycle found; empty when none was.
+    pub hop_length_histogram: Vec<usize>,
+    /// Wall-clock time spent in search passes (the graph copy excluded), summed over the run.
+    /// Only passes over a graph with live edges are timed, so a final shutdown pass over an
+    /// emptied graph counts towards `searches_run` but adds nothing here.
+    pub total_search_time: Duration,
+    /// Slowest single search pass.
+    pub max_search_time: Duration,
     pub invalid_index_updates: usize,
     pub invalid_rate_updates: usize,
+    pub invalid_fee_updates: usize,
+    /// Sequenced updates dropped as older than one already applied to the same edge.
+    pub stale_updates: usize,
+    /// Jittered rates the producer dropped because they came out non-finite.
+    pub producer_sanitized: usize,
+    /// Lines a [`super::ProducerSource::JsonLines`] feed skipped because they did not parse.
+    pub malformed_update_lines: usize,
+    /// Coalesced update batches the writer pulled off the channel.
+    pub batches_formed: usize,
+    /// Largest coalesced batch the writer saw.
+    pub max_batch_size: usize,
+    /// Updates across all batches; divide by `batches_formed` for the mean batch size.
+    pub total_batched: usize,
+    /// Dataset edges collapsed at startup by [`super::PipelineConfig::dedup_edges`].
+    pub duplicate_edges_removed: usize,
+    /// Producer sends that found the update channel full and had to wait for the writer. A
+    /// high count relative to `updates_processed` suggests raising `channel_capacity`.
+    pub backpressure_events: usize,
+    /// Updates superseded or evicted before reaching the writer under
+    /// [`super::ChannelMode::DropOldest`]; always 0 with a bounded channel.
+    pub dropped_updates: usize,
+    /// Set when the producer task panicked. The run still drains and reports what the writer
+    /// and searcher did, but the producer's own counters are lost (left at 0).
+    pub producer_failed: bool,
+    /// Set when [`super::run_with_timeout`] hit its deadline and cut the run short.
+    pub timed_out: bool,
+}
+
+impl PipelineStats {
+    /// Compact JSON of every field, cycles included.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Field-by-field change from `baseline` to `self`, for comparing two runs. The cycle
+    /// lists and the hop histogram are left out; compare those directly.
+    pub fn delta(&self, baseline: &PipelineStats) -> StatsDelta {
+        // Destructure so a newly added field fails to compile here until it is diffed or
+        // explicitly skipped.
+        let PipelineStats {
+            updates_processed,
+            unique_updates_applied,
+            searches_run,
+            last_cycle,
+            last_cycle_symbols: _,
+            recent_cycles: _,
+            hop_length_histogram: _,
+            total_search_time,
+            max_search_time,
+            invalid_index_updates,
+            invalid_rate_updates,
+            invalid_fee_updates,
+            stale_updates,
+            producer_sanitized,
+            malformed_update_lines,
+            batches_formed,
+            max_batch_size,
+            total_batched,
+            duplicate_edges_removed,
+            backpressure_events,
+            dropped_updates,
+            producer_failed,
+            timed_out,
+        } = self;
+        StatsDelta {
+            updates_processed: CounterDelta::between(
+                baseline.updates_processed,
+                *updates_processed,
+            ),
+            unique_updates_applied: CounterDelta::between(
+                baseline.unique_updates_applied,
+                *unique_updates_applied,
+            ),
+            searches_run: CounterDelta::between(baseline.searches_run, *searches_run),
+            total_search_time: DurationDelta::between(
+                baseline.total_search_time,
+                *total_search_time,
+            ),
+            max_search_time: DurationDelta::between(baseline.max_search_time, *max_search_time),
+            invalid_index_updates: CounterDelta::between(
+                baseline.invalid_index_updates,
+                *invalid_index_updates,
+            ),
+            invalid_rate_updates: CounterDelta::between(
+                baseline.invalid_rate_updates,
+                *invalid_rate_updates,
+            ),
+            invalid_fee_updates: CounterDelta::between(
+                baseline.invalid_fee_updates,
+                *invalid_fee_updates,
+            ),
+            stale_updates: CounterDelta::between(baseline.stale_updates, *stale_updates),
+            producer_sanitized: CounterDelta::between(
+                baseline.producer_sanitized,
+                *producer_sanitized,
+            ),
+            malformed_update_lines: CounterDelta::between(
+                baseline.malformed_update_lines,
+                *malformed_update_lines,
+            ),
+            batches_formed: CounterDelta::between(baseline.batches_formed, *batches_formed),
+            max_batch_size: CounterDelta::between(baseline.max_batch_size, *max_batch_size),
+            total_batched: CounterDelta::between(baseline.total_batched, *total_batched),
+            duplicate_edges_removed: CounterDelta::between(
+                baseline.duplicate_edges_removed,
+                *duplicate_edges_removed,
+            ),
+            backpressure_events: CounterDelta::between(
+                baseline.backpressure_events,
+                *backpressure_events,
+            ),
+            dropped_updates: CounterDelta::between(baseline.dropped_updates, *dropped_updates),
+            last_cycle: CyclePresence::between(baseline.last_cycle.is_some(), last_cycle.is_some()),
+            producer_failed: FlagDelta::between(baseline.producer_failed, *producer_failed),
+            timed_out: FlagDelta::between(baseline.timed_out, *timed_out),
+        }
+    }
+}
+
+/// Result of [`PipelineStats::delta`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsDelta {
+    pub updates_processed: CounterDelta,
+    pub unique_updates_applied: CounterDelta,
+    pub searches_run: CounterDelta,
+    pub total_search_time: DurationDelta,
+    pub max_search_time: DurationDelta,
+    pub invalid_index_updates: CounterDelta,
+    pub invalid_rate_updates: CounterDelta,
+    pub invalid_fee_updates: CounterDelta,
+    pub stale_updates: CounterDelta,
+    pub producer_sanitized: CounterDelta,
+    pub malformed_update_lines: CounterDelta,
+    pub batches_formed: CounterDelta,
+    pub max_batch_size: CounterDelta,
+    pub total_batched: CounterDelta,
+    pub duplicate_edges_removed: CounterDelta,
+    pub backpressure_events: CounterDelta,
+    pub dropped_updates: CounterDelta,
+    pub last_cycle: CyclePresence,
+    pub producer_failed: FlagDelta,
+    pub timed_out: FlagDelta,
+}
+
+/// Signed change of one counter between two runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterDelta {
+    /// `current - baseline`.
+    pub absolute: i64,
+    /// `absolute / baseline`; `None` when the baseline counter is zero.
+    pub relative: Option<f64>,
+}
+
+impl CounterDelta {
+    fn between(baseline: usize, current: usize) -> Self {
+        let absolute = current as i64 - baseline as i64;
+        let relative = (baseline != 0).then(|| absolute as f64 / baseline as f64);
+        Self { absolute, relative }
+    }
+}
+
+/// Signed change of one duration between two runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationDelta {
+    /// `current - baseline`, in seconds.
+    pub absolute_secs: f64,
+    /// `absolute_secs / baseline`; `None` when the baseline duration is zero.
+    pub relative: Option<f64>,
+}
+
+impl DurationDelta {
+    fn between(baseline: Duration, current: Duration) -> Self {
+        let absolute_secs = current.as_secs_f64() - baseline.as_secs_f64();
+        let relative = (!baseline.is_zero()).then(|| absolute_secs / baseline.as_secs_f64());
+        Self {
+            absolute_secs,
+            relative,
+        }
+    }
+}
+
+/// How a run flag such as [`PipelineStats::timed_out`] compares between two runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagDelta {
+    BothSet,
+    BothClear,
+    /// Only the current run set the flag.
+    Set,
+    /// Only the baseline run set the flag.
+    Cleared,
+}
+
+impl FlagDelta {
+    fn between(baseline: bool, current: bool) -> Self {
+        match (baseline, current) {
+            (true, true) => Self::BothSet,
+            (false, false) => Self::BothClear,
+            (false, true) => Self::Set,
+            (true, false) => Self::Cleared,
+        }
+    }
+}
+
+/// Whether a profitable cycle was recorded in each of the two runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CyclePresence {
+    BothFound,
+    BothMissing,
+    /// Only the current run recorded a cycle.
+    Appeared,
+    /// Only the baseline run recorded a cycle.
+    Disappeared,
+}
+
+impl CyclePresence {
+    fn between(baseline: bool, current: bool) -> Self {
+        match (baseline, current) {
+            (true, true) => Self::BothFound,
+            (false, false) => Self::BothMissing,
+            (false, true) => Self::Appeared,
+            (true, false) => Self::Disappeared,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle() -> Cycle {
+        Cycle {
+            vertices: vec![0, 1, 0],
+            edge_indexes: vec![0, 1],
+            rates: vec![1.1, 1.0],
+            profit: 1.1,
+            neg_log_sum: -(1.1f64.ln()),
+        }
+    }
+
+    #[test]
+    fn delta_reports_signed_and_relative_changes() {
+        let baseline = PipelineStats {
+            updates_processed: 100,
+            unique_updates_applied: 80,
+            searches_run: 4,
+            last_cycle: Some(cycle()),
+            last_cycle_symbols: None,
+            recent_cycles: Vec::new(),
+            hop_length_histogram: Vec::new(),
+            total_search_time: Duration::from_secs(2),
+            max_search_time: Duration::from_secs(1),
+            backpressure_events: 0,
+            dropped_updates: 0,
+            producer_failed: true,
+            timed_out: false,
+            invalid_index_updates: 0,
+            invalid_rate_updates: 10,
+            invalid_fee_updates: 2,
+            stale_updates: 0,
+            producer_sanitized: 5,
+            malformed_update_lines: 0,
+            batches_formed: 0,
+            max_batch_size: 0,
+            total_batched: 0,
+            duplicate_edges_removed: 0,
+        };
+        let current = PipelineStats {
+            updates_processed: 150,
+            unique_updates_applied: 60,
+            searches_run: 4,
+            last_cycle: None,
+            last_cycle_symbols: None,
+            recent_cycles: Vec::new(),
+            hop_length_histogram: Vec::new(),
+            total_search_time: Duration::from_secs(3),
+            max_search_time: Duration::from_secs(1),
+            backpressure_events: 0,
+            dropped_updates: 0,
+            producer_failed: false,
+            timed_out: true,
+            invalid_index_updates: 3,
+            invalid_rate_updates: 5,
+            invalid_fee_updates: 2,
+            stale_updates: 0,
+            producer_sanitized: 0,
+            malformed_update_lines: 0,
+            batches_formed: 0,
+            max_batch_size: 0,
+            total_batched: 0,
+            duplicate_edges_removed: 2,
+        };
+
+        let delta = current.delta(&baseline);
+        let counter = |absolute, relative| CounterDelta { absolute, relative };
+        assert_eq!(delta.updates_processed, counter(50, Some(0.5)));
+        assert_eq!(delta.unique_updates_applied, counter(-20, Some(-0.25)));
+        assert_eq!(delta.searches_run, counter(0, Some(0.0)));
+        assert_eq!(delta.invalid_index_updates, counter(3, None));
+        assert_eq!(delta.invalid_rate_updates, counter(-5, Some(-0.5)));
+        assert_eq!(delta.invalid_fee_updates, counter(0, Some(0.0)));
+        assert_eq!(delta.stale_updates, counter(0, None));
+        assert_eq!(delta.producer_sanitized, counter(-5, Some(-1.0)));
+        assert_eq!(delta.duplicate_edges_removed, counter(2, None));
+        assert_eq!(
+            delta.total_search_time,
+            DurationDelta {
+                absolute_secs: 1.0,
+                relative: Some(0.5)
+            }
+        );
+        assert_eq!(
+            delta.max_search_time,
+            DurationDelta {
+                absolute_secs: 0.0,
+                relative: Some(0.0)
+            }
+        );
+        assert_eq!(delta.producer_failed, FlagDelta::Cleared);
+        assert_eq!(delta.timed_out, FlagDelta::Set);
+        assert_eq!(baseline.delta(&baseline).timed_out, FlagDelta::BothClear);
+        assert_eq!(delta.last_cycle, CyclePresence::Disappeared);
+        assert_eq!(baseline.delta(&current).last_cycle, CyclePresence::Appeared);
+        assert_eq!(
+            baseline.delta(&baseline).last_cycle,
+            CyclePresence::BothFound
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stats_round_trip_through_json() {
+        let stats = PipelineStats {
+            updates_processed: 12,
+            searches_run: 3,
+            last_cycle: Some(cycle()),
+            last_cycle_symbols: Some(vec!["A".into(), "B".into(), "A".into()]),
+            recent_cycles: vec![cycle()],
+            ..PipelineStats::default()
+        };
+
+        let json = stats.to_json().expect("stats serialize");
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["updates_processed"], 12);
+        let last_cycle = &value["last_cycle"];
+        for key in ["vertices", "edge_indexes", "rates", "profit", "neg_log_sum"] {
+            assert!(last_cycle.get(key).is_some(), "missing {key} in {json}");
+        }
+        assert_eq!(last_cycle["vertices"], serde_json::json!([0, 1, 0]));
+
+        let restored: PipelineStats = serde_json::from_str(&json).expect("stats deserialize");
+        assert_eq!(restored, stats);
+    }
 }
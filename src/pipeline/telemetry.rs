@@ -0,0 +1,159 @@
+//! Metrics the pipeline reports through the [`metrics`] facade when built with the `metrics`
+//! feature. Nothing is collected until the embedding service installs a recorder, e.g. a
+//! Prometheus exporter.
+
+use crate::cycle_finder::Cycle;
+use std::time::Duration;
+
+/// Counter of updates that passed validation.
+pub const UPDATES_PROCESSED: &str = "optimizer_updates_processed_total";
+/// Counter of updates dropped by validation, labelled `reason` = `index` / `rate` / `fee`.
+pub const UPDATES_INVALID: &str = "optimizer_updates_invalid_total";
+/// Counter of sequenced updates dropped as stale.
+pub const UPDATES_STALE: &str = "optimizer_updates_stale_total";
+/// Histogram of search pass latency in seconds.
+pub const SEARCH_DURATION_SECONDS: &str = "optimizer_search_duration_seconds";
+/// Gauge holding the rate product of the most recently found profitable cycle.
+pub const LAST_CYCLE_PROFIT: &str = "optimizer_last_cycle_profit";
+
+pub(super) fn updates_processed(count: usize) {
+    metrics::counter!(UPDATES_PROCESSED).increment(count as u64);
+}
+
+pub(super) fn update_invalid(reason: &'static str) {
+    metrics::counter!(UPDATES_INVALID, "reason" => reason).increment(1);
+}
+
+pub(super) fn update_stale() {
+    metrics::counter!(UPDATES_STALE).increment(1);
+}
+
+pub(super) fn search_completed(elapsed: Duration, cycle: Option<&Cycle>) {
+    metrics::histogram!(SEARCH_DURATION_SECONDS).record(elapsed.as_secs_f64());
+    if let Some(cycle) = cycle {
+        metrics::gauge!(LAST_CYCLE_PROFIT).set(cycle.profit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{run, GraphUpdate, PipelineConfig, ProducerSource, UpdateSource};
+    use crate::{
+        csr_graph::CSRGraph,
+        dataset::{Dataset, Token},
+    };
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use parking_lot::Mutex;
+    use std::{collections::BTreeSet, sync::Arc};
+
+    /// Records every metric the pipeline registers, as `name` or `name{label=value}`.
+    #[derive(Clone, Default)]
+    struct NameRecorder(Arc<Mutex<BTreeSet<String>>>);
+
+    impl NameRecorder {
+        fn seen(&self, key: &Key) {
+            let mut seen = self.0.lock();
+            seen.insert(key.name().to_owned());
+            for label in key.labels() {
+                seen.insert(format!(
+                    "{}{{{}={}}}",
+                    key.name(),
+                    label.key(),
+                    label.value()
+                ));
+            }
+        }
+    }
+
+    impl Recorder for NameRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.seen(key);
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.seen(key);
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.seen(key);
+            Histogram::noop()
+        }
+    }
+
+    struct VecSource(std::vec::IntoIter<GraphUpdate>);
+
+    impl UpdateSource for VecSource {
+        async fn next(&mut self) -> Option<GraphUpdate> {
+            self.0.next()
+        }
+    }
+
+    #[test]
+    fn pipeline_emits_expected_metric_names() {
+        let graph = CSRGraph::from_edges(3, vec![(0, 1, 1.10), (1, 2, 1.05), (2, 0, 0.98)]);
+        let tokens = ["A", "B", "C"]
+            .iter()
+            .zip(0u64..)
+            .map(|(symbol, id)| Token {
+                id,
+                symbol: (*symbol).into(),
+            })
+            .collect();
+        let rate = |edge_index, new_rate, seq| GraphUpdate::Rate {
+            edge_index,
+            new_rate,
+            seq,
+        };
+        let updates = vec![
+            rate(0, 1.10, 2),
+            rate(0, 1.20, 1),
+            rate(9, 1.10, 0),
+            rate(1, -1.0, 0),
+            GraphUpdate::Fee {
+                edge_index: 2,
+                fee_bps: -5.0,
+            },
+        ];
+        let config = PipelineConfig {
+            producer_source: ProducerSource::custom(VecSource(updates.into_iter())),
+            search_interval: Duration::from_millis(5),
+            coalesce_window: Duration::from_millis(1),
+            ..PipelineConfig::default()
+        };
+
+        // A current-thread runtime keeps every pipeline task on this thread, where the local
+        // recorder is installed.
+        let recorder = NameRecorder::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime builds");
+        metrics::with_local_recorder(&recorder, || {
+            runtime
+                .block_on(run(Dataset::from_graph(&graph, tokens), config))
+                .expect("pipeline runs")
+        });
+
+        let seen = recorder.0.lock().clone();
+        for name in [
+            UPDATES_PROCESSED,
+            UPDATES_STALE,
+            &format!("{UPDATES_INVALID}{{reason=index}}"),
+            &format!("{UPDATES_INVALID}{{reason=rate}}"),
+            &format!("{UPDATES_INVALID}{{reason=fee}}"),
+            SEARCH_DURATION_SECONDS,
+            LAST_CYCLE_PROFIT,
+        ] {
+            assert!(seen.contains(name), "missing {name}; saw {seen:?}");
+        }
+    }
+}
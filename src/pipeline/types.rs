@@ -1,31 +1,405 @@
 use crate::{csr_graph::CSRGraph, cycle_finder::Cycle};
-use parking_lot::RwLock;
-use std::sync::Arc;
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::VecDeque, fmt, future::Future, ops::AddAssign, pin::Pin, sync::Arc, time::Duration,
+};
+use tokio::{
+    sync::{mpsc, watch},
+    task::AbortHandle,
+};
 
+/// A single mutation the writer applies to the shared graph.
 #[derive(Debug, Clone, Copy)]
-pub(super) enum GraphUpdate {
-    Rate { edge_index: usize, new_rate: f64 },
-    // TODO: Additional graph mutations (insert/remove edges, fee updates, etc.) can slot in here later.
+pub enum GraphUpdate {
+    /// `seq` orders updates per edge for feeds that may reorder them: anything at or below the
+    /// last applied sequence for the edge is dropped as stale. `0` means unsequenced.
+    Rate {
+        edge_index: usize,
+        new_rate: f64,
+        seq: u64,
+    },
+    Fee {
+        edge_index: usize,
+        fee_bps: f64,
+    },
+    /// Relative rate change: the writer sets `current_rate * (1.0 + pct_change)`, reading the
+    /// current rate under the write lock, so `0.003` is a +0.3% move.
+    RateDelta {
+        edge_index: usize,
+        pct_change: f64,
+    },
+    // TODO: Additional graph mutations (insert/remove edges, etc.) can slot in here later.
 }
 
-#[derive(Debug, Default)]
-pub(super) struct WriterOutcome {
+impl GraphUpdate {
+    #[inline]
+    pub fn edge_index(&self) -> usize {
+        match *self {
+            GraphUpdate::Rate { edge_index, .. }
+            | GraphUpdate::Fee { edge_index, .. }
+            | GraphUpdate::RateDelta { edge_index, .. } => edge_index,
+        }
+    }
+}
+
+/// Pluggable stream of updates feeding the writer; install one with
+/// [`super::ProducerSource::custom`]. Implementations can simply write `async fn next`.
+///
+/// The pipeline stops pulling once `next` returns `None` or the run is cancelled, in which
+/// case a pending `next` future is dropped.
+pub trait UpdateSource: Send {
+    fn next(&mut self) -> impl Future<Output = Option<GraphUpdate>> + Send;
+}
+
+/// Object-safe shim over [`UpdateSource`], whose `next` future is not nameable.
+trait ErasedUpdateSource: Send {
+    fn next_boxed(&mut self) -> Pin<Box<dyn Future<Output = Option<GraphUpdate>> + Send + '_>>;
+}
+
+impl<S: UpdateSource> ErasedUpdateSource for S {
+    fn next_boxed(&mut self) -> Pin<Box<dyn Future<Output = Option<GraphUpdate>> + Send + '_>> {
+        Box::pin(self.next())
+    }
+}
+
+/// Type-erased [`UpdateSource`], as held by [`super::ProducerSource::Custom`].
+pub struct BoxedUpdateSource(Box<dyn ErasedUpdateSource>);
+
+impl BoxedUpdateSource {
+    pub fn new(source: impl UpdateSource + 'static) -> Self {
+        Self(Box::new(source))
+    }
+}
+
+impl UpdateSource for BoxedUpdateSource {
+    fn next(&mut self) -> impl Future<Output = Option<GraphUpdate>> + Send {
+        self.0.next_boxed()
+    }
+}
+
+impl fmt::Debug for BoxedUpdateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoxedUpdateSource(..)")
+    }
+}
+
+/// Counters reported by a writer task once its update stream closes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WriterOutcome {
     pub processed_updates: usize,
     pub unique_updates_applied: usize,
     pub invalid_index_updates: usize,
     pub invalid_rate_updates: usize,
+    pub invalid_fee_updates: usize,
+    /// Sequenced updates dropped because a fresher one for the same edge was already applied.
+    pub stale_updates: usize,
+    /// Coalesced batches pulled off the channel, including ones later discarded.
+    pub batches_formed: usize,
+    /// Largest coalesced batch seen; never exceeds `max_coalesce`.
+    pub max_batch_size: usize,
+    /// Updates across all batches, so `total_batched / batches_formed` is the mean batch size.
+    pub total_batched: usize,
+}
+
+impl WriterOutcome {
+    /// Fold `other` into `self`, summing every counter except `max_batch_size`, which keeps the
+    /// larger peak (used to aggregate several writers).
+    pub fn merge(&mut self, other: WriterOutcome) {
+        // Destructure so a newly added counter fails to compile here until it is merged.
+        let WriterOutcome {
+            processed_updates,
+            unique_updates_applied,
+            invalid_index_updates,
+            invalid_rate_updates,
+            invalid_fee_updates,
+            stale_updates,
+            batches_formed,
+            max_batch_size,
+            total_batched,
+        } = other;
+        self.processed_updates += processed_updates;
+        self.unique_updates_applied += unique_updates_applied;
+        self.invalid_index_updates += invalid_index_updates;
+        self.invalid_rate_updates += invalid_rate_updates;
+        self.invalid_fee_updates += invalid_fee_updates;
+        self.stale_updates += stale_updates;
+        self.batches_formed += batches_formed;
+        self.max_batch_size = self.max_batch_size.max(max_batch_size);
+        self.total_batched += total_batched;
+    }
+}
+
+impl AddAssign for WriterOutcome {
+    fn add_assign(&mut self, other: WriterOutcome) {
+        self.merge(other);
+    }
+}
+
+impl FromIterator<WriterOutcome> for WriterOutcome {
+    fn from_iter<I: IntoIterator<Item = WriterOutcome>>(outcomes: I) -> Self {
+        let mut total = WriterOutcome::default();
+        for outcome in outcomes {
+            total.merge(outcome);
+        }
+        total
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ProducerOutcome {
+    pub sanitized_rates: usize,
+    pub malformed_lines: usize,
+    /// Sends that found the channel full and waited for capacity.
+    pub backpressure_events: usize,
 }
 
 #[derive(Debug, Default)]
 pub(super) struct SearchOutcome {
     pub searches_run: usize,
     pub last_cycle: Option<Cycle>,
+    /// Oldest first, at most `cycle_history_len` entries.
+    pub recent_cycles: VecDeque<Cycle>,
+    /// Index = hop count, value = cycles found at that length.
+    pub hop_length_histogram: Vec<usize>,
+    pub total_search_time: Duration,
+    pub max_search_time: Duration,
+}
+
+/// A profitable cycle tagged with when the searcher found it, relative to the start of `run`.
+#[derive(Debug, Clone)]
+pub struct TimedCycle {
+    pub cycle: Cycle,
+    pub found_at: Duration,
+}
+
+/// Channel the searcher pushes detected cycles into, when configured.
+#[derive(Debug, Clone)]
+pub enum CycleSink {
+    /// One message per cycle.
+    PerCycle(mpsc::UnboundedSender<TimedCycle>),
+    /// Accumulate cycles and send them together once `max_cycles` are pending or `max_delay`
+    /// has passed since the oldest pending one (checked every search tick and on shutdown).
+    Batched {
+        sender: mpsc::UnboundedSender<Vec<TimedCycle>>,
+        max_cycles: usize,
+        max_delay: Duration,
+    },
+}
+
+/// Hook the searcher calls synchronously for every profitable cycle (never under the graph lock).
+#[derive(Clone)]
+pub struct CycleCallback(Arc<dyn Fn(&Cycle) + Send + Sync>);
+
+impl CycleCallback {
+    pub fn new(callback: impl Fn(&Cycle) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    #[inline]
+    pub(super) fn call(&self, cycle: &Cycle) {
+        (self.0)(cycle)
+    }
+}
+
+impl fmt::Debug for CycleCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CycleCallback(..)")
+    }
 }
 
 #[derive(Debug)]
 pub(super) enum UpdateValidationError {
     IndexOutOfBounds(usize),
     InvalidRate(f64),
+    InvalidRateDelta(f64),
+    InvalidFee(f64),
+}
+
+/// The graph as the writer and searcher share it (a `parking_lot` lock).
+pub type SharedGraph = Arc<RwLock<CSRGraph>>;
+
+/// Pause or resume the searcher's scheduled passes via
+/// [`super::PipelineHandle::control_searcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearcherControl {
+    /// Skip interval ticks and triggers (no graph clone, no search) until resumed. The final
+    /// shutdown pass still runs.
+    Pause,
+    Resume,
+}
+
+/// Inputs the searcher listens on besides shutdown.
+pub(super) struct SearcherControls {
+    pub triggers: mpsc::UnboundedReceiver<()>,
+    pub paused: watch::Receiver<bool>,
 }
 
-pub(super) type SharedGraph = Arc<RwLock<CSRGraph>>;
+/// One message per requested search pass.
+pub(super) type SearchTriggerSender = mpsc::UnboundedSender<()>;
+
+/// Edge indices the writer touched since the searcher last drained them.
+pub(super) type DirtyEdges = Arc<Mutex<TouchedEdges>>;
+
+const UNLINKED: usize = usize::MAX;
+
+/// Ordered set of touched edge indices, oldest touch first. Touching an edge already in the set
+/// moves it to the back, so it holds at most one entry per edge however long the searcher takes
+/// to drain it. Backed by a doubly linked list over per-edge slots: touches are O(1) and a drain
+/// costs only what it returns.
+#[derive(Debug)]
+pub(super) struct TouchedEdges {
+    previous: Vec<usize>,
+    next: Vec<usize>,
+    present: Vec<bool>,
+    head: usize,
+    tail: usize,
+}
+
+impl TouchedEdges {
+    /// An empty set with slots for edge indices below `edge_index_bound`; larger indices grow it.
+    pub fn with_bound(edge_index_bound: usize) -> Self {
+        Self {
+            previous: vec![UNLINKED; edge_index_bound],
+            next: vec![UNLINKED; edge_index_bound],
+            present: vec![false; edge_index_bound],
+            head: UNLINKED,
+            tail: UNLINKED,
+        }
+    }
+
+    /// Record a touch of `edge_index`, moving it to the back if it is already in the set.
+    pub fn touch(&mut self, edge_index: usize) {
+        if edge_index >= self.present.len() {
+            let bound = edge_index + 1;
+            self.previous.resize(bound, UNLINKED);
+            self.next.resize(bound, UNLINKED);
+            self.present.resize(bound, false);
+        }
+        if self.present[edge_index] {
+            if self.tail == edge_index {
+                return;
+            }
+            self.unlink(edge_index);
+        }
+        self.previous[edge_index] = self.tail;
+        self.next[edge_index] = UNLINKED;
+        if self.tail == UNLINKED {
+            self.head = edge_index;
+        } else {
+            self.next[self.tail] = edge_index;
+        }
+        self.tail = edge_index;
+        self.present[edge_index] = true;
+    }
+
+    /// Empty the set, returning its edge indices oldest touch first.
+    pub fn drain(&mut self) -> Vec<usize> {
+        let mut drained = Vec::new();
+        let mut cursor = self.head;
+        while cursor != UNLINKED {
+            drained.push(cursor);
+            self.present[cursor] = false;
+            cursor = self.next[cursor];
+        }
+        self.head = UNLINKED;
+        self.tail = UNLINKED;
+        drained
+    }
+
+    fn unlink(&mut self, edge_index: usize) {
+        let (previous, next) = (self.previous[edge_index], self.next[edge_index]);
+        if previous == UNLINKED {
+            self.head = next;
+        } else {
+            self.next[previous] = next;
+        }
+        if next == UNLINKED {
+            self.tail = previous;
+        } else {
+            self.previous[next] = previous;
+        }
+        self.present[edge_index] = false;
+    }
+}
+
+impl Extend<usize> for TouchedEdges {
+    fn extend<T: IntoIterator<Item = usize>>(&mut self, edge_indices: T) {
+        edge_indices
+            .into_iter()
+            .for_each(|edge_index| self.touch(edge_index));
+    }
+}
+
+/// Abort handles for the tasks a run spawns besides its driver, so a run that overruns its
+/// shutdown grace can be torn down completely.
+pub(super) type ChildTasks = Arc<Mutex<Vec<AbortHandle>>>;
+
+/// Internal early-stop flag: flipping it to `true` tells the producer to stop sending.
+pub(super) type StopSignal = watch::Receiver<bool>;
+
+/// Resolve once `stop` flips to `true`; never resolves if every sender is gone.
+pub(super) async fn stop_requested(stop: &mut StopSignal) {
+    if stop.wait_for(|stopped| *stopped).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touched_edges_keep_one_entry_per_edge_in_latest_touch_order() {
+        let mut touched = TouchedEdges::with_bound(4);
+        touched.extend([2, 0, 3, 2, 1, 0, 0]);
+        assert_eq!(touched.drain(), vec![3, 2, 1, 0]);
+        assert!(touched.drain().is_empty());
+
+        // Storm on one edge: the set stays within the edge count.
+        touched.extend(std::iter::repeat_n(1, 1_000).chain([3, 1, 6]));
+        assert_eq!(touched.drain(), vec![3, 1, 6]);
+    }
+
+    #[test]
+    fn merging_writer_outcomes_sums_every_counter() {
+        let mut first = WriterOutcome {
+            processed_updates: 10,
+            unique_updates_applied: 8,
+            invalid_index_updates: 1,
+            invalid_rate_updates: 2,
+            invalid_fee_updates: 0,
+            stale_updates: 2,
+            batches_formed: 3,
+            max_batch_size: 4,
+            total_batched: 9,
+        };
+        let second = WriterOutcome {
+            processed_updates: 5,
+            unique_updates_applied: 4,
+            invalid_index_updates: 3,
+            invalid_rate_updates: 7,
+            invalid_fee_updates: 1,
+            stale_updates: 0,
+            batches_formed: 2,
+            max_batch_size: 6,
+            total_batched: 5,
+        };
+        let expected = WriterOutcome {
+            processed_updates: 15,
+            unique_updates_applied: 12,
+            invalid_index_updates: 4,
+            invalid_rate_updates: 9,
+            invalid_fee_updates: 1,
+            stale_updates: 2,
+            batches_formed: 5,
+            max_batch_size: 6,
+            total_batched: 14,
+        };
+
+        let folded: WriterOutcome = vec![first.clone(), second.clone()].into_iter().collect();
+        assert_eq!(folded, expected);
+
+        first += second;
+        assert_eq!(first, expected);
+    }
+}
@@ -1,8 +1,14 @@
+#[cfg(feature = "metrics")]
+use super::telemetry;
 use super::{
     config::{PipelineConfig, RateBounds},
-    types::{GraphUpdate, SharedGraph, UpdateValidationError, WriterOutcome},
+    types::{
+        DirtyEdges, GraphUpdate, SearchTriggerSender, SharedGraph, UpdateValidationError,
+        WriterOutcome,
+    },
 };
-use std::time::Duration;
+use crate::{csr_graph::MAX_FEE_BPS, error::PipelineError};
+use std::{collections::HashSet, time::Duration};
 use tokio::{
     sync::mpsc,
     task::JoinHandle,
@@ -11,14 +17,21 @@ use tokio::{
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tracing::{debug, error, info, instrument, warn};
 
+/// Updates the writer must see before `max_invalid_fraction` is enforced.
+const INVALID_FRACTION_MIN_SAMPLE: usize = 16;
+
 pub(super) fn start(
     shared_edges: SharedGraph,
     receiver: mpsc::Receiver<GraphUpdate>,
+    dirty_edges: Option<DirtyEdges>,
+    search_trigger: Option<SearchTriggerSender>,
     config: PipelineConfig,
-) -> JoinHandle<WriterOutcome> {
+) -> JoinHandle<Result<WriterOutcome, PipelineError>> {
     tokio::spawn(writer_task(
         shared_edges,
         ReceiverStream::new(receiver),
+        dirty_edges,
+        search_trigger,
         config,
     ))
 }
@@ -35,10 +48,13 @@ pub(super) fn start(
 async fn writer_task(
     shared_edges: SharedGraph,
     mut update_stream: ReceiverStream<GraphUpdate>,
+    dirty_edges: Option<DirtyEdges>,
+    search_trigger: Option<SearchTriggerSender>,
     config: PipelineConfig,
-) -> WriterOutcome {
-    let edge_count = shared_edges.read().edge_count();
+) -> Result<WriterOutcome, PipelineError> {
+    let edge_index_bound = shared_edges.read().edge_index_bound();
     let mut outcome = WriterOutcome::default();
+    let mut last_seq_by_edge = vec![0u64; edge_index_bound];
 
     let max_coalesce = config.max_coalesce.max(1);
     let coalesce_window = config.coalesce_window;
@@ -47,41 +63,82 @@ async fn writer_task(
     while let Some(batch) = next_batch(&mut update_stream, max_coalesce, coalesce_window).await {
         let mut validated = Vec::with_capacity(batch.len());
         debug!(batch_size = batch.len(), "coalesced batch ready");
+        outcome.batches_formed += 1;
+        outcome.max_batch_size = outcome.max_batch_size.max(batch.len());
+        outcome.total_batched += batch.len();
         for update in batch {
-            match validate_update(update, edge_count) {
+            match validate_update(update, edge_index_bound) {
+                Ok(valid) if is_stale(&valid, &mut last_seq_by_edge) => {
+                    outcome.stale_updates += 1;
+                    #[cfg(feature = "metrics")]
+                    telemetry::update_stale();
+                    debug!(update = ?valid, "dropped stale sequenced update");
+                }
                 Ok(valid) => validated.push(valid),
                 Err(UpdateValidationError::IndexOutOfBounds(index)) => {
                     outcome.invalid_index_updates += 1;
+                    #[cfg(feature = "metrics")]
+                    telemetry::update_invalid("index");
                     warn!(index, "dropped update with out-of-bounds index");
                 }
                 Err(UpdateValidationError::InvalidRate(rate)) => {
                     outcome.invalid_rate_updates += 1;
+                    #[cfg(feature = "metrics")]
+                    telemetry::update_invalid("rate");
                     warn!(rate, "dropped update with invalid rate");
                 }
+                Err(UpdateValidationError::InvalidRateDelta(pct_change)) => {
+                    outcome.invalid_rate_updates += 1;
+                    #[cfg(feature = "metrics")]
+                    telemetry::update_invalid("rate");
+                    warn!(pct_change, "dropped rate delta with invalid change");
+                }
+                Err(UpdateValidationError::InvalidFee(fee_bps)) => {
+                    outcome.invalid_fee_updates += 1;
+                    #[cfg(feature = "metrics")]
+                    telemetry::update_invalid("fee");
+                    warn!(fee_bps, "dropped update with invalid fee");
+                }
             }
         }
 
+        outcome.processed_updates += validated.len();
+        #[cfg(feature = "metrics")]
+        telemetry::updates_processed(validated.len());
+        check_invalid_fraction(&outcome, config.max_invalid_fraction)?;
+
         if validated.is_empty() {
             error!("discarded batch: no valid updates after validation");
             continue;
         }
 
-        outcome.processed_updates += validated.len();
-
         let bounded_updates: Vec<GraphUpdate> = validated
             .into_iter()
             .map(|update| match update {
                 GraphUpdate::Rate {
                     edge_index,
                     new_rate,
+                    seq,
                 } => GraphUpdate::Rate {
                     edge_index,
                     new_rate: bounds.clamp(new_rate),
+                    seq,
                 },
+                // Deltas are clamped once resolved against the current rate.
+                other @ (GraphUpdate::Fee { .. } | GraphUpdate::RateDelta { .. }) => other,
             })
             .collect();
+        let bounded_updates = keep_last_write_per_edge(bounded_updates);
 
-        let applied = apply_valid_updates(&shared_edges, &bounded_updates);
+        let applied = if config.dry_run {
+            debug!(
+                batch_received = bounded_updates.len(),
+                "dry run; leaving graph untouched"
+            );
+            bounded_updates.len()
+        } else {
+            apply_valid_updates(&shared_edges, &bounded_updates, bounds)
+        };
         if applied == 0 {
             error!(
                 batch_received = bounded_updates.len(),
@@ -91,6 +148,16 @@ async fn writer_task(
         }
 
         outcome.unique_updates_applied += applied;
+        if let Some(dirty_edges) = dirty_edges.as_ref().filter(|_| !config.dry_run) {
+            dirty_edges
+                .lock()
+                .extend(bounded_updates.iter().map(GraphUpdate::edge_index));
+        }
+        if let Some(search_trigger) = search_trigger.as_ref() {
+            if search_trigger.send(()).is_err() {
+                debug!("searcher gone; dropping search trigger");
+            }
+        }
         info!(
             batch_received = bounded_updates.len(),
             unique_applied = applied,
@@ -100,11 +167,41 @@ async fn writer_task(
         );
     }
 
-    outcome
+    Ok(outcome)
+}
+
+/// Fail once enough updates were seen and the invalid share exceeds `max_invalid_fraction`.
+fn check_invalid_fraction(
+    outcome: &WriterOutcome,
+    max_invalid_fraction: Option<f64>,
+) -> Result<(), PipelineError> {
+    let Some(max_fraction) = max_invalid_fraction else {
+        return Ok(());
+    };
+    let invalid =
+        outcome.invalid_index_updates + outcome.invalid_rate_updates + outcome.invalid_fee_updates;
+    let seen = outcome.processed_updates + invalid;
+    if seen < INVALID_FRACTION_MIN_SAMPLE {
+        return Ok(());
+    }
+
+    let fraction = invalid as f64 / seen as f64;
+    if fraction > max_fraction {
+        error!(
+            fraction,
+            max_fraction, "too many invalid updates; aborting writer"
+        );
+        return Err(PipelineError::TooManyInvalidUpdates { fraction });
+    }
+    Ok(())
 }
 
 #[instrument(level = "trace", skip_all, fields(batch = updates.len()))]
-fn apply_valid_updates(shared_graph: &SharedGraph, updates: &[GraphUpdate]) -> usize {
+fn apply_valid_updates(
+    shared_graph: &SharedGraph,
+    updates: &[GraphUpdate],
+    bounds: RateBounds,
+) -> usize {
     if updates.is_empty() {
         return 0;
     }
@@ -115,7 +212,25 @@ fn apply_valid_updates(shared_graph: &SharedGraph, updates: &[GraphUpdate]) -> u
             GraphUpdate::Rate {
                 edge_index,
                 new_rate,
+                ..
+            } => {
+                graph
+                    .update_rate(edge_index, new_rate)
+                    .expect("validated update should succeed");
+            }
+            GraphUpdate::Fee {
+                edge_index,
+                fee_bps,
+            } => {
+                graph
+                    .update_fee(edge_index, fee_bps)
+                    .expect("validated update should succeed");
+            }
+            GraphUpdate::RateDelta {
+                edge_index,
+                pct_change,
             } => {
+                let new_rate = bounds.clamp(graph.edge_rate(edge_index) * (1.0 + pct_change));
                 graph
                     .update_rate(edge_index, new_rate)
                     .expect("validated update should succeed");
@@ -125,6 +240,25 @@ fn apply_valid_updates(shared_graph: &SharedGraph, updates: &[GraphUpdate]) -> u
     updates.len()
 }
 
+/// Collapse a batch so each edge gets at most one rate and one fee write: the last of each
+/// wins, kept at its original position. Earlier writes would be overwritten under the same lock
+/// anyway, so dropping them only saves work. Rate deltas compound, so every one is kept in
+/// place; those before the surviving absolute rate are harmlessly overwritten by it.
+fn keep_last_write_per_edge(updates: Vec<GraphUpdate>) -> Vec<GraphUpdate> {
+    let mut seen = HashSet::with_capacity(updates.len());
+    let mut kept: Vec<GraphUpdate> = updates
+        .into_iter()
+        .rev()
+        .filter(|update| match update {
+            GraphUpdate::RateDelta { .. } => true,
+            GraphUpdate::Fee { .. } => seen.insert((update.edge_index(), true)),
+            GraphUpdate::Rate { .. } => seen.insert((update.edge_index(), false)),
+        })
+        .collect();
+    kept.reverse();
+    kept
+}
+
 /// Coalescing helper (aka chunk timeout):
 /// - Always awaits the first item to respect backpressure.
 /// - Then drains up to `max_coalesce - 1` additional items until `coalesce_window` elapses.
@@ -143,12 +277,21 @@ where
             batch.push(first);
 
             if coalesce_window > Duration::ZERO && max_coalesce > 1 {
-                let deadline = Instant::now() + coalesce_window;
+                // A window too large to represent as a deadline just waits on the stream.
+                let deadline = Instant::now().checked_add(coalesce_window);
                 while batch.len() < max_coalesce {
-                    match timeout_at(deadline, stream.next()).await {
-                        Ok(Some(next)) => batch.push(next),
-                        Ok(None) => break,
-                        Err(_) => break,
+                    let next = match deadline {
+                        Some(deadline) => match timeout_at(deadline, stream.next()).await {
+                            Ok(next) => next,
+                            Err(_) => break,
+                        },
+                        None => stream.next().await,
+                    };
+                    // A closed stream (producer done) returns the partial batch immediately
+                    // instead of waiting out the window.
+                    match next {
+                        Some(next) => batch.push(next),
+                        None => break,
                     }
                 }
             }
@@ -159,16 +302,37 @@ where
     }
 }
 
+/// Track per-edge sequence numbers, reporting sequenced updates that are not newer than the last
+/// one accepted for their edge. Unsequenced (`seq == 0`) and fee updates are never stale.
+fn is_stale(update: &GraphUpdate, last_seq_by_edge: &mut [u64]) -> bool {
+    let GraphUpdate::Rate {
+        edge_index, seq, ..
+    } = *update
+    else {
+        return false;
+    };
+    if seq == 0 {
+        return false;
+    }
+    let last_seq = &mut last_seq_by_edge[edge_index];
+    if seq <= *last_seq {
+        return true;
+    }
+    *last_seq = seq;
+    false
+}
+
 fn validate_update(
     update: GraphUpdate,
-    edge_count: usize,
+    edge_index_bound: usize,
 ) -> Result<GraphUpdate, UpdateValidationError> {
     match update {
         GraphUpdate::Rate {
             edge_index,
             new_rate,
+            seq,
         } => {
-            if edge_index >= edge_count {
+            if edge_index >= edge_index_bound {
                 return Err(UpdateValidationError::IndexOutOfBounds(edge_index));
             }
             if new_rate <= 0.0 || !new_rate.is_finite() {
@@ -177,15 +341,47 @@ fn validate_update(
             Ok(GraphUpdate::Rate {
                 edge_index,
                 new_rate,
+                seq,
             })
         }
+        GraphUpdate::Fee {
+            edge_index,
+            fee_bps,
+        } => {
+            if edge_index >= edge_index_bound {
+                return Err(UpdateValidationError::IndexOutOfBounds(edge_index));
+            }
+            if !(0.0..=MAX_FEE_BPS).contains(&fee_bps) {
+                return Err(UpdateValidationError::InvalidFee(fee_bps));
+            }
+            Ok(GraphUpdate::Fee {
+                edge_index,
+                fee_bps,
+            })
+        }
+        GraphUpdate::RateDelta {
+            edge_index,
+            pct_change,
+        } => {
+            if edge_index >= edge_index_bound {
+                return Err(UpdateValidationError::IndexOutOfBounds(edge_index));
+            }
+            // Current rates are positive, so the result stays positive iff the factor does.
+            if !pct_change.is_finite() || 1.0 + pct_change <= 0.0 {
+                return Err(UpdateValidationError::InvalidRateDelta(pct_change));
+            }
+            Ok(update)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::csr_graph::CSRGraph;
+    use crate::{
+        csr_graph::{CSRGraph, WeightedGraph},
+        cycle_finder::find_profitable_cycle_with_graph,
+    };
     use parking_lot::RwLock;
     use std::sync::Arc;
     use tokio_stream::wrappers::ReceiverStream;
@@ -202,6 +398,7 @@ mod tests {
         tx.send(GraphUpdate::Rate {
             edge_index: 5,
             new_rate: 1.0,
+            seq: 0,
         })
         .await
         .unwrap();
@@ -209,6 +406,7 @@ mod tests {
         tx.send(GraphUpdate::Rate {
             edge_index: 0,
             new_rate: 0.0,
+            seq: 0,
         })
         .await
         .unwrap();
@@ -217,17 +415,342 @@ mod tests {
         let outcome = writer_task(
             Arc::clone(&shared),
             ReceiverStream::new(rx),
+            None,
+            None,
             PipelineConfig {
                 max_coalesce: 4,
                 coalesce_window: Duration::from_millis(1),
                 ..PipelineConfig::default()
             },
         )
-        .await;
+        .await
+        .expect("writer tolerates invalid updates by default");
 
         assert_eq!(outcome.processed_updates, 0);
         assert_eq!(outcome.invalid_index_updates, 1);
         assert_eq!(outcome.invalid_rate_updates, 1);
         assert_eq!(outcome.unique_updates_applied, 0);
     }
+
+    #[tokio::test]
+    async fn writer_aborts_when_invalid_fraction_exceeded() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 1.0)],
+        )));
+        let (tx, rx) = mpsc::channel(64);
+        for i in 0..40 {
+            let edge_index = if i % 4 == 0 { 0 } else { 9 };
+            tx.send(GraphUpdate::Rate {
+                edge_index,
+                new_rate: 1.01,
+                seq: 0,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let err = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 4,
+                coalesce_window: Duration::from_millis(1),
+                max_invalid_fraction: Some(0.5),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect_err("mostly invalid stream should abort the writer");
+
+        match err {
+            PipelineError::TooManyInvalidUpdates { fraction } => assert!(fraction > 0.5),
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_fees_count_towards_the_invalid_fraction() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 1.0)],
+        )));
+        let (tx, rx) = mpsc::channel(64);
+        for _ in 0..40 {
+            tx.send(GraphUpdate::Fee {
+                edge_index: 0,
+                fee_bps: -5.0,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let err = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 4,
+                coalesce_window: Duration::from_millis(1),
+                max_invalid_fraction: Some(0.5),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect_err("a stream of bad fees should abort the writer");
+
+        assert!(matches!(
+            err,
+            PipelineError::TooManyInvalidUpdates { fraction } if fraction == 1.0
+        ));
+    }
+
+    #[tokio::test]
+    async fn writer_reports_coalescing_effectiveness() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 1.0)],
+        )));
+        let (tx, rx) = mpsc::channel(32);
+        for i in 0..20 {
+            tx.send(GraphUpdate::Rate {
+                edge_index: 0,
+                new_rate: 1.0 + i as f64 / 100.0,
+                seq: 0,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let max_coalesce = 6;
+        let outcome = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce,
+                coalesce_window: Duration::from_millis(50),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect("writer succeeds");
+
+        assert!(outcome.batches_formed >= 1);
+        assert!(outcome.max_batch_size <= max_coalesce);
+        assert_eq!(outcome.max_batch_size, max_coalesce);
+        assert_eq!(outcome.total_batched, 20);
+    }
+
+    #[tokio::test]
+    async fn repeated_edge_in_one_batch_is_written_once() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 1.0)],
+        )));
+        let (tx, rx) = mpsc::channel(8);
+        for new_rate in [1.1, 1.2, 1.3] {
+            tx.send(GraphUpdate::Rate {
+                edge_index: 0,
+                new_rate,
+                seq: 0,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let outcome = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 8,
+                coalesce_window: Duration::from_millis(50),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect("writer succeeds");
+
+        assert_eq!(outcome.batches_formed, 1);
+        assert_eq!(outcome.processed_updates, 3);
+        assert_eq!(outcome.unique_updates_applied, 1);
+        assert_eq!(shared.read().edge_rate(0), 1.3);
+    }
+
+    #[tokio::test]
+    async fn closed_stream_short_circuits_long_coalesce_window() {
+        for coalesce_window in [Duration::from_secs(3600), Duration::MAX] {
+            let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+                2,
+                vec![(0usize, 1usize, 1.0)],
+            )));
+            let (tx, rx) = mpsc::channel(8);
+            for _ in 0..3 {
+                tx.send(GraphUpdate::Rate {
+                    edge_index: 0,
+                    new_rate: 1.01,
+                    seq: 0,
+                })
+                .await
+                .unwrap();
+            }
+            drop(tx);
+
+            let outcome = tokio::time::timeout(
+                Duration::from_secs(1),
+                writer_task(
+                    Arc::clone(&shared),
+                    ReceiverStream::new(rx),
+                    None,
+                    None,
+                    PipelineConfig {
+                        max_coalesce: 64,
+                        coalesce_window,
+                        ..PipelineConfig::default()
+                    },
+                ),
+            )
+            .await
+            .expect("writer should not wait out the coalesce window")
+            .expect("valid updates");
+
+            assert_eq!(outcome.processed_updates, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn fee_updates_shrink_cycle_profit() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            3,
+            vec![(0usize, 1usize, 1.02), (1, 2, 1.02), (2, 0, 0.98)],
+        )));
+        let profit_before = find_profitable_cycle_with_graph(&*shared.read(), 3)
+            .expect("cycle is profitable before fees")
+            .profit;
+
+        let (tx, rx) = mpsc::channel(4);
+        tx.send(GraphUpdate::Fee {
+            edge_index: 0,
+            fee_bps: 30.0,
+        })
+        .await
+        .unwrap();
+        tx.send(GraphUpdate::Fee {
+            edge_index: 1,
+            fee_bps: -1.0,
+        })
+        .await
+        .unwrap();
+        drop(tx);
+
+        let outcome = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 4,
+                coalesce_window: Duration::from_millis(1),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect("writer tolerates invalid updates by default");
+
+        assert_eq!(outcome.unique_updates_applied, 1);
+        assert_eq!(outcome.invalid_fee_updates, 1);
+        let graph = shared.read();
+        assert_eq!(graph.edge_fee_bps(0), 30.0);
+        let profit_after = find_profitable_cycle_with_graph(&*graph, 3)
+            .expect("still profitable after a 0.3% fee")
+            .profit;
+        assert!((profit_after - profit_before * 0.997).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn writer_drops_out_of_order_sequenced_updates() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 1.0)],
+        )));
+        let (tx, rx) = mpsc::channel(8);
+        for (new_rate, seq) in [(1.10, 2), (1.05, 1), (1.20, 3), (1.15, 3), (1.30, 0)] {
+            tx.send(GraphUpdate::Rate {
+                edge_index: 0,
+                new_rate,
+                seq,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let outcome = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 1,
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect("stale updates are not errors");
+
+        assert_eq!(outcome.stale_updates, 2);
+        assert_eq!(outcome.unique_updates_applied, 3);
+        // The unsequenced update still lands last, as it would without sequencing.
+        assert_eq!(shared.read().edge_rate(0), 1.30);
+    }
+
+    #[tokio::test]
+    async fn rate_delta_scales_current_rate() {
+        let shared = Arc::new(RwLock::new(CSRGraph::from_edges(
+            2,
+            vec![(0usize, 1usize, 2.0), (1, 0, 0.5)],
+        )));
+        let (tx, rx) = mpsc::channel(8);
+        for pct_change in [0.10, f64::NAN, -1.0] {
+            tx.send(GraphUpdate::RateDelta {
+                edge_index: 0,
+                pct_change,
+            })
+            .await
+            .unwrap();
+        }
+        drop(tx);
+
+        let outcome = writer_task(
+            Arc::clone(&shared),
+            ReceiverStream::new(rx),
+            None,
+            None,
+            PipelineConfig {
+                max_coalesce: 8,
+                coalesce_window: Duration::from_millis(1),
+                ..PipelineConfig::default()
+            },
+        )
+        .await
+        .expect("writer tolerates invalid deltas by default");
+
+        assert_eq!(outcome.unique_updates_applied, 1);
+        assert_eq!(outcome.invalid_rate_updates, 2);
+        let graph = shared.read();
+        let expected = 2.0 * 1.10;
+        assert!((graph.edge_rate(0) - expected).abs() < 1e-12);
+        assert!((graph.weight(0) + expected.ln()).abs() < 1e-12);
+        assert_eq!(graph.edge_rate(1), 0.5);
+    }
 }